@@ -0,0 +1,587 @@
+use std::io::Read;
+
+use crate::config::Config;
+use crate::convert::{self, Format};
+use crate::model::{
+    AnimationData, Diagnostic, Severity, TimeNotation, WriteOptions, render_changes,
+    render_changes_json, validate_document,
+};
+
+// exit codes shared by every subcommand
+const OK: i32 = 0;
+// the document itself has problems: errors, conflicts, not formatted
+const PROBLEMS: i32 = 1;
+// bad arguments or unreadable / unwritable files
+const FAILURE: i32 = 2;
+
+pub const USAGE: &str = "\
+usage: animated-text [<command>] [<args>]
+
+  (no command) [<config options>] open the editor on the drafts folders
+  edit <file> [<config options>]  open the editor on <file>
+  convert [--from <fmt>] --to <fmt> [<input>|-] [-o <output>]
+                                  formats: txt, json, ttml, lrc
+  validate <file> [--fix]         report problems, optionally fix them in place
+  fmt <file> [--write|--check] [--compact] [--clock]
+                                  rewrite the file in canonical form
+  info <file>                     summary of lines, timings, parts
+  shift <file> <seconds> [--lines <from>-<to>] [--write|-o <output>]
+                                  move timings, lines are 1-based and inclusive
+  serve [<config options>]        run the remote control server without the editor
+  merge <base> <ours> <theirs> [-o <output>]
+  diff <old> <new> [--json]
+  textconv <file>
+
+config options, over $XDG_CONFIG_HOME/animated-text/config.toml:
+  --config <file>  --bind <address>  --port <port>  --drafts <folder>...
+
+exit codes: 0 ok, 1 the document has problems, 2 usage or i/o errors";
+
+// splits `args` into positionals, switches and `--name value` options
+struct Args<'a> {
+    positional: Vec<&'a str>,
+    options: Vec<(&'a str, Option<&'a str>)>,
+}
+
+impl<'a> Args<'a> {
+    fn parse(args: &'a [String], switches: &[&str], valued: &[&str]) -> Result<Self, String> {
+        let mut parsed = Args {
+            positional: Vec::new(),
+            options: Vec::new(),
+        };
+        let mut iter = args.iter().map(String::as_str);
+        while let Some(arg) = iter.next() {
+            // "-" is stdin and "-1.5" a negative number, not options
+            let is_option = arg.len() > 1
+                && arg.starts_with('-')
+                && !arg[1..].starts_with(|c: char| c.is_ascii_digit() || c == '.');
+            if !is_option {
+                parsed.positional.push(arg);
+            } else if switches.contains(&arg) {
+                parsed.options.push((arg, None));
+            } else if valued.contains(&arg) {
+                let value = iter.next().ok_or(format!("{} needs a value", arg))?;
+                parsed.options.push((arg, Some(value)));
+            } else {
+                return Err(format!("unknown option {}", arg));
+            }
+        }
+        Ok(parsed)
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.options.iter().any(|(n, _)| *n == name)
+    }
+
+    fn value(&self, names: &[&str]) -> Option<&'a str> {
+        self.options
+            .iter()
+            .rev()
+            .find(|(n, _)| names.contains(n))
+            .and_then(|(_, v)| *v)
+    }
+
+    fn values(&self, names: &[&str]) -> Vec<&'a str> {
+        self.options
+            .iter()
+            .filter(|(n, _)| names.contains(n))
+            .filter_map(|(_, v)| *v)
+            .collect()
+    }
+
+    fn write_options(&self) -> WriteOptions {
+        WriteOptions {
+            compact: self.flag("--compact"),
+            notation: if self.flag("--clock") {
+                TimeNotation::Clock
+            } else {
+                TimeNotation::Seconds
+            },
+        }
+    }
+}
+
+fn usage_error(message: &str) -> i32 {
+    eprintln!("{}\n\n{}", message, USAGE);
+    FAILURE
+}
+
+fn read_document(path: &str) -> Option<AnimationData> {
+    convert::read_file(path)
+        .map_err(|e| eprintln!("{}", e))
+        .ok()
+}
+
+// writes to `output`, or stdout without one
+fn emit(output: Option<&str>, content: &str) -> i32 {
+    match output {
+        Some(path) => match convert::write_atomic(path, content, 0) {
+            Ok(()) => OK,
+            Err(e) => {
+                eprintln!("{}", e);
+                FAILURE
+            }
+        },
+        None => {
+            print!("{}", content);
+            OK
+        }
+    }
+}
+
+// every subcommand that doesn't need the terminal or the server
+pub fn run(args: &[String]) -> i32 {
+    let Some(command) = args.first() else {
+        return usage_error("missing command");
+    };
+    let rest = &args[1..];
+    match command.as_str() {
+        "convert" => convert(rest),
+        "validate" => validate(rest),
+        "fmt" => fmt(rest),
+        "info" => info(rest),
+        "shift" => shift(rest),
+        "merge" => merge(rest),
+        "diff" => diff(rest),
+        "textconv" => textconv(rest),
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            OK
+        }
+        other => usage_error(&format!("unknown command '{}'", other)),
+    }
+}
+
+// animated-text convert [--from <fmt>] --to <fmt> [<input>|-] [-o <output>]
+pub fn convert(args: &[String]) -> i32 {
+    let args = match Args::parse(
+        args,
+        &["--compact", "--clock"],
+        &["--from", "--to", "--output", "-o"],
+    ) {
+        Ok(args) => args,
+        Err(e) => return usage_error(&e),
+    };
+    let input_path = args.positional.first().copied().filter(|p| *p != "-");
+    let output = args.value(&["--output", "-o"]);
+
+    let parse_format = |name: Option<&str>, path: Option<&str>| match (name, path) {
+        (Some(name), _) => Format::from_name(name),
+        (None, Some(path)) => Ok(Format::from_path(path)),
+        (None, None) => Ok(Format::Text),
+    };
+    let (from, to) = match (
+        parse_format(args.value(&["--from"]), input_path),
+        parse_format(args.value(&["--to"]), output),
+    ) {
+        (Ok(from), Ok(to)) => (from, to),
+        (Err(e), _) | (_, Err(e)) => return usage_error(&e),
+    };
+
+    let input = match input_path {
+        Some(path) => std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e)),
+        None => {
+            let mut input = String::new();
+            std::io::stdin()
+                .read_to_string(&mut input)
+                .map(|_| input)
+                .map_err(|e| format!("stdin: {}", e))
+        }
+    };
+    let result = input
+        .and_then(|input| convert::read(&input, from))
+        .and_then(|data| convert::write(&data, to, &args.write_options()));
+    match result {
+        Ok(content) => emit(output, &content),
+        Err(e) => {
+            eprintln!("{}", e);
+            FAILURE
+        }
+    }
+}
+
+// animated-text validate <file> [--fix]
+// exit code 0: clean or warnings only, 1: errors, 2: unreadable input
+pub fn validate(args: &[String]) -> i32 {
+    let args = match Args::parse(args, &["--fix"], &[]) {
+        Ok(args) => args,
+        Err(e) => return usage_error(&e),
+    };
+    let Some(&path) = args.positional.first() else {
+        return usage_error("usage: animated-text validate <file> [--fix]");
+    };
+    let format = Format::from_path(path);
+
+    let (mut data, diagnostics) = if format == Format::Text {
+        // the native format also gets checked for what the parser skips over
+        let input = match std::fs::read_to_string(path) {
+            Ok(input) => input,
+            Err(e) => {
+                eprintln!("{}: {}", path, e);
+                return FAILURE;
+            }
+        };
+        match validate_document(&input) {
+            Ok(result) => result,
+            Err(e) => {
+                eprintln!("{}: {}", path, e);
+                return FAILURE;
+            }
+        }
+    } else {
+        let Some(data) = read_document(path) else {
+            return FAILURE;
+        };
+        let diagnostics = data.validate();
+        (data, diagnostics)
+    };
+
+    for d in &diagnostics {
+        println!("{}: {}", path, d);
+    }
+
+    if args.flag("--fix") {
+        let applied = data.apply_fixes(&diagnostics);
+        if applied > 0 {
+            let written = convert::write_file(path, &data, &WriteOptions::default(), 0);
+            if let Err(e) = written {
+                eprintln!("{}", e);
+                return FAILURE;
+            }
+            println!("{}: applied {} fixes", path, applied);
+        }
+        return exit_code(&data.validate());
+    }
+    exit_code(&diagnostics)
+}
+
+fn exit_code(diagnostics: &[Diagnostic]) -> i32 {
+    if diagnostics.iter().any(|d| d.severity == Severity::Error) {
+        PROBLEMS
+    } else {
+        OK
+    }
+}
+
+// animated-text fmt <file> [--write|--check] [--compact] [--clock]
+// prints the canonical form, rewrites the file with --write,
+// and with --check exits 1 when the file isn't in that form
+pub fn fmt(args: &[String]) -> i32 {
+    let args = match Args::parse(args, &["--write", "--check", "--compact", "--clock"], &[]) {
+        Ok(args) => args,
+        Err(e) => return usage_error(&e),
+    };
+    let Some(&path) = args.positional.first() else {
+        return usage_error("usage: animated-text fmt <file> [--write|--check]");
+    };
+    let input = match std::fs::read_to_string(path) {
+        Ok(input) => input,
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            return FAILURE;
+        }
+    };
+    let format = Format::from_path(path);
+    let formatted = match convert::read(&input, format)
+        .and_then(|data| convert::write(&data, format, &args.write_options()))
+    {
+        Ok(formatted) => formatted,
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            return FAILURE;
+        }
+    };
+
+    if args.flag("--check") {
+        if formatted == input {
+            OK
+        } else {
+            eprintln!("{}: not formatted", path);
+            PROBLEMS
+        }
+    } else if args.flag("--write") {
+        if formatted == input {
+            return OK;
+        }
+        emit(Some(path), &formatted)
+    } else {
+        emit(None, &formatted)
+    }
+}
+
+// animated-text info <file>
+pub fn info(args: &[String]) -> i32 {
+    let Some(path) = args.first() else {
+        return usage_error("usage: animated-text info <file>");
+    };
+    let Some(data) = read_document(path) else {
+        return FAILURE;
+    };
+
+    let clock = |t: f32| crate::model::format_time(t, TimeNotation::Clock);
+    let lines = &data.lines;
+    let empty = lines.iter().filter(|l| l.text.trim().is_empty()).count();
+    let keyed = lines.iter().filter(|l| !l.keyframes.is_empty()).count();
+    let keyframes: usize = lines.iter().map(|l| l.keyframes.len()).sum();
+    let ruby: usize = lines.iter().map(|l| l.ruby.len()).sum();
+    let start = lines.iter().map(|l| l.start).fold(f32::INFINITY, f32::min);
+    let end = lines.iter().map(|l| l.end).fold(0.0, f32::max);
+
+    println!("file       {}", path);
+    println!("lines      {} ({} empty)", lines.len(), empty);
+    if !lines.is_empty() {
+        println!("timing     {} - {}", clock(start), clock(end));
+    }
+    println!("keyframes  {} on {} lines", keyframes, keyed);
+    println!("ruby       {}", ruby);
+    for part in &data.parts {
+        let repeat = match &part.repeat {
+            Some(r) => format!(
+                ", repeats {} {:+.3}s",
+                data.parts.get(r.source).map_or("?", |p| p.name.as_str()),
+                r.offset
+            ),
+            None => String::new(),
+        };
+        println!(
+            "part       {} lines {}-{}{}",
+            part.name,
+            part.start + 1,
+            part.end,
+            repeat
+        );
+    }
+
+    let diagnostics = data.validate();
+    let count = |s: Severity| diagnostics.iter().filter(|d| d.severity == s).count();
+    println!(
+        "problems   {} errors, {} warnings",
+        count(Severity::Error),
+        count(Severity::Warning)
+    );
+    OK
+}
+
+// animated-text shift <file> <seconds> [--lines <from>-<to>] [--write|-o <output>]
+pub fn shift(args: &[String]) -> i32 {
+    let args = match Args::parse(
+        args,
+        &["--write", "--compact", "--clock"],
+        &["--lines", "--output", "-o"],
+    ) {
+        Ok(args) => args,
+        Err(e) => return usage_error(&e),
+    };
+    let [path, seconds] = args.positional[..] else {
+        return usage_error("usage: animated-text shift <file> <seconds>");
+    };
+    let Some(offset) = crate::model::parse_time(seconds.trim_start_matches('-'))
+        .map(|t| if seconds.starts_with('-') { -t } else { t })
+    else {
+        return usage_error(&format!("'{}' is not a time", seconds));
+    };
+    let Some(mut data) = read_document(path) else {
+        return FAILURE;
+    };
+
+    let range = match args.value(&["--lines"]) {
+        Some(lines) => {
+            let parsed = lines.split_once('-').and_then(|(from, to)| {
+                Some((
+                    from.trim().parse::<usize>().ok()?,
+                    to.trim().parse::<usize>().ok()?,
+                ))
+            });
+            match parsed {
+                Some((from, to)) if from >= 1 && from <= to => from - 1..to,
+                _ => return usage_error(&format!("'{}' should look like 3-10", lines)),
+            }
+        }
+        None => 0..data.lines.len(),
+    };
+    data.shift_times(range, offset);
+
+    let output = if args.flag("--write") {
+        Some(path)
+    } else {
+        args.value(&["--output", "-o"])
+    };
+    let format = output.map_or(Format::from_path(path), Format::from_path);
+    match convert::write(&data, format, &args.write_options()) {
+        Ok(content) => emit(output, &content),
+        Err(e) => {
+            eprintln!("{}", e);
+            FAILURE
+        }
+    }
+}
+
+// options shared by the editor and `serve`
+pub const CONFIG_OPTIONS: &[&str] = &["--config", "--bind", "--port", "-p", "--drafts"];
+
+// the config file, or the one found in the XDG dirs, with the command line on top
+fn load_config(args: &Args) -> Result<Config, String> {
+    let mut config = match args.value(&["--config"]) {
+        Some(path) => Config::load(std::path::Path::new(path))?,
+        None => Config::load_default()?,
+    };
+    if let Some(bind) = args.value(&["--bind"]) {
+        config.remote.bind = bind
+            .parse()
+            .map_err(|_| format!("--bind needs an IP address, got '{}'", bind))?;
+    }
+    if let Some(port) = args.value(&["--port", "-p"]) {
+        config.remote.port = port.parse().map_err(|_| "--port needs a number")?;
+    }
+    let drafts = args.values(&["--drafts"]);
+    if !drafts.is_empty() {
+        config.drafts.roots = drafts.into_iter().map(str::to_string).collect();
+    }
+    Ok(config)
+}
+
+// animated-text [edit <file>] [<config options>], the file to open and the config
+pub fn editor_args(args: &[String]) -> Result<(Option<String>, Config), i32> {
+    let args = Args::parse(args, &[], CONFIG_OPTIONS).map_err(|e| usage_error(&e))?;
+    let file = match args.positional[..] {
+        [] => None,
+        ["edit", path] => Some(path.to_string()),
+        _ => return Err(usage_error("usage: animated-text edit <file>")),
+    };
+    if let Some(path) = &file {
+        convert::read_file(path).map_err(|e| {
+            eprintln!("{}", e);
+            FAILURE
+        })?;
+    }
+    let config = load_config(&args).map_err(|e| {
+        eprintln!("{}", e);
+        FAILURE
+    })?;
+    Ok((file, config))
+}
+
+// animated-text serve [--port <port>]
+// runs the websocket / browser client server and logs what comes through
+pub async fn serve(args: &[String]) -> i32 {
+    let args = match Args::parse(args, &[], CONFIG_OPTIONS) {
+        Ok(args) => args,
+        Err(e) => return usage_error(&e),
+    };
+    let config = match load_config(&args) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            return FAILURE;
+        }
+    };
+
+    let tx = crate::remote::start_server(config.remote.addr());
+    let mut rx = tx.subscribe();
+    loop {
+        tokio::select! {
+            msg = rx.recv() => match msg {
+                Ok(text) if text.starts_with("Error:") => {
+                    eprintln!("{}", text);
+                    return FAILURE;
+                }
+                Ok(text) => println!("{}", text),
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => {}
+                Err(_) => return FAILURE,
+            },
+            _ = tokio::signal::ctrl_c() => return OK,
+        }
+    }
+}
+
+// animated-text merge <base> <ours> <theirs> [--output <file>]
+// usable as a git merge driver, the result replaces <ours> unless --output is given:
+//   git config merge.animated-text.driver "animated-text merge %O %A %B"
+//   echo "drafts/*.txt merge=animated-text" >> .gitattributes
+// exit code 0: clean merge, 1: conflicts (ours kept), 2: unreadable input
+pub fn merge(args: &[String]) -> i32 {
+    let args = match Args::parse(args, &[], &["--output", "-o"]) {
+        Ok(args) => args,
+        Err(e) => return usage_error(&e),
+    };
+    let [base, ours, theirs] = args.positional[..] else {
+        return usage_error("usage: animated-text merge <base> <ours> <theirs> [--output <file>]");
+    };
+
+    let mut docs = Vec::with_capacity(3);
+    for path in [base, ours, theirs] {
+        match read_document(path) {
+            Some(data) => docs.push(data),
+            None => return FAILURE,
+        }
+    }
+
+    let merged = AnimationData::merge(&docs[0], &docs[1], &docs[2]);
+    let output = args.value(&["--output", "-o"]).unwrap_or(ours);
+    let written = convert::write(
+        &merged.data,
+        Format::from_path(output),
+        &WriteOptions::default(),
+    );
+    match written {
+        Ok(content) if emit(Some(output), &content) == OK => {}
+        Ok(_) => return FAILURE,
+        Err(e) => {
+            eprintln!("{}: {}", output, e);
+            return FAILURE;
+        }
+    }
+
+    for c in &merged.conflicts {
+        eprintln!("{}: conflict at {}", output, c);
+    }
+    if merged.conflicts.is_empty() {
+        OK
+    } else {
+        PROBLEMS
+    }
+}
+
+// animated-text diff <old> <new> [--json]
+// also accepts the seven arguments git passes to GIT_EXTERNAL_DIFF / diff.<name>.command,
+// always exits 0 on success so git keeps going
+pub fn diff(args: &[String]) -> i32 {
+    let args = match Args::parse(args, &["--json"], &[]) {
+        Ok(args) => args,
+        Err(e) => return usage_error(&e),
+    };
+    let paths = &args.positional;
+    let (old, new) = match paths[..] {
+        [old, new] => (old, new),
+        [_, old, _, _, new, _, _] => (old, new),
+        _ => return usage_error("usage: animated-text diff <old> <new> [--json]"),
+    };
+
+    let (Some(old_data), Some(new_data)) = (read_document(old), read_document(new)) else {
+        return FAILURE;
+    };
+    let changes = old_data.diff(&new_data);
+    if args.flag("--json") {
+        println!("{}", render_changes_json(&changes));
+    } else {
+        if paths.len() == 7 {
+            println!("diff {}", paths[0]);
+        }
+        print!("{}", render_changes(&changes));
+    }
+    OK
+}
+
+// animated-text textconv <file>
+// prints one timing per row so plain `git diff` stays readable:
+//   git config diff.animated-text.textconv "animated-text textconv"
+//   echo "drafts/*.txt diff=animated-text" >> .gitattributes
+pub fn textconv(args: &[String]) -> i32 {
+    let Some(path) = args.first() else {
+        return usage_error("usage: animated-text textconv <file>");
+    };
+    match read_document(path) {
+        Some(data) => emit(None, &data.to_expanded()),
+        None => FAILURE,
+    }
+}
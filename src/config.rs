@@ -0,0 +1,187 @@
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::tui::{Keymap, Theme};
+
+// the editor settings, read from the first `animated-text/config.toml` in
+// $XDG_CONFIG_HOME (~/.config) and $XDG_CONFIG_DIRS (/etc/xdg). every key is
+// optional, e.g.
+//
+//   [remote]
+//   bind = "0.0.0.0"
+//   port = 3004
+//
+//   [drafts]
+//   roots = ["drafts", "~/lyrics"]
+//
+//   [steps]
+//   seek = 0.5              # seconds per Left / Right
+//   keyframe_time = 0.025   # seconds per Up / Down on a keyframe
+//   keyframe_position = 0.5 # characters per Up / Down on a keyframe
+//
+//   [keys.list]             # also global, drafts, line, text, tap, timeline
+//   repeat_part = "R"
+//   scroll_up = ["up", "k"]
+//
+//   [theme]
+//   accent = "magenta"
+//   playing = "#8ae234"
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub remote: RemoteConfig,
+    pub drafts: DraftsConfig,
+    pub steps: Steps,
+    pub keys: Keymap,
+    pub theme: Theme,
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RemoteConfig {
+    pub bind: IpAddr,
+    pub port: u16,
+}
+
+impl Default for RemoteConfig {
+    fn default() -> Self {
+        RemoteConfig {
+            bind: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            port: 3004,
+        }
+    }
+}
+
+impl RemoteConfig {
+    pub fn addr(&self) -> SocketAddr {
+        SocketAddr::new(self.bind, self.port)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DraftsConfig {
+    // listed by the draft selector, new drafts go to the first one
+    pub roots: Vec<String>,
+}
+
+impl Default for DraftsConfig {
+    fn default() -> Self {
+        DraftsConfig {
+            roots: vec!["drafts".to_string()],
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Steps {
+    pub seek: f32,
+    pub keyframe_time: f32,
+    pub keyframe_position: f32,
+}
+
+impl Default for Steps {
+    fn default() -> Self {
+        Steps {
+            seek: 0.5,
+            keyframe_time: 0.025,
+            keyframe_position: 0.5,
+        }
+    }
+}
+
+fn home() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .filter(|h| !h.is_empty())
+        .map(PathBuf::from)
+}
+
+// `~/` at the start of a drafts root
+fn expand_home(path: &str) -> String {
+    match (path.strip_prefix("~/"), home()) {
+        (Some(rest), Some(home)) => home.join(rest).to_string_lossy().into_owned(),
+        _ => path.to_string(),
+    }
+}
+
+fn search_path() -> Vec<PathBuf> {
+    let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+    let mut dirs = Vec::new();
+    match var("XDG_CONFIG_HOME") {
+        Some(dir) => dirs.push(PathBuf::from(dir)),
+        None => dirs.extend(home().map(|h| h.join(".config"))),
+    }
+    let system = var("XDG_CONFIG_DIRS").unwrap_or("/etc/xdg".to_string());
+    dirs.extend(std::env::split_paths(&system));
+    dirs.into_iter()
+        .map(|d| d.join("animated-text").join("config.toml"))
+        .collect()
+}
+
+impl Config {
+    pub fn from_toml(input: &str) -> Result<Config, String> {
+        let mut config: Config = toml::from_str(input).map_err(|e| e.to_string())?;
+        if config.drafts.roots.is_empty() {
+            return Err("drafts.roots needs at least one folder".to_string());
+        }
+        config.drafts.roots = config.drafts.roots.iter().map(|r| expand_home(r)).collect();
+        Ok(config)
+    }
+
+    pub fn load(path: &Path) -> Result<Config, String> {
+        let input =
+            std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Config::from_toml(&input).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    // the defaults when there is no config file
+    pub fn load_default() -> Result<Config, String> {
+        match search_path().into_iter().find(|p| p.is_file()) {
+            Some(path) => Config::load(&path),
+            None => Ok(Config::default()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Config;
+    use crate::tui::{Action, ViewMode};
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    #[test]
+    fn test_config_overrides_defaults() {
+        let config = Config::from_toml(
+            "[remote]\nport = 4000\n\n[steps]\nseek = 2.0\n\n\
+             [keys.list]\nrepeat_part = \"R\"\nscroll_up = [\"up\", \"k\"]\n\n\
+             [theme]\naccent = \"#ff8800\"\n",
+        )
+        .unwrap();
+        assert_eq!(config.remote.addr().to_string(), "127.0.0.1:4000");
+        assert_eq!(config.steps.seek, 2.0);
+        assert_eq!(config.steps.keyframe_position, 0.5);
+        assert_eq!(config.drafts.roots, ["drafts"]);
+
+        let key = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+        let keys = &config.keys;
+        assert_eq!(
+            keys.action(ViewMode::List, &key('R')),
+            Some(Action::RepeatPart)
+        );
+        assert_eq!(keys.action(ViewMode::List, &key('r')), None);
+        assert_eq!(
+            keys.action(ViewMode::List, &key('k')),
+            Some(Action::ScrollUp)
+        );
+        // global keys still apply below the list table
+        assert_eq!(keys.action(ViewMode::List, &key('s')), Some(Action::Save));
+        assert_eq!(keys.hint(ViewMode::List, Action::ScrollUp), "UP/K");
+
+        assert!(Config::from_toml("[keys.list]\nrepeat_part = \"hyper+r\"").is_err());
+        assert!(Config::from_toml("[theme]\naccent = \"nope\"").is_err());
+        assert!(Config::from_toml("[remote]\nprot = 1").is_err());
+    }
+}
@@ -1,10 +1,144 @@
-use crate::model::AnimationData;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 
-pub fn from_LRC(data: &str) -> AnimationData {
-    return AnimationData { lines: vec![] };
+use crate::model::{AnimationData, WriteOptions};
+
+// every format a document can be read from or written to
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    // the native bracket format
+    Text,
+    Json,
+    Ttml,
+    Lrc,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Result<Format, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "txt" | "text" | "animated-text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "ttml" | "xml" => Ok(Format::Ttml),
+            "lrc" => Ok(Format::Lrc),
+            _ => Err(format!(
+                "Unknown format '{}', expected txt, json, ttml or lrc",
+                name
+            )),
+        }
+    }
+
+    // guessed from the extension, anything unknown is the native format
+    pub fn from_path(path: &str) -> Format {
+        path.rsplit_once('.')
+            .and_then(|(_, ext)| Format::from_name(ext).ok())
+            .unwrap_or(Format::Text)
+    }
+}
+
+pub fn read(input: &str, format: Format) -> Result<AnimationData, String> {
+    match format {
+        Format::Text => input.parse::<AnimationData>(),
+        Format::Json => AnimationData::from_json(input),
+        Format::Ttml => AnimationData::from_ttml(input),
+        Format::Lrc => AnimationData::from_lrc(input),
+    }
+}
+
+// `options` only affect the native format
+pub fn write(
+    data: &AnimationData,
+    format: Format,
+    options: &WriteOptions,
+) -> Result<String, String> {
+    match format {
+        Format::Text => Ok(data.compile_with(options)),
+        Format::Json => data.to_json(),
+        Format::Ttml => data.to_ttml(),
+        Format::Lrc => Ok(data.to_lrc()),
+    }
+}
+
+pub fn read_file(path: &str) -> Result<AnimationData, String> {
+    let input = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    read(&input, Format::from_path(path)).map_err(|e| format!("{}: {}", path, e))
 }
 
-pub fn from_TTML(data: &str) -> AnimationData {
+// how many `<path>.bak.N` copies `write_file` keeps, `.bak.1` being the newest
+pub const DEFAULT_BACKUPS: usize = 3;
+
+fn backup_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".bak.{}", n));
+    PathBuf::from(name)
+}
+
+// replaces `path` in one step: the content goes to a temp file next to it which
+// is then renamed over. the previous content is rotated into `backups` copies.
+pub fn write_atomic(path: &str, content: &str, backups: usize) -> Result<(), String> {
+    let target = Path::new(path);
+    let err = |e: std::io::Error| format!("{}: {}", path, e);
+
+    if backups > 0 && target.exists() {
+        for n in (1..backups).rev() {
+            let from = backup_path(target, n);
+            if from.exists() {
+                fs::rename(&from, backup_path(target, n + 1)).map_err(err)?;
+            }
+        }
+        fs::copy(target, backup_path(target, 1)).map_err(err)?;
+    }
+
+    let file_name = target
+        .file_name()
+        .ok_or(format!("{}: not a file path", path))?
+        .to_string_lossy();
+    let tmp = target.with_file_name(format!(".{}.tmp-{}", file_name, std::process::id()));
+    let written = fs::File::create(&tmp).and_then(|mut file| {
+        file.write_all(content.as_bytes())?;
+        file.sync_all()
+    });
+    if let Err(e) = written.and_then(|_| fs::rename(&tmp, target)) {
+        let _ = fs::remove_file(&tmp);
+        return Err(err(e));
+    }
+    Ok(())
+}
+
+// writes `data` in the format matching the extension of `path`
+pub fn write_file(
+    path: &str,
+    data: &AnimationData,
+    options: &WriteOptions,
+    backups: usize,
+) -> Result<(), String> {
+    let content = write(data, Format::from_path(path), options)?;
+    write_atomic(path, &content, backups)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{backup_path, write_atomic};
+    use std::path::Path;
+
+    #[test]
+    fn test_write_atomic_rotates_backups() {
+        let dir = std::env::temp_dir().join(format!("animated-text-save-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("song.txt");
+        let path = file.to_str().unwrap();
 
+        for content in ["one", "two", "three", "four"] {
+            write_atomic(path, content, 2).unwrap();
+        }
+        let read = |p: &Path| std::fs::read_to_string(p).unwrap();
+        assert_eq!(read(&file), "four");
+        assert_eq!(read(&backup_path(&file, 1)), "three");
+        assert_eq!(read(&backup_path(&file, 2)), "two");
+        assert!(!backup_path(&file, 3).exists());
+        // no temp files left behind
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 3);
 
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
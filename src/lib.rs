@@ -0,0 +1,11 @@
+// parser, writer and converters for the animated-text lyric format.
+// the editor and the remote server are only part of the binary,
+// behind the `tui` and `server` features.
+pub mod convert;
+pub mod model;
+
+pub use convert::Format;
+pub use model::{
+    AnimationData, Diagnostic, Keyframe, Part, Repeat, Ruby, Severity, TextSegment, TimeNotation,
+    WriteOptions,
+};
@@ -1,34 +1,80 @@
 use crossterm::{
-    event::{self, Event, KeyCode},
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyboardEnhancementFlags,
+        PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
     execute,
-    terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
+    terminal::{
+        EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode,
+        supports_keyboard_enhancement,
+    },
 };
 
 use ratatui::{Terminal, backend::CrosstermBackend};
 
 use std::{io, time::Duration};
 
-mod model;
+mod cli;
+mod config;
 mod remote;
 mod tui;
 
-use tui::{App, UI, ViewMode};
+use animated_text::{convert, model};
+
+use config::Config;
+use tui::{Action, App, UI, ViewMode};
 
 #[tokio::main]
 async fn main() -> io::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    // the editor also takes config options without a command, `--port 4000` or `-p 4000`
+    let first = args.first().map(String::as_str);
+    let is_editor = match first {
+        None | Some("edit") => true,
+        Some(arg) => cli::CONFIG_OPTIONS.contains(&arg),
+    };
+    if is_editor {
+        return match cli::editor_args(&args) {
+            Ok((file, config)) => run_editor(file.as_deref(), config).await,
+            Err(code) => std::process::exit(code),
+        };
+    }
+    match first {
+        Some("serve") => std::process::exit(cli::serve(&args[1..]).await),
+        _ => std::process::exit(cli::run(&args)),
+    }
+}
+
+async fn run_editor(file: Option<&str>, config: Config) -> io::Result<()> {
     // Start remote server
-    let remote_tx = remote::start_server(3004);
+    let remote_tx = remote::start_server(config.remote.addr());
 
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    // key releases, for holding lines in tap sync, where the terminal can report them
+    let key_releases = supports_keyboard_enhancement().unwrap_or(false);
+    if key_releases {
+        execute!(
+            stdout,
+            PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+        )?;
+    }
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let mut app = App::new(remote_tx.clone());
+    let mut app = App::new(remote_tx.clone(), config);
+    app.key_releases = key_releases;
+    if let Some(path) = file {
+        app.open_file(path);
+    }
     let mut remote_rx = remote_tx.subscribe();
 
     loop {
+        if app.should_quit {
+            app.discard_journal();
+            break;
+        }
         app.update();
         terminal.draw(|f| UI::draw(f, &app))?;
 
@@ -41,28 +87,25 @@ async fn main() -> io::Result<()> {
             } => {
                 if let Ok(Some(ev)) = result {
                     match ev {
-                        Event::Key(key) => {
-                            if app.view_mode == ViewMode::TextEdit {
-                                app.handle_text_edits(key);
-                            } else {
-                                if key.code == KeyCode::Char('q') {
-                                    break;
-                                }
-                                app.handle_control_input(key);
-                            }
-                        }
-                        Event::Paste(text) => {
-                            if app.view_mode == ViewMode::TextEdit {
-                                app.insert_text(&text);
+                        Event::Key(key) => app.handle_key(key),
+                        Event::Mouse(mouse) => app.handle_mouse(mouse, terminal.size()?.into()),
+                        Event::Paste(text)
+                            if app.view_mode == ViewMode::TextEdit => {
+                                app.dispatch(Action::InsertText(text));
                             }
-                        }
                         _ => {}
                     }
                 }
             }
             msg = remote_rx.recv() => {
                 if let Ok(text) = msg {
-                    if text == "Connected" || text == "Disconnected" || text.starts_with("Listening") {
+                    if let Some(action) = remote::parse_action(&text) {
+                        if action.allowed_remotely() {
+                            app.dispatch(action);
+                        } else {
+                            app.server_status = format!("Remote may not {:?}", action);
+                        }
+                    } else if text == "Connected" || text == "Disconnected" || text.starts_with("Listening") {
                         app.server_status = text;
                     } else {
                         app.server_status = format!("Msg: {}", text);
@@ -73,10 +116,14 @@ async fn main() -> io::Result<()> {
     }
 
     disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
-
-    let data = app.data.compile();
-    print!("{}", data);
+    if key_releases {
+        execute!(terminal.backend_mut(), PopKeyboardEnhancementFlags)?;
+    }
+    execute!(
+        terminal.backend_mut(),
+        DisableMouseCapture,
+        LeaveAlternateScreen
+    )?;
 
     Ok(())
 }
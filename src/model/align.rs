@@ -0,0 +1,398 @@
+use super::{AnimationData, Keyframe, Part, Ruby, TextSegment};
+
+// an unmatched word without neighbours on one side gets this much time
+const FALLBACK_WORD_SECS: f32 = 0.3;
+
+// result of carrying timings over to a revised lyric text
+#[derive(Clone, Debug, PartialEq)]
+pub struct Realignment {
+    pub data: AnimationData,
+    // lines with interpolated timings, in `data`
+    pub review: Vec<usize>,
+    // parts left without lines, and repeats that no longer read like their source
+    pub dropped: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
+struct WordRef<'a> {
+    line: usize,
+    text: &'a str,
+    chars: (usize, usize),
+    bytes: (usize, usize),
+    first: bool,
+    last: bool,
+}
+
+fn words_of(lines: &[TextSegment]) -> Vec<WordRef<'_>> {
+    let mut words = Vec::new();
+    for (li, line) in lines.iter().enumerate() {
+        let mut line_words = Vec::new();
+        let mut start: Option<(usize, usize)> = None;
+        for (ci, (bi, c)) in line.text.char_indices().enumerate() {
+            match (start, c.is_whitespace()) {
+                (None, false) => start = Some((ci, bi)),
+                (Some((cs, bs)), true) => {
+                    line_words.push((cs, ci, bs, bi));
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some((cs, bs)) = start {
+            line_words.push((cs, line.text.chars().count(), bs, line.text.len()));
+        }
+
+        let count = line_words.len();
+        for (wi, (cs, ce, bs, be)) in line_words.into_iter().enumerate() {
+            words.push(WordRef {
+                line: li,
+                text: &line.text[bs..be],
+                chars: (cs, ce),
+                bytes: (bs, be),
+                first: wi == 0,
+                last: wi + 1 == count,
+            });
+        }
+    }
+    words
+}
+
+// longest common subsequence, returns the matched old index per new item
+pub(super) fn lcs_matches<T>(
+    old: &[T],
+    new: &[T],
+    eq: impl Fn(&T, &T) -> bool,
+) -> Vec<Option<usize>> {
+    let (n, m) = (old.len(), new.len());
+    let mut lcs = vec![0u32; (n + 1) * (m + 1)];
+    let at = |i: usize, j: usize| i * (m + 1) + j;
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[at(i, j)] = if eq(&old[i], &new[j]) {
+                lcs[at(i + 1, j + 1)] + 1
+            } else {
+                lcs[at(i + 1, j)].max(lcs[at(i, j + 1)])
+            };
+        }
+    }
+
+    let mut matches = vec![None; m];
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if eq(&old[i], &new[j]) {
+            matches[j] = Some(i);
+            i += 1;
+            j += 1;
+        } else if lcs[at(i + 1, j)] >= lcs[at(i, j + 1)] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    matches
+}
+
+impl AnimationData {
+    // aligns `new_text` against this document word by word. unchanged lines are
+    // copied, changed lines keep the timings of their matched words and
+    // interpolate the rest; those are listed in `review`.
+    pub fn realign(&self, new_text: &str) -> Realignment {
+        let mut data = AnimationData::default();
+        data.parse_lines(new_text);
+
+        let old_words = words_of(&self.lines);
+        let revised = data.lines.clone();
+        let new_words = words_of(&revised);
+        let matches = lcs_matches(&old_words, &new_words, |a, b| a.text == b.text);
+
+        let word_time = |w: &WordRef| {
+            let line = &self.lines[w.line];
+            (
+                line.start + line.time_at_index(w.bytes.0 as f32),
+                line.start + line.time_at_index(w.bytes.1 as f32),
+            )
+        };
+
+        // absolute (start, end) per new word, interpolating unmatched runs by length
+        let mut times: Vec<Option<(f32, f32)>> = matches
+            .iter()
+            .map(|m| m.map(|oi| word_time(&old_words[oi])))
+            .collect();
+        let song_end = self.lines.iter().map(|l| l.end).fold(0.0, f32::max);
+        let mut j = 0;
+        while j < times.len() {
+            if times[j].is_some() {
+                j += 1;
+                continue;
+            }
+            let run_start = j;
+            while j < times.len() && times[j].is_none() {
+                j += 1;
+            }
+            let run = &new_words[run_start..j];
+            let total_chars: usize = run.iter().map(|w| w.chars.1 - w.chars.0).sum();
+
+            let from = run_start
+                .checked_sub(1)
+                .and_then(|p| times[p])
+                .map_or(0.0, |t| t.1);
+            let to = times
+                .get(j)
+                .copied()
+                .flatten()
+                .map(|t| t.0)
+                .unwrap_or_else(|| song_end.max(from + FALLBACK_WORD_SECS * run.len() as f32));
+
+            let mut t = from;
+            for (k, w) in run.iter().enumerate() {
+                let share = (w.chars.1 - w.chars.0) as f32 / total_chars.max(1) as f32;
+                let next = t + (to - from).max(0.0) * share;
+                times[run_start + k] = Some((t, next));
+                t = next;
+            }
+        }
+
+        let mut review = Vec::new();
+        for (li, revised_line) in revised.iter().enumerate() {
+            let idxs: Vec<usize> = (0..new_words.len())
+                .filter(|&wi| new_words[wi].line == li)
+                .collect();
+            if idxs.is_empty() {
+                continue;
+            }
+
+            let sources: Vec<usize> = idxs
+                .iter()
+                .filter_map(|&wi| matches[wi].map(|oi| old_words[oi].line))
+                .collect();
+            let fully_matched = sources.len() == idxs.len();
+            let single_source = sources.windows(2).all(|p| p[0] == p[1]);
+
+            if fully_matched && single_source && self.lines[sources[0]].text == revised_line.text {
+                data.lines[li] = self.lines[sources[0]].clone();
+                continue;
+            }
+
+            let first_word = &new_words[idxs[0]];
+            let last_word = &new_words[idxs[idxs.len() - 1]];
+            let mut start = times[idxs[0]].unwrap().0;
+            let mut end = times[idxs[idxs.len() - 1]].unwrap().1;
+            // keep the lead-in and tail of lines whose edges survived
+            if let Some(oi) = matches[idxs[0]]
+                && old_words[oi].first
+                && first_word.first
+            {
+                start = self.lines[old_words[oi].line].start;
+            }
+            if let Some(oi) = matches[idxs[idxs.len() - 1]]
+                && old_words[oi].last
+                && last_word.last
+            {
+                end = self.lines[old_words[oi].line].end;
+            }
+
+            let keyed = sources.iter().any(|&s| !self.lines[s].keyframes.is_empty());
+            let line = &mut data.lines[li];
+            line.start = start;
+            line.end = end.max(start);
+
+            for &wi in &idxs {
+                let w = &new_words[wi];
+                if keyed {
+                    let (t, _) = times[wi].unwrap();
+                    line.keyframes.push(Keyframe {
+                        time: (t - start).max(0.0),
+                        index: w.bytes.0 as f32,
+                    });
+                }
+                let Some(oi) = matches[wi] else {
+                    continue;
+                };
+                let ow = &old_words[oi];
+                let old_line = &self.lines[ow.line];
+                if keyed {
+                    for kf in old_line
+                        .keyframes
+                        .iter()
+                        .filter(|kf| kf.index > ow.bytes.0 as f32 && kf.index < ow.bytes.1 as f32)
+                    {
+                        line.keyframes.push(Keyframe {
+                            time: (old_line.start + kf.time - start).max(0.0),
+                            index: w.bytes.0 as f32 + (kf.index - ow.bytes.0 as f32),
+                        });
+                    }
+                }
+                for r in old_line
+                    .ruby
+                    .iter()
+                    .filter(|r| r.start >= ow.chars.0 && r.end <= ow.chars.1)
+                {
+                    let mut ruby = Ruby::new(
+                        w.chars.0 + (r.start - ow.chars.0),
+                        w.chars.0 + (r.end - ow.chars.0),
+                        &r.text,
+                    );
+                    for kf in &r.keyframes {
+                        ruby.add_keyframe((old_line.start + kf.time - start).max(0.0), kf.index);
+                    }
+                    line.ruby.push(ruby);
+                }
+            }
+            if keyed {
+                line.keyframes.push(Keyframe {
+                    time: line.end - line.start,
+                    index: line.text.len() as f32,
+                });
+                line.sort_keyframes();
+            }
+
+            if !fully_matched || !single_source {
+                review.push(li);
+            }
+        }
+
+        // lines without words fill the gap between their neighbours
+        for li in 0..data.lines.len() {
+            if !data.lines[li].text.split_whitespace().any(|_| true) {
+                let prev_end = li.checked_sub(1).map_or(0.0, |p| data.lines[p].end);
+                let next_start = data.lines[li + 1..]
+                    .iter()
+                    .find(|l| l.text.split_whitespace().any(|_| true))
+                    .map_or(prev_end, |l| l.start);
+                data.lines[li].start = prev_end;
+                data.lines[li].end = next_start.max(prev_end);
+            }
+        }
+
+        let dropped = self.carry_parts(&mut data);
+        Realignment {
+            data,
+            review,
+            dropped,
+        }
+    }
+
+    // moves the parts onto the revised lines their lines were matched to.
+    // returns what could not be carried over
+    fn carry_parts(&self, data: &mut AnimationData) -> Vec<String> {
+        let matches = lcs_matches(&self.lines, &data.lines, |a, b| a.text == b.text);
+        // a boundary before old line `b` goes before the first line matched at
+        // or after it, so lines inserted at the end of a part stay in it
+        let at = |b: usize| {
+            matches
+                .iter()
+                .position(|m| m.is_some_and(|oi| oi >= b))
+                .unwrap_or(matches.len())
+        };
+
+        let mut dropped = Vec::new();
+        let mut index = Vec::new();
+        for p in &self.parts {
+            let (start, end) = (at(p.start), at(p.end));
+            if start == end {
+                index.push(None);
+                dropped.push(p.name.clone());
+                continue;
+            }
+            index.push(Some(data.parts.len()));
+            data.parts.push(Part {
+                start,
+                end,
+                ..p.clone()
+            });
+        }
+
+        for pi in 0..data.parts.len() {
+            let Some(r) = data.parts[pi].repeat.clone() else {
+                continue;
+            };
+            let part = &data.parts[pi];
+            let source = index.get(r.source).copied().flatten().filter(|&si| {
+                let src = &data.parts[si];
+                src.len() == part.len()
+                    && (0..src.len())
+                        .all(|k| data.lines[src.start + k].text == data.lines[part.start + k].text)
+            });
+            match source {
+                Some(si) => {
+                    let offset =
+                        data.lines[part.start].start - data.lines[data.parts[si].start].start;
+                    if let Some(r) = &mut data.parts[pi].repeat {
+                        r.source = si;
+                        r.offset = offset;
+                    }
+                }
+                None => {
+                    dropped.push(format!("repeat {}", part.name));
+                    data.parts[pi].repeat = None;
+                }
+            }
+        }
+        data.sync_repeats();
+        dropped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::AnimationData;
+
+    #[test]
+    fn test_realign_keeps_unchanged_words() {
+        let mut old = AnimationData::default();
+        old.add_line("hello world", 1.0, 3.0)
+            .add_keyframe(0.0, 0.0)
+            .add_keyframe(0.5, 6.0)
+            .add_keyframe(2.0, 11.0);
+        old.add_line("foo bar", 4.0, 6.0);
+
+        let result = old.realign("hello big world\nfoo bar\nnew words");
+        let data = &result.data;
+        assert_eq!(data.lines.len(), 3);
+        assert_eq!(result.review, vec![0, 2]);
+
+        // unchanged line is carried over as is
+        assert_eq!(data.lines[1], old.lines[1]);
+
+        // "hello" and "world" keep their times, "big" sits in between
+        let line = &data.lines[0];
+        assert_eq!((line.start, line.end), (1.0, 3.0));
+        assert_eq!(line.keyframes.first().unwrap().time, 0.0);
+        let world = line.keyframes.iter().find(|k| k.index == 10.0).unwrap();
+        assert_eq!(world.time, 0.5);
+        assert_eq!(line.keyframes.last().unwrap().index, 15.0);
+
+        // trailing new line is placed after the last known word
+        assert!(data.lines[2].start >= 6.0);
+        assert!(data.lines[2].end > data.lines[2].start);
+    }
+
+    #[test]
+    fn test_realign_carries_parts() {
+        let mut old = AnimationData::default();
+        old.add_line("verse one", 0.0, 2.0);
+        old.add_line("chorus one", 2.0, 4.0);
+        old.add_line("chorus two", 4.0, 6.0);
+        old.add_line("bridge", 6.0, 8.0);
+        old.add_part("Verse", 0, 1);
+        old.add_part("Chorus", 1, 3);
+        old.add_part("Bridge", 3, 4);
+        old.repeat_part(1, 4, 10.0).unwrap();
+
+        // a line added to the verse, the bridge gone
+        let result =
+            old.realign("verse one\nverse two\nchorus one\nchorus two\nchorus one\nchorus two");
+        let parts: Vec<(usize, usize)> =
+            result.data.parts.iter().map(|p| (p.start, p.end)).collect();
+        assert_eq!(parts, [(0, 2), (2, 4), (4, 6)]);
+        assert_eq!(result.data.parts[2].repeat.as_ref().unwrap().source, 1);
+        assert_eq!(result.dropped, ["Bridge"]);
+        assert!(result.data.check_parts().is_ok());
+
+        // a repeat that was changed on its own is no longer one
+        let result = old.realign("verse one\nchorus one\nchorus two\nbridge\nchorus one\nchorus 2");
+        assert!(result.data.parts[3].repeat.is_none());
+        assert_eq!(result.dropped, ["repeat Chorus"]);
+        assert_eq!(result.data.lines[5].text, "chorus 2");
+    }
+}
@@ -0,0 +1,460 @@
+use std::fmt;
+use std::fmt::Write as _;
+
+use serde::Serialize;
+
+use super::align::lcs_matches;
+use super::{AnimationData, Keyframe, Part, Ruby, TextSegment};
+
+fn ms(secs: f32) -> i64 {
+    (secs * 1000.0).round() as i64
+}
+
+// line numbers are 0-based, `line` always refers to the new document
+// except for removed lines
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum Change {
+    LineAdded {
+        line: usize,
+        text: String,
+        start_ms: i64,
+        end_ms: i64,
+    },
+    LineRemoved {
+        old_line: usize,
+        text: String,
+    },
+    TextChanged {
+        old_line: usize,
+        line: usize,
+        old: String,
+        new: String,
+    },
+    TimingChanged {
+        line: usize,
+        start_delta_ms: i64,
+        end_delta_ms: i64,
+    },
+    KeyframeAdded {
+        line: usize,
+        index: f32,
+        time_ms: i64,
+    },
+    KeyframeRemoved {
+        line: usize,
+        index: f32,
+        time_ms: i64,
+    },
+    KeyframeMoved {
+        line: usize,
+        index: f32,
+        old_time_ms: i64,
+        new_time_ms: i64,
+    },
+    RubyChanged {
+        line: usize,
+        old: Vec<String>,
+        new: Vec<String>,
+    },
+    AgentChanged {
+        line: usize,
+        old: Option<String>,
+        new: Option<String>,
+    },
+    PartsChanged {
+        old: Vec<String>,
+        new: Vec<String>,
+    },
+}
+
+fn signed_ms(delta: i64) -> String {
+    format!("{:+}ms", delta)
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::LineAdded {
+                line,
+                text,
+                start_ms,
+                end_ms,
+            } => write!(
+                f,
+                "+ line {}: {:?} [{:.3} - {:.3}]",
+                line + 1,
+                text,
+                *start_ms as f32 / 1000.0,
+                *end_ms as f32 / 1000.0
+            ),
+            Change::LineRemoved { old_line, text } => {
+                write!(f, "- line {}: {:?}", old_line + 1, text)
+            }
+            Change::TextChanged {
+                old_line,
+                line,
+                old,
+                new,
+            } => {
+                if old_line == line {
+                    write!(f, "~ line {}: {:?} -> {:?}", line + 1, old, new)
+                } else {
+                    write!(
+                        f,
+                        "~ line {} (was {}): {:?} -> {:?}",
+                        line + 1,
+                        old_line + 1,
+                        old,
+                        new
+                    )
+                }
+            }
+            Change::TimingChanged {
+                line,
+                start_delta_ms,
+                end_delta_ms,
+            } => write!(
+                f,
+                "~ line {}: start {}, end {}",
+                line + 1,
+                signed_ms(*start_delta_ms),
+                signed_ms(*end_delta_ms)
+            ),
+            Change::KeyframeAdded {
+                line,
+                index,
+                time_ms,
+            } => write!(
+                f,
+                "+ line {}: keyframe @{:.2} at {}ms",
+                line + 1,
+                index,
+                time_ms
+            ),
+            Change::KeyframeRemoved {
+                line,
+                index,
+                time_ms,
+            } => write!(
+                f,
+                "- line {}: keyframe @{:.2} at {}ms",
+                line + 1,
+                index,
+                time_ms
+            ),
+            Change::KeyframeMoved {
+                line,
+                index,
+                old_time_ms,
+                new_time_ms,
+            } => write!(
+                f,
+                "~ line {}: keyframe @{:.2} moved {} ({}ms -> {}ms)",
+                line + 1,
+                index,
+                signed_ms(new_time_ms - old_time_ms),
+                old_time_ms,
+                new_time_ms
+            ),
+            Change::RubyChanged { line, old, new } => write!(
+                f,
+                "~ line {}: ruby [{}] -> [{}]",
+                line + 1,
+                old.join(","),
+                new.join(",")
+            ),
+            Change::AgentChanged { line, old, new } => write!(
+                f,
+                "~ line {}: agent {} -> {}",
+                line + 1,
+                old.as_deref().unwrap_or("-"),
+                new.as_deref().unwrap_or("-")
+            ),
+            Change::PartsChanged { old, new } => {
+                write!(f, "~ parts [{}] -> [{}]", old.join(","), new.join(","))
+            }
+        }
+    }
+}
+
+// keyframes are matched by text position, a changed time is a move
+fn diff_keyframes(line: usize, old: &[Keyframe], new: &[Keyframe], changes: &mut Vec<Change>) {
+    let key = |k: &Keyframe| (k.index * 1000.0).round() as i64;
+    let mut used = vec![false; new.len()];
+    for o in old {
+        let found = new
+            .iter()
+            .enumerate()
+            .position(|(ni, n)| !used[ni] && key(n) == key(o));
+        match found {
+            Some(ni) => {
+                used[ni] = true;
+                let (old_time_ms, new_time_ms) = (ms(o.time), ms(new[ni].time));
+                if old_time_ms != new_time_ms {
+                    changes.push(Change::KeyframeMoved {
+                        line,
+                        index: o.index,
+                        old_time_ms,
+                        new_time_ms,
+                    });
+                }
+            }
+            None => changes.push(Change::KeyframeRemoved {
+                line,
+                index: o.index,
+                time_ms: ms(o.time),
+            }),
+        }
+    }
+    for (n, _) in new.iter().zip(&used).filter(|(_, used)| !**used) {
+        changes.push(Change::KeyframeAdded {
+            line,
+            index: n.index,
+            time_ms: ms(n.time),
+        });
+    }
+}
+
+fn ruby_entries(ruby: &[Ruby]) -> Vec<String> {
+    ruby.iter().map(Ruby::to_string_entry).collect()
+}
+
+fn diff_line(
+    (old_line, old): (usize, &TextSegment),
+    (line, new): (usize, &TextSegment),
+    changes: &mut Vec<Change>,
+) {
+    if old.text != new.text {
+        changes.push(Change::TextChanged {
+            old_line,
+            line,
+            old: old.text.clone(),
+            new: new.text.clone(),
+        });
+    }
+    let start_delta_ms = ms(new.start) - ms(old.start);
+    let end_delta_ms = ms(new.end) - ms(old.end);
+    if start_delta_ms != 0 || end_delta_ms != 0 {
+        changes.push(Change::TimingChanged {
+            line,
+            start_delta_ms,
+            end_delta_ms,
+        });
+    }
+    diff_keyframes(line, &old.keyframes, &new.keyframes, changes);
+    if old.ruby != new.ruby {
+        changes.push(Change::RubyChanged {
+            line,
+            old: ruby_entries(&old.ruby),
+            new: ruby_entries(&new.ruby),
+        });
+    }
+    if old.agent != new.agent {
+        changes.push(Change::AgentChanged {
+            line,
+            old: old.agent.clone(),
+            new: new.agent.clone(),
+        });
+    }
+}
+
+// whether `new` parts are `old` ones on the lines those were paired with
+// (`at`, old line -> new). a boundary may sit anywhere among the lines added
+// where it was, those show up as added lines already
+fn same_parts(old: &[Part], new: &[Part], at: &[Option<usize>], new_len: usize) -> bool {
+    let fits = |b: usize, nb: usize| {
+        let lo = at[..b].iter().rev().find_map(|&n| n).map_or(0, |n| n + 1);
+        let hi = at[b..].iter().find_map(|&n| n).unwrap_or(new_len);
+        (lo..=hi).contains(&nb)
+    };
+    old.len() == new.len()
+        && old.iter().zip(new).all(|(o, n)| {
+            o.name == n.name
+                && o.repeat == n.repeat
+                && o.end <= at.len()
+                && fits(o.start, n.start)
+                && fits(o.end, n.end)
+        })
+}
+
+impl AnimationData {
+    // semantic changes from `self` to `new`. lines are matched by text first,
+    // unmatched lines between two matches are paired up as text edits.
+    pub fn diff(&self, new: &AnimationData) -> Vec<Change> {
+        let matches = lcs_matches(&self.lines, &new.lines, |a, b| a.text == b.text);
+        let anchors = matches
+            .iter()
+            .enumerate()
+            .filter_map(|(ni, oi)| Some((oi.as_ref().copied()?, ni)))
+            .chain(std::iter::once((self.lines.len(), new.lines.len())));
+
+        let mut changes = Vec::new();
+        let mut at = vec![None; self.lines.len()];
+        let (mut po, mut pn) = (0, 0);
+        for (oi, ni) in anchors {
+            let paired = (oi - po).min(ni - pn);
+            for k in 0..paired {
+                at[po + k] = Some(pn + k);
+                diff_line(
+                    (po + k, &self.lines[po + k]),
+                    (pn + k, &new.lines[pn + k]),
+                    &mut changes,
+                );
+            }
+            for (k, old) in self.lines[po + paired..oi].iter().enumerate() {
+                changes.push(Change::LineRemoved {
+                    old_line: po + paired + k,
+                    text: old.text.clone(),
+                });
+            }
+            for (k, added) in new.lines[pn + paired..ni].iter().enumerate() {
+                changes.push(Change::LineAdded {
+                    line: pn + paired + k,
+                    text: added.text.clone(),
+                    start_ms: ms(added.start),
+                    end_ms: ms(added.end),
+                });
+            }
+            if oi < self.lines.len() {
+                at[oi] = Some(ni);
+                diff_line((oi, &self.lines[oi]), (ni, &new.lines[ni]), &mut changes);
+            }
+            (po, pn) = (oi + 1, ni + 1);
+        }
+
+        if !same_parts(&self.parts, &new.parts, &at, new.lines.len()) {
+            let entries =
+                |data: &AnimationData| data.parts.iter().map(|p| p.to_string_entry()).collect();
+            changes.push(Change::PartsChanged {
+                old: entries(self),
+                new: entries(new),
+            });
+        }
+        changes
+    }
+
+    // one line per lyric with every timing spelled out, for `git diff` textconv
+    pub fn to_expanded(&self) -> String {
+        let mut out = String::new();
+        for (i, line) in self.lines.iter().enumerate() {
+            if let Some(part) = self.part_starting_at(i) {
+                let _ = writeln!(out, "[{}]", part.name);
+            }
+            let _ = write!(out, "{:>9.3} {:>9.3}  {}", line.start, line.end, line.text);
+            if let Some(agent) = &line.agent {
+                let _ = write!(out, "  ({})", agent);
+            }
+            for kf in &line.keyframes {
+                let _ = write!(
+                    out,
+                    "\n{:>9} {:>9.3}  @{:.2}",
+                    "",
+                    line.start + kf.time,
+                    kf.index
+                );
+            }
+            for ruby in &line.ruby {
+                let _ = write!(
+                    out,
+                    "\n{:>9} {:>9}  ruby {}",
+                    "",
+                    "",
+                    ruby.to_string_entry()
+                );
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+pub fn render_changes(changes: &[Change]) -> String {
+    changes.iter().map(|c| format!("{}\n", c)).collect()
+}
+
+pub fn render_changes_json(changes: &[Change]) -> String {
+    serde_json::to_string_pretty(changes).unwrap_or_else(|_| "[]".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::AnimationData;
+
+    use super::Change;
+
+    #[test]
+    fn test_diff_reports_semantic_changes() {
+        let mut old = AnimationData::default();
+        old.add_line("city of stars", 1.0, 3.0)
+            .add_keyframe(0.0, 0.0)
+            .add_keyframe(1.0, 5.0)
+            .add_keyframe(2.0, 13.0);
+        old.add_line("are you shining", 3.0, 5.0);
+        old.add_line("just for me", 5.0, 7.0);
+
+        let mut new = old.clone();
+        new.lines[0].start = 1.25;
+        new.lines[0].keyframes[1].time = 1.2;
+        new.lines[1].text = "are you shining just".to_string();
+        new.lines.remove(2);
+
+        let changes = old.diff(&new);
+        assert_eq!(
+            changes,
+            vec![
+                Change::TimingChanged {
+                    line: 0,
+                    start_delta_ms: 250,
+                    end_delta_ms: 0,
+                },
+                Change::KeyframeMoved {
+                    line: 0,
+                    index: 5.0,
+                    old_time_ms: 1000,
+                    new_time_ms: 1200,
+                },
+                Change::TextChanged {
+                    old_line: 1,
+                    line: 1,
+                    old: "are you shining".to_string(),
+                    new: "are you shining just".to_string(),
+                },
+                Change::LineRemoved {
+                    old_line: 2,
+                    text: "just for me".to_string(),
+                },
+            ]
+        );
+        assert!(super::render_changes_json(&changes).contains("\"change\": \"keyframe_moved\""));
+    }
+
+    #[test]
+    fn test_parts_follow_their_lines() {
+        let mut old = AnimationData::default();
+        old.add_line("verse", 0.0, 2.0);
+        old.add_line("chorus one", 2.0, 4.0);
+        old.add_line("chorus two", 4.0, 6.0);
+        old.add_part("Verse", 0, 1);
+        old.add_part("Chorus", 1, 3);
+
+        // a line added at the top moves every part along with it
+        let mut new = old.clone();
+        let mut intro = new.lines[0].clone();
+        intro.text = "intro".to_string();
+        new.lines.insert(0, intro);
+        new.parts[0].start = 1;
+        new.parts[0].end = 2;
+        new.parts[1].start = 2;
+        new.parts[1].end = 4;
+        let changes = old.diff(&new);
+        assert!(matches!(changes[..], [Change::LineAdded { line: 0, .. }]));
+
+        // a chorus line moved into the verse is a parts change
+        let mut new = old.clone();
+        new.parts[0].end = 2;
+        new.parts[1].start = 2;
+        let changes = old.diff(&new);
+        assert!(matches!(changes[..], [Change::PartsChanged { .. }]));
+    }
+}
@@ -0,0 +1,271 @@
+use std::fmt;
+
+use super::{AnimationData, Keyframe, TextSegment, split_top_level, strip_group};
+
+// compact [lbl]/[lsk] sections start with this, the parser switches on it
+const COMPACT_PREFIX: char = '~';
+
+// how `compile_with` writes the data sections. parsing detects every variant
+// on its own, so these only matter for the writer.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WriteOptions {
+    // integer milliseconds, line starts as a delta from the previous line end,
+    // ends as durations, and `<`/`>` for the usual first and last keyframe:
+    //   [lbl][~3920/7112,100/0]
+    //   [lsk][~(<400/179,5400/893>),()]
+    pub compact: bool,
+    // notation of the times in non-compact sections
+    pub notation: TimeNotation,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum TimeNotation {
+    // canonical `81.373`
+    #[default]
+    Seconds,
+    // `01:21.373`, or `01:01:21.373` past the hour
+    Clock,
+}
+
+pub fn format_time(secs: f32, notation: TimeNotation) -> String {
+    match notation {
+        TimeNotation::Seconds => format!("{:.3}", secs),
+        TimeNotation::Clock => {
+            let total = ms(secs).max(0);
+            let (h, m, s, frac) = (
+                total / 3_600_000,
+                total / 60_000 % 60,
+                total / 1000 % 60,
+                total % 1000,
+            );
+            if h > 0 {
+                format!("{:02}:{:02}:{:02}.{:03}", h, m, s, frac)
+            } else {
+                format!("{:02}:{:02}.{:03}", m, s, frac)
+            }
+        }
+    }
+}
+
+// accepts `81.373`, `mm:ss.mmm` and `hh:mm:ss.mmm`
+pub fn parse_time(s: &str) -> Option<f32> {
+    let s = s.trim();
+    if !s.contains(':') {
+        return s.parse().ok();
+    }
+
+    let fields: Vec<&str> = s.split(':').collect();
+    if fields.len() > 3 {
+        return None;
+    }
+    let (seconds, whole) = fields.split_last()?;
+    let seconds: f64 = seconds.parse().ok()?;
+    if !(0.0..60.0).contains(&seconds) {
+        return None;
+    }
+    let mut total = 0.0;
+    for (i, field) in whole.iter().enumerate() {
+        let value: u32 = field.parse().ok()?;
+        // minutes below hours only go up to 59
+        if i > 0 && value >= 60 {
+            return None;
+        }
+        total = total * 60.0 + value as f64;
+    }
+    Some((total * 60.0 + seconds) as f32)
+}
+
+pub(super) struct Compiled<'a> {
+    pub data: &'a AnimationData,
+    pub options: &'a WriteOptions,
+}
+
+impl fmt::Display for Compiled<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.data.write_document(f, self.options)
+    }
+}
+
+impl AnimationData {
+    pub fn compile_with(&self, options: &WriteOptions) -> String {
+        Compiled {
+            data: self,
+            options,
+        }
+        .to_string()
+    }
+}
+
+fn ms(secs: f32) -> i64 {
+    (secs * 1000.0).round() as i64
+}
+
+fn secs(ms: i64) -> f32 {
+    ms as f32 / 1000.0
+}
+
+// the part after `~` for compact sections
+pub(super) fn strip_compact(section: &str) -> Option<&str> {
+    section.trim().strip_prefix(COMPACT_PREFIX)
+}
+
+pub(super) fn write_compact_timestamps(lines: &[TextSegment]) -> String {
+    let mut prev_end = 0;
+    let entries: Vec<String> = lines
+        .iter()
+        .map(|line| {
+            let (start, end) = (ms(line.start), ms(line.end));
+            let entry = format!("{}/{}", start - prev_end, end - start);
+            prev_end = end;
+            entry
+        })
+        .collect();
+    format!("{}{}", COMPACT_PREFIX, entries.join(","))
+}
+
+pub(super) fn parse_compact_timestamps(lines: &mut [TextSegment], timestamps: &str) {
+    let mut prev_end = 0;
+    for (line, entry) in lines.iter_mut().zip(timestamps.split(',')) {
+        let Some((gap, duration)) = entry.split_once('/') else {
+            continue;
+        };
+        let gap = gap.trim().parse::<i64>().unwrap_or(0);
+        let duration = duration.trim().parse::<i64>().unwrap_or(0);
+        let start = prev_end + gap;
+        line.start = secs(start);
+        line.end = secs(start + duration);
+        prev_end = start + duration;
+    }
+}
+
+fn permille(index: f32, line_len: f32) -> i64 {
+    if line_len > 0.0 {
+        (index / line_len * 1000.0).round() as i64
+    } else {
+        0
+    }
+}
+
+pub(super) fn write_compact_keyframes(lines: &[TextSegment]) -> String {
+    let groups: Vec<String> = lines
+        .iter()
+        .map(|line| {
+            let line_len = line.text.len() as f32;
+            let duration = ms(line.end) - ms(line.start);
+            let encoded: Vec<(i64, i64)> = line
+                .keyframes
+                .iter()
+                .map(|kf| (ms(kf.time), permille(kf.index, line_len)))
+                .collect();
+
+            let mut inner = &encoded[..];
+            let starts = inner.first() == Some(&(0, 0));
+            if starts {
+                inner = &inner[1..];
+            }
+            let ends = inner.last() == Some(&(duration, 1000));
+            if ends {
+                inner = &inner[..inner.len() - 1];
+            }
+
+            let entries: Vec<String> = inner.iter().map(|(t, p)| format!("{}/{}", t, p)).collect();
+            format!(
+                "({}{}{})",
+                if starts { "<" } else { "" },
+                entries.join(","),
+                if ends { ">" } else { "" }
+            )
+        })
+        .collect();
+    format!("{}{}", COMPACT_PREFIX, groups.join(","))
+}
+
+pub(super) fn parse_compact_keyframes(lines: &mut [TextSegment], keyframes: &str) {
+    for (line, group) in lines.iter_mut().zip(split_top_level(keyframes, ',')) {
+        let line_len = line.text.len() as f32;
+        let mut group = strip_group(group).trim();
+
+        if let Some(rest) = group.strip_prefix('<') {
+            line.keyframes.push(Keyframe {
+                time: 0.0,
+                index: 0.0,
+            });
+            group = rest;
+        }
+        let ends = group.ends_with('>');
+        group = group.trim_end_matches('>');
+
+        for entry in group.split(',').filter(|e| !e.trim().is_empty()) {
+            let Some((time, pct)) = entry.split_once('/') else {
+                continue;
+            };
+            let (Ok(time), Ok(pct)) = (time.trim().parse::<i64>(), pct.trim().parse::<i64>())
+            else {
+                continue;
+            };
+            line.keyframes.push(Keyframe {
+                time: secs(time),
+                index: pct as f32 / 1000.0 * line_len,
+            });
+        }
+
+        if ends {
+            line.keyframes.push(Keyframe {
+                time: secs(ms(line.end) - ms(line.start)),
+                index: line_len,
+            });
+        }
+        line.sort_keyframes();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TimeNotation, format_time, parse_time};
+    use crate::model::{AnimationData, WriteOptions};
+
+    #[test]
+    fn test_compact_round_trip() {
+        let mut data = AnimationData::default();
+        data.add_line("City of stars", 3.92, 11.032)
+            .add_keyframe(0.0, 0.0)
+            .add_keyframe(0.4, 2.327)
+            .add_keyframe(7.112, 13.0);
+        data.add_line("", 11.132, 11.132);
+        data.add_line("late", 10.5, 12.0).add_keyframe(0.25, 2.0);
+
+        let compact = data.compile_with(&WriteOptions {
+            compact: true,
+            ..Default::default()
+        });
+        assert!(compact.contains("[lbl][~3920/7112,100/0,-632/1500]"));
+        assert!(compact.contains("[lsk][~(<400/179>),(),(250/500)]"));
+
+        let parsed = compact.parse::<AnimationData>().unwrap();
+        assert_eq!(parsed.compile(), data.compile());
+    }
+
+    #[test]
+    fn test_clock_notation() {
+        assert_eq!(parse_time("81.373"), Some(81.373));
+        assert_eq!(parse_time("01:21.373"), Some(81.373));
+        assert_eq!(parse_time("1:01:21.5"), Some(3681.5));
+        assert_eq!(parse_time("01:75.000"), None);
+        assert_eq!(format_time(3681.5, TimeNotation::Clock), "01:01:21.500");
+
+        let mut data = AnimationData::default();
+        data.add_line("City of stars", 81.373, 87.357)
+            .add_keyframe(0.0, 0.0)
+            .add_keyframe(1.2, 13.0);
+        let clock = data.compile_with(&WriteOptions {
+            notation: TimeNotation::Clock,
+            ..Default::default()
+        });
+        assert!(clock.contains("[lbl][01:21.373/01:27.357]"));
+        assert!(clock.contains("[lsk][(00:00.000/0.000,00:01.200/1.000)]"));
+        assert_eq!(
+            clock.parse::<AnimationData>().unwrap().compile(),
+            data.compile()
+        );
+    }
+}
@@ -0,0 +1,117 @@
+use serde::{Deserialize, Serialize};
+
+use super::AnimationData;
+
+// JSON interchange format, version 1. times are seconds (floats), line and
+// char positions are 0-based.
+//
+// {
+//   "version": 1,
+//   "lines": [{
+//     "text": "City of stars",
+//     "start": 3.92,              // absolute line start
+//     "end": 11.032,              // absolute line end
+//     "keyframes": [              // optional, empty = linear sweep over the line
+//       { "time": 0.0, "index": 0.0 }  // time relative to `start`, index in bytes of `text`
+//     ],
+//     "agent": "v1",              // optional, who sings the line (a TTML agent id)
+//     "ruby": [{                  // optional
+//       "start": 0, "end": 1,     // base text chars [start, end)
+//       "text": "reading",
+//       "keyframes": []           // time relative to the line start, index in chars of `text`
+//     }]
+//   }],
+//   "parts": [{                   // optional
+//     "name": "Chorus",
+//     "start": 0, "end": 4,       // lines [start, end)
+//     "repeat": { "source": 0, "offset": 30.0 }  // optional, mirrors part `source` shifted by `offset`
+//   }]
+// }
+//
+// readers reject documents with a newer version than they know.
+pub const JSON_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct JsonDocument {
+    version: u32,
+    #[serde(flatten)]
+    data: AnimationData,
+}
+
+impl AnimationData {
+    pub fn to_json(&self) -> Result<String, String> {
+        let doc = JsonDocument {
+            version: JSON_SCHEMA_VERSION,
+            data: self.clone(),
+        };
+        serde_json::to_string_pretty(&doc).map_err(|e| e.to_string())
+    }
+
+    pub fn from_json(input: &str) -> Result<AnimationData, String> {
+        let doc: JsonDocument = serde_json::from_str(input).map_err(|e| e.to_string())?;
+        if doc.version > JSON_SCHEMA_VERSION {
+            return Err(format!(
+                "JSON schema version {} is newer than the supported {}",
+                doc.version, JSON_SCHEMA_VERSION
+            ));
+        }
+        let mut data = doc.data;
+        data.check_parts()?;
+        data.sync_repeats();
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::AnimationData;
+
+    #[test]
+    fn test_json_round_trip() {
+        let mut data = AnimationData::default();
+        data.add_line("City of stars", 1.0, 3.0)
+            .add_keyframe(0.0, 0.0)
+            .add_keyframe(2.0, 13.0)
+            .add_ruby(0, 4, "shi");
+        data.add_line("are you shining", 3.0, 5.0);
+        data.add_part("Chorus", 0, 2);
+
+        let json = data.to_json().unwrap();
+        assert!(json.contains("\"version\": 1"));
+        assert_eq!(AnimationData::from_json(&json).unwrap(), data);
+
+        let minimal = r#"{"version": 1, "lines": [{"text": "hi", "start": 0.5, "end": 1.0}]}"#;
+        let parsed = AnimationData::from_json(minimal).unwrap();
+        assert_eq!(parsed.lines[0].text, "hi");
+        assert!(parsed.lines[0].keyframes.is_empty());
+
+        assert!(AnimationData::from_json(r#"{"version": 2, "lines": []}"#).is_err());
+    }
+
+    #[test]
+    fn test_json_parts_are_checked() {
+        let doc = |parts: &str| {
+            format!(
+                r#"{{"version": 1, "lines": [{{"text": "a", "start": 0, "end": 1}}, {{"text": "b", "start": 1, "end": 2}}], "parts": {}}}"#,
+                parts
+            )
+        };
+        let past_the_end = doc(
+            r#"[{"name": "A", "start": 0, "end": 5}, {"name": "B", "start": 5, "end": 5, "repeat": {"source": 0, "offset": 2}}]"#,
+        );
+        assert!(AnimationData::from_json(&past_the_end).is_err());
+        let own_source = doc(
+            r#"[{"name": "A", "start": 0, "end": 1}, {"name": "B", "start": 1, "end": 2, "repeat": {"source": 1, "offset": 2}}]"#,
+        );
+        assert!(AnimationData::from_json(&own_source).is_err());
+        let backwards = doc(r#"[{"name": "A", "start": 2, "end": 1}]"#);
+        assert!(AnimationData::from_json(&backwards).is_err());
+
+        let fine = doc(
+            r#"[{"name": "A", "start": 0, "end": 1}, {"name": "B", "start": 1, "end": 2, "repeat": {"source": 0, "offset": 2}}]"#,
+        );
+        let data = AnimationData::from_json(&fine).unwrap();
+        assert_eq!(data.lines[1].text, "a");
+        assert_eq!(data.lines[1].start, 2.0);
+    }
+}
@@ -0,0 +1,173 @@
+use super::format::parse_time;
+use super::{AnimationData, Keyframe, TextSegment};
+
+// the last line of a file without a closing timestamp gets this long
+const LAST_LINE_SECS: f32 = 5.0;
+
+// `mm:ss.xx`, as LRC players expect centiseconds
+fn lrc_time(secs: f32) -> String {
+    let cs = (secs.max(0.0) * 100.0).round() as i64;
+    format!("{:02}:{:02}.{:02}", cs / 6000, cs / 100 % 60, cs % 100)
+}
+
+// leading `[time]` tags of a line, metadata tags like `[ar:...]` yield None
+fn split_time_tags(line: &str) -> Option<(Vec<f32>, &str)> {
+    let mut times = Vec::new();
+    let mut rest = line.trim_start();
+    while let Some(inner) = rest.strip_prefix('[') {
+        let close = inner.find(']')?;
+        times.push(parse_time(&inner[..close])?);
+        rest = &inner[close + 1..];
+    }
+    if times.is_empty() {
+        None
+    } else {
+        Some((times, rest))
+    }
+}
+
+// strips enhanced `<time>` word tags, returning the text and (absolute time, byte index) pairs
+fn split_word_tags(body: &str) -> (String, Vec<(f32, usize)>) {
+    let mut text = String::new();
+    let mut tags = Vec::new();
+    let mut rest = body;
+    while let Some(open) = rest.find('<') {
+        let Some(close) = rest[open..].find('>') else {
+            break;
+        };
+        match parse_time(&rest[open + 1..open + close]) {
+            Some(t) => {
+                text.push_str(&rest[..open]);
+                tags.push((t, text.len()));
+            }
+            None => text.push_str(&rest[..open + close + 1]),
+        }
+        rest = &rest[open + close + 1..];
+    }
+    text.push_str(rest);
+
+    // tags may sit before the spaces between words
+    let trimmed = text.trim_start().len();
+    let lead = text.len() - trimmed;
+    let text = text.trim().to_string();
+    let tags = tags
+        .into_iter()
+        .map(|(t, i)| (t, i.saturating_sub(lead).min(text.len())))
+        .collect();
+    (text, tags)
+}
+
+impl AnimationData {
+    // plain and enhanced (`<mm:ss.xx>` per word) LRC. a line ends where the next begins.
+    pub fn from_lrc(input: &str) -> Result<Self, String> {
+        let mut entries: Vec<(f32, &str)> = Vec::new();
+        for line in input.lines() {
+            if let Some((times, body)) = split_time_tags(line) {
+                entries.extend(times.into_iter().map(|t| (t, body)));
+            }
+        }
+        if entries.is_empty() {
+            return Err("No timed lines found".to_string());
+        }
+        entries.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+
+        let mut data = AnimationData::default();
+        for (i, &(start, body)) in entries.iter().enumerate() {
+            let (text, tags) = split_word_tags(body);
+            let last_tag = tags.last().map_or(start, |t| t.0);
+            let end = entries
+                .get(i + 1)
+                .map_or(last_tag.max(start) + LAST_LINE_SECS, |next| next.0);
+
+            let mut line = TextSegment::new(text, start, end);
+            if !tags.is_empty() {
+                let len = line.text.len() as f32;
+                for (t, index) in tags {
+                    line.keyframes.push(Keyframe {
+                        time: (t - start).clamp(0.0, end - start),
+                        index: index as f32,
+                    });
+                }
+                if line.keyframes[0].index > 0.0 {
+                    line.keyframes.insert(
+                        0,
+                        Keyframe {
+                            time: 0.0,
+                            index: 0.0,
+                        },
+                    );
+                }
+                if line.keyframes.last().is_some_and(|k| k.index < len) {
+                    line.keyframes.push(Keyframe {
+                        time: end - start,
+                        index: len,
+                    });
+                }
+                line.sort_keyframes();
+            }
+            data.lines.push(line);
+        }
+        Ok(data)
+    }
+
+    // keyframed lines get a `<time>` tag per word, gaps an empty timestamp line
+    pub fn to_lrc(&self) -> String {
+        let mut out = String::new();
+        let lines: Vec<&TextSegment> = self
+            .lines
+            .iter()
+            .filter(|l| !l.text.trim().is_empty())
+            .collect();
+
+        for (i, line) in lines.iter().enumerate() {
+            out.push_str(&format!("[{}]", lrc_time(line.start)));
+            if line.keyframes.is_empty() {
+                out.push_str(line.text.trim());
+            } else {
+                let mut prev_space = true;
+                for (bi, c) in line.text.char_indices() {
+                    if prev_space && !c.is_whitespace() {
+                        let t = line.start + line.time_at_index(bi as f32);
+                        out.push_str(&format!("<{}>", lrc_time(t)));
+                    }
+                    prev_space = c.is_whitespace();
+                    out.push(c);
+                }
+            }
+            out.push('\n');
+
+            let next_start = lines.get(i + 1).map(|n| n.start);
+            if next_start.is_none_or(|s| s - line.end > 0.01) {
+                out.push_str(&format!("[{}]\n", lrc_time(line.end)));
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::AnimationData;
+
+    #[test]
+    fn test_lrc_round_trip() {
+        let input = "[ar:Someone]\n[00:03.92]City of stars\n[00:11.03]\n[00:12.00]<00:12.00>are <00:12.50>you <00:13.00>shining\n[00:14.00]\n";
+        let data = AnimationData::from_lrc(input).unwrap();
+        assert_eq!(data.lines.len(), 4);
+        assert_eq!(data.lines[0].text, "City of stars");
+        assert_eq!((data.lines[0].start, data.lines[0].end), (3.92, 11.03));
+        assert_eq!(data.lines[1].text, "");
+
+        let line = &data.lines[2];
+        assert_eq!(line.text, "are you shining");
+        assert_eq!(line.end, 14.0);
+        assert_eq!(line.keyframes[1].index, 4.0);
+        assert_eq!(line.keyframes[1].time, 0.5);
+        assert_eq!(line.keyframes.last().unwrap().index, 15.0);
+
+        assert_eq!(
+            data.to_lrc(),
+            "[00:03.92]City of stars\n[00:11.03]\n[00:12.00]<00:12.00>are <00:12.50>you <00:13.00>shining\n[00:14.00]\n"
+        );
+    }
+}
@@ -0,0 +1,367 @@
+use std::fmt;
+
+use super::align::lcs_matches;
+use super::{AnimationData, Keyframe, Part, Ruby, TextSegment};
+
+// a field both sides changed differently; the merged document keeps ours
+#[derive(Clone, Debug, PartialEq)]
+pub struct Conflict {
+    // line in the merged document, None for document-level fields
+    pub line: Option<usize>,
+    pub field: &'static str,
+    pub base: String,
+    pub ours: String,
+    pub theirs: String,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {} {}", line + 1, self.field)?,
+            None => write!(f, "{}", self.field)?,
+        }
+        write!(
+            f,
+            ": base {}, ours {}, theirs {}",
+            self.base, self.ours, self.theirs
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Merge {
+    pub data: AnimationData,
+    pub conflicts: Vec<Conflict>,
+}
+
+// takes whichever side changed, None if both changed it differently
+fn pick<T: PartialEq + Clone>(base: &T, ours: &T, theirs: &T) -> Option<T> {
+    if ours == theirs || theirs == base {
+        Some(ours.clone())
+    } else if ours == base {
+        Some(theirs.clone())
+    } else {
+        None
+    }
+}
+
+fn describe_keyframes(keyframes: &[Keyframe]) -> String {
+    let entries: Vec<String> = keyframes
+        .iter()
+        .map(|k| format!("{:.3}@{}", k.time, k.index))
+        .collect();
+    format!("[{}]", entries.join(","))
+}
+
+fn describe_ruby(ruby: &[Ruby]) -> String {
+    let entries: Vec<String> = ruby.iter().map(Ruby::to_string_entry).collect();
+    format!("[{}]", entries.join(","))
+}
+
+// `parts` of one side with their lines moved to where they ended up in the merge
+fn map_parts(parts: &[Part], at: &[usize]) -> Vec<Part> {
+    parts
+        .iter()
+        .map(|p| {
+            let mut part = p.clone();
+            part.start = at[p.start.min(at.len() - 1)];
+            part.end = at[p.end.min(at.len() - 1)];
+            part
+        })
+        .collect()
+}
+
+fn describe_lines(lines: &[TextSegment]) -> String {
+    let texts: Vec<String> = lines.iter().map(|l| format!("{:?}", l.text)).collect();
+    format!("[{}]", texts.join(", "))
+}
+
+struct Merger {
+    lines: Vec<TextSegment>,
+    conflicts: Vec<Conflict>,
+}
+
+impl Merger {
+    fn field<T: PartialEq + Clone>(
+        &mut self,
+        name: &'static str,
+        (base, ours, theirs): (&T, &T, &T),
+        describe: impl Fn(&T) -> String,
+    ) -> T {
+        pick(base, ours, theirs).unwrap_or_else(|| {
+            self.conflicts.push(Conflict {
+                line: Some(self.lines.len()),
+                field: name,
+                base: describe(base),
+                ours: describe(ours),
+                theirs: describe(theirs),
+            });
+            ours.clone()
+        })
+    }
+
+    fn line(&mut self, base: &TextSegment, ours: &TextSegment, theirs: &TextSegment) {
+        let text = self.field("text", (&base.text, &ours.text, &theirs.text), |t| {
+            format!("{:?}", t)
+        });
+        let secs = |t: &f32| format!("{:.3}", t);
+        let start = self.field("start", (&base.start, &ours.start, &theirs.start), secs);
+        let end = self.field("end", (&base.end, &ours.end, &theirs.end), secs);
+
+        // same keyframe count on all sides merges keyframe by keyframe
+        let keyframes = if base.keyframes.len() == ours.keyframes.len()
+            && base.keyframes.len() == theirs.keyframes.len()
+        {
+            let mut keyframes = Vec::with_capacity(base.keyframes.len());
+            let mut clash = false;
+            for ((b, o), t) in base
+                .keyframes
+                .iter()
+                .zip(&ours.keyframes)
+                .zip(&theirs.keyframes)
+            {
+                keyframes.push(pick(b, o, t).unwrap_or_else(|| {
+                    clash = true;
+                    o.clone()
+                }));
+            }
+            if clash {
+                self.conflicts.push(Conflict {
+                    line: Some(self.lines.len()),
+                    field: "keyframes",
+                    base: describe_keyframes(&base.keyframes),
+                    ours: describe_keyframes(&ours.keyframes),
+                    theirs: describe_keyframes(&theirs.keyframes),
+                });
+            }
+            keyframes
+        } else {
+            self.field(
+                "keyframes",
+                (&base.keyframes, &ours.keyframes, &theirs.keyframes),
+                |k| describe_keyframes(k),
+            )
+        };
+        let ruby = self.field("ruby", (&base.ruby, &ours.ruby, &theirs.ruby), |r| {
+            describe_ruby(r)
+        });
+
+        let agent = self.field("agent", (&base.agent, &ours.agent, &theirs.agent), |a| {
+            a.clone().unwrap_or_default()
+        });
+
+        let mut line = TextSegment {
+            text,
+            keyframes,
+            start,
+            end,
+            ruby,
+            agent,
+        };
+        line.sort_keyframes();
+        self.lines.push(line);
+    }
+
+    // lines between two anchors that at least one side inserted, removed or rewrote
+    fn region(&mut self, base: &[TextSegment], ours: &[TextSegment], theirs: &[TextSegment]) {
+        if ours == base {
+            self.lines.extend_from_slice(theirs);
+        } else if theirs == base || ours == theirs {
+            self.lines.extend_from_slice(ours);
+        } else if base.len() == ours.len() && base.len() == theirs.len() {
+            for ((b, o), t) in base.iter().zip(ours).zip(theirs) {
+                self.line(b, o, t);
+            }
+        } else {
+            self.conflicts.push(Conflict {
+                line: Some(self.lines.len()),
+                field: "lines",
+                base: describe_lines(base),
+                ours: describe_lines(ours),
+                theirs: describe_lines(theirs),
+            });
+            self.lines.extend_from_slice(ours);
+        }
+    }
+}
+
+impl AnimationData {
+    // three-way merge of `ours` and `theirs` against their common `base`.
+    // lines are matched by text, then merged field by field and keyframe by keyframe.
+    pub fn merge(base: &AnimationData, ours: &AnimationData, theirs: &AnimationData) -> Merge {
+        let same_text = |a: &TextSegment, b: &TextSegment| a.text == b.text;
+        let mut in_ours = vec![None; base.lines.len()];
+        for (oi, bi) in lcs_matches(&base.lines, &ours.lines, same_text)
+            .into_iter()
+            .enumerate()
+        {
+            if let Some(bi) = bi {
+                in_ours[bi] = Some(oi);
+            }
+        }
+        let mut in_theirs = vec![None; base.lines.len()];
+        for (ti, bi) in lcs_matches(&base.lines, &theirs.lines, same_text)
+            .into_iter()
+            .enumerate()
+        {
+            if let Some(bi) = bi {
+                in_theirs[bi] = Some(ti);
+            }
+        }
+
+        // base lines kept by both sides anchor the merge
+        let anchors = (0..base.lines.len())
+            .filter_map(|bi| Some((bi, in_ours[bi]?, in_theirs[bi]?)))
+            .chain(std::iter::once((
+                base.lines.len(),
+                ours.lines.len(),
+                theirs.lines.len(),
+            )));
+
+        let mut merger = Merger {
+            lines: Vec::new(),
+            conflicts: Vec::new(),
+        };
+        // where each line of a side lands in the merge, plus its end. lines of a
+        // region land in order as far as the merged region has room
+        let mut at = [
+            vec![0; base.lines.len() + 1],
+            vec![0; ours.lines.len() + 1],
+            vec![0; theirs.lines.len() + 1],
+        ];
+        let (mut pb, mut po, mut pt) = (0, 0, 0);
+        for (bi, oi, ti) in anchors {
+            let from = merger.lines.len();
+            merger.region(
+                &base.lines[pb..bi],
+                &ours.lines[po..oi],
+                &theirs.lines[pt..ti],
+            );
+            let to = merger.lines.len();
+            for (side, range) in at.iter_mut().zip([pb..bi, po..oi, pt..ti]) {
+                let start = range.start;
+                for k in range {
+                    side[k] = from + (k - start).min(to - from);
+                }
+            }
+            if bi < base.lines.len() {
+                for (side, k) in at.iter_mut().zip([bi, oi, ti]) {
+                    side[k] = to;
+                }
+                merger.line(&base.lines[bi], &ours.lines[oi], &theirs.lines[ti]);
+            }
+            (pb, po, pt) = (bi + 1, oi + 1, ti + 1);
+        }
+        let len = merger.lines.len();
+        for side in &mut at {
+            *side.last_mut().unwrap() = len;
+        }
+
+        // part ranges are compared where their lines ended up
+        let [at_base, at_ours, at_theirs] = &at;
+        let mapped = (
+            map_parts(&base.parts, at_base),
+            map_parts(&ours.parts, at_ours),
+            map_parts(&theirs.parts, at_theirs),
+        );
+        let parts = pick(&mapped.0, &mapped.1, &mapped.2).unwrap_or_else(|| {
+            let describe = |data: &AnimationData| {
+                let names: Vec<String> = data.parts.iter().map(|p| p.to_string_entry()).collect();
+                format!("[{}]", names.join(","))
+            };
+            merger.conflicts.push(Conflict {
+                line: None,
+                field: "parts",
+                base: describe(base),
+                ours: describe(ours),
+                theirs: describe(theirs),
+            });
+            mapped.1.clone()
+        });
+
+        let mut data = AnimationData {
+            lines: merger.lines,
+            parts,
+        };
+        data.sync_repeats();
+        Merge {
+            data,
+            conflicts: merger.conflicts,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::{AnimationData, TextSegment};
+
+    #[test]
+    fn test_three_way_merge() {
+        let mut base = AnimationData::default();
+        base.add_line("one", 0.0, 2.0)
+            .add_keyframe(0.0, 0.0)
+            .add_keyframe(1.0, 1.0)
+            .add_keyframe(2.0, 3.0);
+        base.add_line("two", 2.0, 4.0);
+        base.add_line("three", 4.0, 6.0);
+
+        let mut ours = base.clone();
+        ours.lines[0].start = 0.5;
+        ours.lines[2].end = 7.0;
+        let mut theirs = base.clone();
+        theirs.lines[0].keyframes[1].time = 1.5;
+        theirs.insert_line(
+            2,
+            TextSegment {
+                text: "two and a half".to_string(),
+                keyframes: vec![],
+                start: 3.0,
+                end: 4.0,
+                ruby: vec![],
+                agent: None,
+            },
+        );
+
+        let merged = AnimationData::merge(&base, &ours, &theirs);
+        assert!(merged.conflicts.is_empty());
+        let lines = &merged.data.lines;
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0].start, 0.5);
+        assert_eq!(lines[0].keyframes[1].time, 1.5);
+        assert_eq!(lines[2].text, "two and a half");
+        assert_eq!(lines[3].end, 7.0);
+
+        // both moved the same line end somewhere else
+        theirs.lines[3].end = 8.0;
+        let merged = AnimationData::merge(&base, &ours, &theirs);
+        assert_eq!(merged.conflicts.len(), 1);
+        assert_eq!(merged.conflicts[0].line, Some(3));
+        assert_eq!(merged.conflicts[0].field, "end");
+        assert_eq!(merged.data.lines[3].end, 7.0);
+    }
+
+    #[test]
+    fn test_merge_parts_follow_inserted_lines() {
+        let mut base = AnimationData::default();
+        for (i, text) in ["a1", "a2", "b1", "b2"].iter().enumerate() {
+            base.add_line(text, i as f32, i as f32 + 1.0);
+        }
+        base.add_part("A", 0, 2);
+        base.add_part("B", 2, 4);
+
+        let line = |text: &str| TextSegment::new(text.to_string(), 9.0, 10.0);
+        let mut ours = base.clone();
+        ours.insert_line(0, line("a0"));
+        ours.parts[0].start = 0;
+        let mut theirs = base.clone();
+        theirs.insert_line(4, line("b3"));
+
+        let merged = AnimationData::merge(&base, &ours, &theirs);
+        assert!(merged.conflicts.is_empty(), "{:?}", merged.conflicts);
+        let texts: Vec<&str> = merged.data.lines.iter().map(|l| l.text.as_str()).collect();
+        assert_eq!(texts, ["a0", "a1", "a2", "b1", "b2", "b3"]);
+        let ranges: Vec<(usize, usize)> =
+            merged.data.parts.iter().map(|p| (p.start, p.end)).collect();
+        assert_eq!(ranges, [(0, 3), (3, 6)]);
+    }
+}
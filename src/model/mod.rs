@@ -1,61 +1,222 @@
 use std::fmt;
+use std::ops::Range;
 use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+mod align;
+mod diff;
+mod format;
+mod json;
+mod lrc;
+mod merge;
+mod parts;
+mod syllables;
+mod timing;
 mod ttml;
+mod validate;
+
+pub use align::Realignment;
+pub use diff::{Change, render_changes, render_changes_json};
+pub use format::{TimeNotation, WriteOptions, format_time, parse_time};
+pub use json::JSON_SCHEMA_VERSION;
+pub use merge::{Conflict, Merge};
+pub use parts::{Part, Repeat};
+pub use syllables::{Split, byte_offset, split_points};
+pub use validate::{Diagnostic, DiagnosticKind, Fix, Severity, validate_document};
 
 static DATA_SECTION_SPLIT_MARKER: &str = "\n\n[//]";
 static LINE_BY_LINE_TIMESTAMP_MARKER: &str = "[lbl]";
 static LINE_SYLABLE_KEYFRAME_MARKER: &str = "[lsk]";
+static LINE_RUBY_MARKER: &str = "[rby]";
+static SONG_PART_MARKER: &str = "[prt]";
+static LINE_AGENT_MARKER: &str = "[agt]";
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Keyframe {
     pub time: f32,
     pub index: f32, // TODO: use progress
 }
 
 // represents segments of a lyrics.
-// sorted by time. segements with [bracketed sections] are first element of the group.
-#[derive(Clone, Debug, PartialEq)]
+// sorted by time. [bracketed sections] are tracked as `Part`s on AnimationData.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct TextSegment {
     pub text: String,
+    #[serde(default)]
     pub keyframes: Vec<Keyframe>,
-    pub part: Option<String>,
     pub start: f32,
     pub end: f32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ruby: Vec<Ruby>,
+    // who sings the line, a TTML agent id like "v1"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent: Option<String>,
+}
+
+// ruby / furigana reading over the base text chars [start, end).
+// keyframes are relative to the line start, index counts chars of the reading.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Ruby {
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+    #[serde(default)]
+    pub keyframes: Vec<Keyframe>,
 }
 
-#[derive(Clone, Debug, Default, PartialEq)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct AnimationData {
     pub lines: Vec<TextSegment>,
+    #[serde(default)]
+    pub parts: Vec<Part>,
 }
 
 impl Keyframe {
     pub fn to_string_pct(&self, line_len: f32) -> String {
+        self.to_string_notation(line_len, TimeNotation::Seconds)
+    }
+
+    pub fn to_string_notation(&self, line_len: f32, notation: TimeNotation) -> String {
         let pct = if line_len > 0.0 {
             self.index / line_len
         } else {
             0.0
         };
-        format!("{:.3}/{:.3}", self.time, pct)
+        format!("{}/{:.3}", format::format_time(self.time, notation), pct)
     }
 
     // Helper: requires context
     pub fn from_string_pct(s: &str, line_len: f32) -> Option<Keyframe> {
         let (time_str, pct_str) = s.split_once('/')?;
-        let time: f32 = time_str.parse().ok()?;
+        let time = format::parse_time(time_str)?;
         let pct: f32 = pct_str.parse().ok()?;
         let index = pct * line_len;
         Some(Keyframe { time, index })
     }
 }
 
+fn interpolate_index(keyframes: &[Keyframe], rel_time: f32) -> Option<f32> {
+    for pair in keyframes.windows(2) {
+        let (k1, k2) = (&pair[0], &pair[1]);
+        if rel_time >= k1.time && rel_time <= k2.time {
+            let t = (rel_time - k1.time) / (k2.time - k1.time);
+            return Some(k1.index + (k2.index - k1.index) * t);
+        }
+    }
+    keyframes.last().map(|k| k.index)
+}
+
+// splits on `sep` outside of parentheses: "(a,b),(c)" -> ["(a,b)", "(c)"]
+fn split_top_level(s: &str, sep: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut last = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            c if c == sep && depth == 0 => {
+                parts.push(&s[last..i]);
+                last = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&s[last..]);
+    parts
+}
+
+fn strip_group(s: &str) -> &str {
+    let s = s.trim();
+    let s = s.strip_prefix('(').unwrap_or(s);
+    s.strip_suffix(')').unwrap_or(s)
+}
+
+impl Ruby {
+    pub fn new(start: usize, end: usize, text: &str) -> Self {
+        Self {
+            start,
+            end,
+            text: text.to_string(),
+            keyframes: Vec::new(),
+        }
+    }
+
+    pub fn reading_len(&self) -> usize {
+        self.text.chars().count()
+    }
+
+    // without keyframes the reading follows the progress over its base range
+    pub fn get_current_index(&self, rel_time: f32, base_index: f32) -> f32 {
+        if let Some(index) = interpolate_index(&self.keyframes, rel_time) {
+            return index;
+        }
+        let span = (self.end - self.start).max(1) as f32;
+        let t = ((base_index - self.start as f32) / span).clamp(0.0, 1.0);
+        self.reading_len() as f32 * t
+    }
+
+    pub fn add_keyframe(&mut self, time: f32, index: f32) -> &mut Self {
+        self.keyframes.push(Keyframe { time, index });
+        self.keyframes.sort_by(|a, b| {
+            a.time
+                .partial_cmp(&b.time)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        self
+    }
+
+    // start/end/reading followed by optional (time/pct,...) keyframes
+    pub fn to_string_entry(&self) -> String {
+        let reading: String = self
+            .text
+            .chars()
+            .filter(|&c| !c.is_control() && !"/[](),".contains(c))
+            .collect();
+        let mut entry = format!("{}/{}/{}", self.start, self.end, reading);
+        if !self.keyframes.is_empty() {
+            let reading_len = reading.chars().count() as f32;
+            let kfs: Vec<String> = self
+                .keyframes
+                .iter()
+                .map(|kf| kf.to_string_pct(reading_len))
+                .collect();
+            entry.push_str(&format!("({})", kfs.join(",")));
+        }
+        entry
+    }
+
+    pub fn from_string_entry(s: &str) -> Option<Ruby> {
+        let s = s.trim();
+        let (head, kfs) = match s.find('(') {
+            Some(i) => (&s[..i], Some(strip_group(&s[i..]))),
+            None => (s, None),
+        };
+        let mut fields = head.splitn(3, '/');
+        let start: usize = fields.next()?.trim().parse().ok()?;
+        let end: usize = fields.next()?.trim().parse().ok()?;
+        let mut ruby = Ruby::new(start, end.max(start), fields.next()?);
+
+        let reading_len = ruby.reading_len() as f32;
+        for kf_entry in kfs.into_iter().flat_map(|g| g.split(',')) {
+            if let Some(kf) = Keyframe::from_string_pct(kf_entry.trim(), reading_len) {
+                ruby.add_keyframe(kf.time, kf.index);
+            }
+        }
+        Some(ruby)
+    }
+}
+
 impl TextSegment {
     pub fn new(text: String, start: f32, end: f32) -> Self {
         Self {
             text,
             start,
             end,
-            part: None,
             keyframes: Vec::new(),
+            ruby: Vec::new(),
+            agent: None,
         }
     }
 
@@ -66,15 +227,23 @@ impl TextSegment {
             return (self.text.len() as f32) * t;
         }
 
-        for i in 0..self.keyframes.len() - 1 {
-            let k1 = &self.keyframes[i];
-            let k2 = &self.keyframes[i + 1];
-            if rel_time >= k1.time && rel_time <= k2.time {
-                let t = (rel_time - k1.time) / (k2.time - k1.time);
-                return k1.index + (k2.index - k1.index) * t;
+        interpolate_index(&self.keyframes, rel_time).unwrap_or(0.0)
+    }
+
+    // the progress counted in chars of the text, as ruby ranges are
+    pub fn get_current_char_index(&self, rel_time: f32) -> f32 {
+        let byte = self
+            .get_current_index(rel_time)
+            .clamp(0.0, self.text.len() as f32);
+        let mut chars = 0.0;
+        for (b, c) in self.text.char_indices() {
+            let len = c.len_utf8() as f32;
+            if byte < b as f32 + len {
+                return chars + (byte - b as f32) / len;
             }
+            chars += 1.0;
         }
-        self.keyframes.last().map(|k| k.index).unwrap_or(0.0)
+        chars
     }
 
     pub fn sort_keyframes(&mut self) {
@@ -96,12 +265,70 @@ impl TextSegment {
         self.add_keyframe(time, index)
     }
 
-    pub fn to_string(&self) -> String {
-        // if part exists: prefix with [part]\n + segment.text
-        if let Some(part) = &self.part {
-            return format!("[{}]\n{}\n", part, self.text);
+    pub fn add_ruby(&mut self, start: usize, end: usize, text: &str) -> &mut Ruby {
+        let pos = self.ruby.partition_point(|r| r.start <= start);
+        self.ruby.insert(pos, Ruby::new(start, end, text));
+        &mut self.ruby[pos]
+    }
+
+    // `count` chars went in at char `at`: readings after it move along, one
+    // the insert falls inside grows
+    pub fn ruby_inserted(&mut self, at: usize, count: usize) {
+        for r in &mut self.ruby {
+            if r.start >= at {
+                r.start += count;
+                r.end += count;
+            } else if r.end > at {
+                r.end += count;
+            }
+        }
+    }
+
+    // chars `removed` were deleted: readings close up around the gap and go
+    // once none of their base text is left
+    pub fn ruby_removed(&mut self, removed: Range<usize>) {
+        let map = |i: usize| {
+            if i <= removed.start {
+                i
+            } else {
+                i.saturating_sub(removed.len()).max(removed.start)
+            }
+        };
+        for r in &mut self.ruby {
+            r.start = map(r.start);
+            r.end = map(r.end);
+        }
+        self.ruby.retain(|r| r.start < r.end);
+    }
+
+    // takes the readings from char `at` on, counted from `at` for the line the
+    // text after it becomes. one spanning `at` stays here, cut at the split.
+    // their keyframes were timed against this line and go
+    pub fn split_ruby(&mut self, at: usize) -> Vec<Ruby> {
+        let (keep, moved): (Vec<Ruby>, Vec<Ruby>) = self.ruby.drain(..).partition(|r| r.start < at);
+        self.ruby = keep;
+        for r in &mut self.ruby {
+            r.end = r.end.min(at);
+        }
+        moved
+            .into_iter()
+            .map(|r| Ruby::new(r.start - at, r.end - at, &r.text))
+            .collect()
+    }
+
+    // appends the text of `next` along with its readings
+    pub fn join(&mut self, next: TextSegment) {
+        let offset = self.text.chars().count();
+        let shift = next.start - self.start;
+        for mut r in next.ruby {
+            r.start += offset;
+            r.end += offset;
+            for kf in &mut r.keyframes {
+                kf.time = (kf.time + shift).max(0.0);
+            }
+            self.ruby.push(r);
         }
-        self.text.clone()
+        self.text.push_str(&next.text);
     }
 
     fn is_boundary(&self, kf_idx: usize) -> bool {
@@ -115,6 +342,7 @@ impl AnimationData {
 
         Self {
             lines: vec![empty_line],
+            parts: Vec::new(),
         }
     }
 
@@ -142,19 +370,8 @@ impl AnimationData {
             None => previous + 0.1,
         };
 
-        let value = TextSegment {
-            text: "".to_string(),
-            keyframes: Vec::new(),
-            part: None,
-            start: previous,
-            end: next,
-        };
-
-        if self.lines.len() < index {
-            self.lines.push(value)
-        } else {
-            self.lines.insert(index, value)
-        }
+        let value = TextSegment::new("".to_string(), previous, next);
+        self.insert_line(index, value);
     }
 
     pub fn _sort_lines(&mut self) {
@@ -179,7 +396,7 @@ impl AnimationData {
 
     pub fn edit_keyframe_time(&mut self, line_idx: usize, kf_idx: usize, delta: f32) {
         let line = &mut self.lines[line_idx];
-        let rel_time = (line.keyframes[kf_idx].time + 0.05 * delta).max(0.0);
+        let rel_time = (line.keyframes[kf_idx].time + delta).max(0.0);
         line.keyframes[kf_idx].time = rel_time;
     }
 
@@ -199,10 +416,10 @@ impl AnimationData {
     }
 
     pub fn add_trailing_empty(&mut self) {
-        if let Some(last) = self.lines.last() {
-            if (last.text.trim() != "") {
-                self.add_line(" ", last.end + 0.05, last.end + 5.0);
-            }
+        if let Some(last) = self.lines.last()
+            && last.text.trim() != ""
+        {
+            self.add_line(" ", last.end + 0.05, last.end + 5.0);
         }
     }
 }
@@ -210,11 +427,28 @@ impl AnimationData {
 // This allows you to do: my_data.to_string();
 impl fmt::Display for AnimationData {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_document(f, &WriteOptions::default())
+    }
+}
+
+impl AnimationData {
+    fn write_document(&self, f: &mut fmt::Formatter<'_>, options: &WriteOptions) -> fmt::Result {
         let mut line_strings = Vec::new();
         let mut lines_timestamp = Vec::new();
         let mut lines_keyframes = Vec::new();
+        let mut lines_ruby = Vec::new();
+
+        for (i, line) in self.lines.iter().enumerate() {
+            // part headers go right above the first line of the part
+            for part in self.parts.iter().filter(|p| p.start == i) {
+                let name: String = part
+                    .name
+                    .chars()
+                    .filter(|&c| !c.is_control() && c != '[' && c != ']')
+                    .collect();
+                line_strings.push(format!("[{}]", name));
+            }
 
-        for line in &self.lines {
             // 1. Clean Text
             let sanitized_text: String = line
                 .text
@@ -222,26 +456,35 @@ impl fmt::Display for AnimationData {
                 .filter(|&c| !c.is_control() && c != '/' && c != '[' && c != ']')
                 .collect();
 
-            if let Some(part) = &line.part {
-                line_strings.push(format!("{}\n{}", part, sanitized_text));
-            } else {
-                line_strings.push(sanitized_text);
-            }
+            line_strings.push(sanitized_text);
 
-            lines_timestamp.push(format!("{:.3}/{:.3}", line.start, line.end));
+            lines_timestamp.push(format!(
+                "{}/{}",
+                format::format_time(line.start, options.notation),
+                format::format_time(line.end, options.notation)
+            ));
 
             let line_len = line.text.len() as f32;
             let kfs: Vec<String> = line
                 .keyframes
                 .iter()
-                .map(|kf| kf.to_string_pct(line_len))
+                .map(|kf| kf.to_string_notation(line_len, options.notation))
                 .collect();
             lines_keyframes.push(format!("({})", kfs.join(",")));
+
+            let rbs: Vec<String> = line.ruby.iter().map(Ruby::to_string_entry).collect();
+            lines_ruby.push(format!("({})", rbs.join(",")));
         }
 
         let s1 = line_strings.join("\n");
-        let s2 = lines_timestamp.join(",");
-        let s3 = lines_keyframes.join(",");
+        let (s2, s3) = if options.compact {
+            (
+                format::write_compact_timestamps(&self.lines),
+                format::write_compact_keyframes(&self.lines),
+            )
+        } else {
+            (lines_timestamp.join(","), lines_keyframes.join(","))
+        };
 
         write!(
             f,
@@ -252,61 +495,135 @@ impl fmt::Display for AnimationData {
             s2,
             LINE_SYLABLE_KEYFRAME_MARKER,
             s3,
-        )
+        )?;
+
+        // [rby] is optional and only written when a line carries ruby
+        if self.lines.iter().any(|l| !l.ruby.is_empty()) {
+            writeln!(f, "{}[{}]", LINE_RUBY_MARKER, lines_ruby.join(","))?;
+        }
+
+        // [agt] likewise, once some line has a singer. empty entries have none
+        if self.lines.iter().any(|l| l.agent.is_some()) {
+            let agents: Vec<String> = self
+                .lines
+                .iter()
+                .map(|l| {
+                    l.agent
+                        .iter()
+                        .flat_map(|a| a.chars())
+                        .filter(|&c| !c.is_control() && !c.is_whitespace() && !",[]".contains(c))
+                        .collect()
+                })
+                .collect();
+            writeln!(f, "{}[{}]", LINE_AGENT_MARKER, agents.join(","))?;
+        }
+
+        // [prt] is only needed when the headers alone can't describe the parts
+        let implicit = self.parts.iter().enumerate().all(|(i, p)| {
+            let next = self.parts.get(i + 1).map_or(self.lines.len(), |n| n.start);
+            p.repeat.is_none() && p.end == next
+        });
+        if !implicit {
+            let prts: Vec<String> = self.parts.iter().map(Part::to_string_entry).collect();
+            writeln!(f, "{}[{}]", SONG_PART_MARKER, prts.join(","))?;
+        }
+        Ok(())
     }
 }
 
 impl AnimationData {
     pub fn parse_lines(&mut self, text: &str) {
-        let mut parts: Option<String> = None;
         for line in text.lines() {
             if line.starts_with("[") && line.ends_with("]") {
-                parts = Some(line.to_string());
+                let start = self.lines.len();
+                self.parts.push(Part::new(line, start, start));
             } else {
-                if let Some(part) = parts {
-                    self.add_line(line, 0.0, 0.0).part = Some(part);
-                    parts = None;
-                } else {
-                    self.add_line(line, 0.0, 0.0);
-                }
+                self.add_line(line, 0.0, 0.0);
             }
         }
+
+        // without [prt] a part runs until the next header
+        for i in 0..self.parts.len() {
+            let next = self.parts.get(i + 1).map_or(self.lines.len(), |n| n.start);
+            self.parts[i].end = next;
+        }
     }
 
-    pub fn parse_timestamps(&mut self, timestamps: &str) {
-        let timestamps: Vec<&str> = timestamps.split(',').collect();
-        for i in 0..timestamps.len() {
-            if i >= self.lines.len() {
-                break;
+    pub fn parse_parts(&mut self, parts: &str) {
+        for (part, entry) in self.parts.iter_mut().zip(parts.split(',')) {
+            part.apply_string_entry(entry);
+        }
+        // entries running past the lines or into the part before are cut to
+        // fit and bad repeat sources dropped, `validate_document` reports them
+        let mut prev_end = 0;
+        for part in &mut self.parts {
+            part.start = part.start.clamp(prev_end, self.lines.len());
+            part.end = part.end.clamp(part.start, self.lines.len());
+            prev_end = part.end;
+        }
+        for pi in 0..self.parts.len() {
+            if self.repeat_source(pi).is_none() {
+                self.parts[pi].repeat = None;
             }
-            let timestamp: Vec<&str> = timestamps[i].split('/').collect();
+        }
+        self.sync_repeats();
+    }
+
+    pub fn parse_timestamps(&mut self, timestamps: &str) {
+        if let Some(compact) = format::strip_compact(timestamps) {
+            format::parse_compact_timestamps(&mut self.lines, compact);
+            return;
+        }
+        for (line, entry) in self.lines.iter_mut().zip(timestamps.split(',')) {
+            let timestamp: Vec<&str> = entry.split('/').collect();
             if timestamp.len() == 2 {
-                let start = timestamp[0].trim().parse::<f32>().unwrap_or(0.0);
-                let end = timestamp[1].trim().parse::<f32>().unwrap_or(0.0);
-                self.lines[i].start = start;
-                self.lines[i].end = end;
+                line.start = format::parse_time(timestamp[0]).unwrap_or(0.0);
+                line.end = format::parse_time(timestamp[1]).unwrap_or(0.0);
             }
         }
     }
 
     pub fn parse_keyframes(&mut self, keyframes: &str) {
-        let keyframes: Vec<&str> = keyframes.split(',').collect();
-        for i in 0..keyframes.len() {
-            if i >= self.lines.len() {
-                break;
-            }
-            let line_len = self.lines[i].text.len() as f32;
-            let group = keyframes[i].trim_matches(|c| c == '(' || c == ')');
+        if let Some(compact) = format::strip_compact(keyframes) {
+            format::parse_compact_keyframes(&mut self.lines, compact);
+            return;
+        }
+        for (line, group) in self.lines.iter_mut().zip(split_top_level(keyframes, ',')) {
+            let line_len = line.text.len() as f32;
+            let group = strip_group(group);
             if group.is_empty() {
                 continue;
             }
 
             for kf_entry in group.split(',') {
                 if let Some(keyframe) = Keyframe::from_string_pct(kf_entry.trim(), line_len) {
-                    self.lines[i].keyframes.push(keyframe);
+                    line.keyframes.push(keyframe);
                 }
             }
-            self.lines[i].sort_keyframes();
+            line.sort_keyframes();
+        }
+    }
+
+    pub fn parse_ruby(&mut self, ruby: &str) {
+        for (line, group) in self.lines.iter_mut().zip(split_top_level(ruby, ',')) {
+            let group = strip_group(group);
+            if group.is_empty() {
+                continue;
+            }
+
+            for entry in split_top_level(group, ',') {
+                if let Some(r) = Ruby::from_string_entry(entry) {
+                    line.ruby.push(r);
+                }
+            }
+            line.ruby.sort_by_key(|r| r.start);
+        }
+    }
+
+    pub fn parse_agents(&mut self, agents: &str) {
+        for (line, agent) in self.lines.iter_mut().zip(agents.split(',')) {
+            let agent = agent.trim();
+            line.agent = (!agent.is_empty()).then(|| agent.to_string());
         }
     }
 
@@ -327,7 +644,7 @@ impl FromStr for AnimationData {
 
     fn from_str(input: &str) -> Result<Self, Self::Err> {
         let _draft = true;
-        let mut new_data = AnimationData::new();
+        let mut new_data = AnimationData::default();
 
         let sections: Vec<&str> = input.split(DATA_SECTION_SPLIT_MARKER).collect();
         let text_section = sections[0].replace("\n\n\n", "\n\n");
@@ -344,6 +661,16 @@ impl FromStr for AnimationData {
 
         new_data.parse_timestamps(&lbl_raw);
         new_data.parse_keyframes(&lsk_raw);
+
+        if let Ok(rby_raw) = new_data.extract_section(data_section, LINE_RUBY_MARKER) {
+            new_data.parse_ruby(&rby_raw);
+        }
+        if let Ok(agt_raw) = new_data.extract_section(data_section, LINE_AGENT_MARKER) {
+            new_data.parse_agents(&agt_raw);
+        }
+        if let Ok(prt_raw) = new_data.extract_section(data_section, SONG_PART_MARKER) {
+            new_data.parse_parts(&prt_raw);
+        }
         Ok(new_data)
     }
 }
@@ -359,8 +686,8 @@ fn parse_test() {
     test_data.add_line("line4", 3.0, 4.0);
     test_data.add_line("line5", 4.0, 5.0);
     test_data.gap(7);
-    test_data.lines[1].part = Some("[a]".to_string());
-    test_data.lines[5].part = Some("[b]".to_string());
+    test_data.add_part("[a]", 1, 5);
+    test_data.add_part("[b]", 5, 8);
 
     println!("-----");
     let animated_text = test_data.compile();
@@ -372,8 +699,10 @@ fn parse_test() {
         Ok(data) => {
             assert_eq!(data.lines[0].text, "".to_string());
             assert_eq!(data.lines.len(), 8);
-            assert_eq!(data.lines[1].part, Some("[a]".to_string()));
-            assert_eq!(data.lines[2].part, None);
+            assert_eq!(data.parts.len(), 2);
+            assert_eq!(data.part_starting_at(1).unwrap().name, "a");
+            assert_eq!(data.part_starting_at(2), None);
+            assert_eq!((data.parts[1].start, data.parts[1].end), (5, 8));
             assert_eq!(data.lines[4].text, "".to_string());
             assert_eq!(data.lines[7].text, "".to_string());
         }
@@ -397,3 +726,101 @@ fn parse_test() {
 
     assert_eq!(comp_text, test_text);
 }
+
+#[test]
+fn ruby_test() {
+    let mut test_data = AnimationData::default();
+    test_data.add_line("line1", 0.0, 1.0);
+    // ranges count chars of the base text: 東京 is 0..2, タワー 2..5
+    let line = test_data.add_line("東京タワー", 1.0, 3.0);
+    line.add_ruby(2, 5, "たわー");
+    line.add_ruby(0, 2, "とうきょう")
+        .add_keyframe(0.0, 0.0)
+        .add_keyframe(0.5, 5.0);
+
+    let animated_text = test_data.compile();
+    assert!(
+        animated_text.contains("[rby][(),(0/2/とうきょう(0.000/0.000,0.500/1.000),2/5/たわー)]")
+    );
+
+    let data = animated_text.parse::<AnimationData>().unwrap();
+    assert_eq!(data.lines.len(), 2);
+    assert!(data.lines[0].ruby.is_empty());
+    assert_eq!(data.lines[1].ruby, test_data.lines[1].ruby);
+
+    let plain = AnimationData::default().compile();
+    assert!(!plain.contains(LINE_RUBY_MARKER));
+}
+
+#[test]
+fn agent_test() {
+    let mut test_data = AnimationData::default();
+    test_data.add_line("lead", 0.0, 1.0).agent = Some("v1".to_string());
+    test_data.add_line("everyone", 1.0, 2.0);
+    test_data.add_line("echo", 2.0, 3.0).agent = Some("v 2,".to_string());
+
+    let animated_text = test_data.compile();
+    assert!(animated_text.contains("[agt][v1,,v2]"));
+    let data = animated_text.parse::<AnimationData>().unwrap();
+    let agents: Vec<_> = data.lines.iter().map(|l| l.agent.as_deref()).collect();
+    assert_eq!(agents, [Some("v1"), None, Some("v2")]);
+
+    assert!(
+        !AnimationData::default()
+            .compile()
+            .contains(LINE_AGENT_MARKER)
+    );
+}
+
+#[test]
+fn ruby_follows_text_edits() {
+    let mut line = TextSegment::new("東京タワー".to_string(), 0.0, 2.0);
+    line.add_ruby(0, 2, "とうきょう");
+    line.add_ruby(2, 5, "たわー");
+    let spans = |l: &TextSegment| l.ruby.iter().map(|r| (r.start, r.end)).collect::<Vec<_>>();
+
+    // typing ahead of a reading moves it, typing inside one grows it
+    line.ruby_inserted(0, 1);
+    line.ruby_inserted(4, 1);
+    assert_eq!(spans(&line), [(1, 3), (3, 7)]);
+
+    // deleting all of a reading's base text drops it
+    line.ruby_removed(0..3);
+    assert_eq!(spans(&line), [(0, 4)]);
+    assert_eq!(line.ruby[0].text, "たわー");
+
+    // a split hands later readings to the new line and cuts one spanning it
+    let mut line = TextSegment::new("東京タワー".to_string(), 0.0, 2.0);
+    line.add_ruby(0, 2, "とうきょう");
+    line.add_ruby(3, 5, "わー");
+    let moved = line.split_ruby(1);
+    assert_eq!(spans(&line), [(0, 1)]);
+    assert_eq!(
+        moved.iter().map(|r| (r.start, r.end)).collect::<Vec<_>>(),
+        [(2, 4)]
+    );
+
+    // joining lines re-bases the readings of the second
+    let mut first = TextSegment::new("東".to_string(), 0.0, 1.0);
+    let mut second = TextSegment::new("京タワー".to_string(), 1.0, 2.0);
+    second.add_ruby(1, 4, "たわー").add_keyframe(0.5, 3.0);
+    first.join(second);
+    assert_eq!(first.text, "東京タワー");
+    assert_eq!(spans(&first), [(2, 5)]);
+    assert_eq!(first.ruby[0].keyframes[0].time, 1.5);
+}
+
+#[test]
+fn ruby_progress_counts_chars() {
+    let mut line = TextSegment::new("東京タワー".to_string(), 0.0, 5.0);
+    line.add_ruby(2, 5, "たわー");
+    // halfway through the bytes is halfway through タ
+    assert_eq!(line.get_current_char_index(2.5), 2.5);
+    assert_eq!(line.get_current_char_index(-1.0), 0.0);
+    assert_eq!(line.get_current_char_index(5.0), 5.0);
+    // so its reading has just begun, counting bytes it would be all lit
+    let base = line.get_current_char_index(2.5);
+    assert_eq!(line.ruby[0].get_current_index(2.5, base), 0.5);
+    let base = line.get_current_char_index(4.0);
+    assert_eq!(line.ruby[0].get_current_index(4.0, base), 2.0);
+}
@@ -0,0 +1,557 @@
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use super::{AnimationData, TextSegment};
+
+// a named song section covering lines [start, end).
+// parts are kept sorted by start and never overlap.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Part {
+    pub name: String,
+    pub start: usize,
+    pub end: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repeat: Option<Repeat>,
+}
+
+// the lines of a repeat mirror the `source` part, shifted by `offset` seconds.
+// editing either copy updates the source and every other repeat.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Repeat {
+    pub source: usize,
+    pub offset: f32,
+}
+
+impl Part {
+    pub fn new(name: &str, start: usize, end: usize) -> Self {
+        Self {
+            name: name.trim_matches(|c| c == '[' || c == ']').to_string(),
+            start,
+            end: end.max(start),
+            repeat: None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    pub fn contains(&self, line_idx: usize) -> bool {
+        self.start <= line_idx && line_idx < self.end
+    }
+
+    // start/end, followed by source/offset for repeats
+    pub fn to_string_entry(&self) -> String {
+        match &self.repeat {
+            Some(r) => format!("{}/{}/{}/{:.3}", self.start, self.end, r.source, r.offset),
+            None => format!("{}/{}", self.start, self.end),
+        }
+    }
+
+    // applies a [prt] entry on top of a part discovered from its header line
+    pub fn apply_string_entry(&mut self, s: &str) -> Option<()> {
+        let fields: Vec<&str> = s.trim().split('/').collect();
+        if fields.len() < 2 {
+            return None;
+        }
+        let start: usize = fields[0].trim().parse().ok()?;
+        let end: usize = fields[1].trim().parse().ok()?;
+        self.start = start;
+        self.end = end.max(start);
+        if fields.len() == 4 {
+            self.repeat = Some(Repeat {
+                source: fields[2].trim().parse().ok()?,
+                offset: fields[3].trim().parse().ok()?,
+            });
+        }
+        Some(())
+    }
+}
+
+impl AnimationData {
+    pub fn part_at(&self, line_idx: usize) -> Option<usize> {
+        self.parts.iter().position(|p| p.contains(line_idx))
+    }
+
+    pub fn part_starting_at(&self, line_idx: usize) -> Option<&Part> {
+        self.parts
+            .iter()
+            .find(|p| p.start == line_idx && !p.is_empty())
+    }
+
+    fn insert_part(&mut self, part: Part) -> usize {
+        // empty parts already sitting at `start` stay in front of the new one
+        let pos = self
+            .parts
+            .partition_point(|p| p.start < part.start || p.end <= part.start);
+        for p in &mut self.parts {
+            if let Some(r) = &mut p.repeat
+                && r.source >= pos
+            {
+                r.source += 1;
+            }
+        }
+        self.parts.insert(pos, part);
+        pos
+    }
+
+    pub fn add_part(&mut self, name: &str, start: usize, end: usize) -> &mut Part {
+        let pos = self.insert_part(Part::new(name, start, end));
+        &mut self.parts[pos]
+    }
+
+    // repeats that referenced the removed part keep their lines as a plain part
+    pub fn remove_part(&mut self, part_idx: usize) -> Part {
+        let part = self.parts.remove(part_idx);
+        for p in &mut self.parts {
+            match &mut p.repeat {
+                Some(r) if r.source == part_idx => p.repeat = None,
+                Some(r) if r.source > part_idx => r.source -= 1,
+                _ => {}
+            }
+        }
+        part
+    }
+
+    // makes `range` one part called `name`, or no part when the name is empty.
+    // parts it covers are dropped, parts it cuts into are trimmed (split when it
+    // falls inside one) and no longer repeat or get repeated
+    pub fn set_part(&mut self, range: Range<usize>, name: &str) {
+        let len = self.lines.len();
+        let range = range.start.min(len)..range.end.min(len).max(range.start.min(len));
+        let mut pi = 0;
+        while pi < self.parts.len() {
+            let (start, end) = (self.parts[pi].start, self.parts[pi].end);
+            if end <= range.start || start >= range.end {
+                pi += 1;
+                continue;
+            }
+            if range.start <= start && end <= range.end {
+                self.remove_part(pi);
+                continue;
+            }
+            self.parts[pi].repeat = None;
+            for p in &mut self.parts {
+                if p.repeat.as_ref().is_some_and(|r| r.source == pi) {
+                    p.repeat = None;
+                }
+            }
+            if start < range.start {
+                self.parts[pi].end = range.start;
+                if end > range.end {
+                    let name = self.parts[pi].name.clone();
+                    self.insert_part(Part::new(&name, range.end, end));
+                }
+            } else {
+                self.parts[pi].start = range.end;
+            }
+            pi += 1;
+        }
+        if !Part::new(name, 0, 0).name.is_empty() && !range.is_empty() {
+            self.add_part(name, range.start, range.end);
+        }
+    }
+
+    // a part header in the text starts a new part at `line_idx`,
+    // splitting the part that currently runs over it
+    pub fn start_part_at(&mut self, line_idx: usize, name: &str) {
+        if let Some(pi) = self.part_at(line_idx) {
+            if self.parts[pi].start == line_idx {
+                self.parts[pi].name = Part::new(name, 0, 0).name;
+                return;
+            }
+            let end = self.parts[pi].end;
+            self.parts[pi].end = line_idx;
+            self.add_part(name, line_idx, end);
+            return;
+        }
+        let end = self
+            .parts
+            .iter()
+            .map(|p| p.start)
+            .find(|&s| s > line_idx)
+            .unwrap_or(self.lines.len());
+        self.add_part(name, line_idx, end);
+    }
+
+    // the new line joins the part of the line before it
+    pub fn insert_line(&mut self, idx: usize, line: TextSegment) {
+        let idx = idx.min(self.lines.len());
+        self.lines.insert(idx, line);
+        for p in &mut self.parts {
+            if p.start < idx && idx <= p.end {
+                p.end += 1;
+            } else if p.start >= idx {
+                p.start += 1;
+                p.end += 1;
+            }
+        }
+    }
+
+    pub fn remove_line(&mut self, idx: usize) -> TextSegment {
+        let line = self.lines.remove(idx);
+        for p in &mut self.parts {
+            if p.start > idx {
+                p.start -= 1;
+            }
+            if p.end > idx {
+                p.end -= 1;
+            }
+        }
+        line
+    }
+
+    // a repeat the range covers goes along with its lines, one it only cuts
+    // into would grow back from its source, so that is refused
+    pub fn delete_lines(&mut self, range: Range<usize>) -> Result<(), String> {
+        let mut covered = Vec::new();
+        for (pi, p) in self.parts.iter().enumerate() {
+            if p.repeat.is_none() || p.end <= range.start || range.end <= p.start {
+                continue;
+            }
+            if p.start < range.start || range.end < p.end {
+                return Err(format!(
+                    "[{}] repeats another part, delete all of it or edit the source",
+                    p.name
+                ));
+            }
+            covered.push(pi);
+        }
+        for idx in range.rev() {
+            self.remove_line(idx);
+        }
+        for pi in covered.into_iter().rev() {
+            self.remove_part(pi);
+        }
+        self.sync_repeats();
+        Ok(())
+    }
+
+    fn generated(&self, line_idx: usize) -> Option<usize> {
+        self.part_at(line_idx)
+            .filter(|&pi| self.parts[pi].repeat.is_some())
+    }
+
+    // swaps line `idx` with the one after it. inside a repeat the source lines
+    // swap instead, a swap carrying a line into or out of a repeat is refused
+    pub fn swap_lines(&mut self, idx: usize) -> bool {
+        if idx + 1 >= self.lines.len() {
+            return false;
+        }
+        let part = self.generated(idx).or(self.generated(idx + 1));
+        if part.is_some() {
+            if self.part_at(idx) != self.part_at(idx + 1) {
+                return false;
+            }
+            let Some((src, _)) = self
+                .source_line(idx)
+                .filter(|_| self.source_line(idx + 1).is_some())
+            else {
+                return false;
+            };
+            self.lines.swap(src, src + 1);
+        } else {
+            self.lines.swap(idx, idx + 1);
+        }
+        self.sync_repeats();
+        true
+    }
+
+    // appends line `idx` to the one before it and returns where the merged
+    // line ends up. inside a repeat the source lines merge instead, merging
+    // across the edge of a repeat is refused
+    pub fn join_lines(&mut self, idx: usize) -> Option<usize> {
+        if idx == 0 || idx >= self.lines.len() {
+            return None;
+        }
+        let Some(pi) = self.generated(idx).or(self.generated(idx - 1)) else {
+            let next = self.remove_line(idx);
+            self.lines[idx - 1].join(next);
+            self.sync_repeats();
+            return Some(idx - 1);
+        };
+        if self.part_at(idx) != self.part_at(idx - 1) {
+            return None;
+        }
+        let (src, _) = self.source_line(idx)?;
+        let pos = idx - 1 - self.parts[pi].start;
+        let next = self.remove_line(src);
+        self.lines[src - 1].join(next);
+        self.sync_repeats();
+        Some(self.parts[pi].start + pos)
+    }
+
+    fn splice_part_lines(&mut self, part_idx: usize, lines: Vec<TextSegment>) {
+        let (start, end) = (self.parts[part_idx].start, self.parts[part_idx].end);
+        let delta = lines.len() as isize - (end - start) as isize;
+        self.lines.splice(start..end, lines);
+        self.parts[part_idx].end = (end as isize + delta) as usize;
+        for p in self.parts.iter_mut().skip(part_idx + 1) {
+            p.start = (p.start as isize + delta) as usize;
+            p.end = (p.end as isize + delta) as usize;
+        }
+    }
+
+    // inserts a repeat of `source` at `at`, shifted so it begins at `start_time`.
+    // if `at` falls inside a part, the repeat goes after that part.
+    pub fn repeat_part(&mut self, source: usize, at: usize, start_time: f32) -> Option<usize> {
+        // repeats always point at the original part
+        let source = match &self.parts.get(source)?.repeat {
+            Some(r) => r.source,
+            None => source,
+        };
+        let src = self.parts.get(source)?.clone();
+        let first_start = self.lines.get(src.start)?.start;
+
+        let at = match self.part_at(at) {
+            Some(pi) if self.parts[pi].start < at => self.parts[pi].end,
+            _ => at.min(self.lines.len()),
+        };
+        let mut part = Part::new(&src.name, at, at);
+        part.repeat = Some(Repeat {
+            source,
+            offset: start_time - first_start,
+        });
+        let pos = self.insert_part(part);
+        self.sync_repeats();
+        Some(pos)
+    }
+
+    // the part repeat `pi` copies, when that is an original part
+    pub fn repeat_source(&self, pi: usize) -> Option<&Part> {
+        let r = self.parts.get(pi)?.repeat.as_ref()?;
+        self.parts
+            .get(r.source)
+            .filter(|s| r.source != pi && s.repeat.is_none())
+    }
+
+    // parts in order, inside the lines, and repeating original parts
+    pub fn check_parts(&self) -> Result<(), String> {
+        let mut prev_end = 0;
+        for (pi, p) in self.parts.iter().enumerate() {
+            if p.start < prev_end || p.start > p.end || p.end > self.lines.len() {
+                return Err(format!(
+                    "part [{}] covers lines {}..{} of {}",
+                    p.name,
+                    p.start,
+                    p.end,
+                    self.lines.len()
+                ));
+            }
+            prev_end = p.end;
+            if let Some(r) = &p.repeat
+                && self.repeat_source(pi).is_none()
+            {
+                return Err(format!(
+                    "part [{}] repeats part {} which is not an original part",
+                    p.name, r.source
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub(super) fn source_line(&self, line_idx: usize) -> Option<(usize, f32)> {
+        let part = &self.parts[self.part_at(line_idx)?];
+        let r = part.repeat.as_ref()?;
+        let src = self.parts.get(r.source)?;
+        let src_idx = src.start + (line_idx - part.start);
+        src.contains(src_idx).then_some((src_idx, r.offset))
+    }
+
+    // regenerates the lines of every repeat from its source
+    pub fn sync_repeats(&mut self) {
+        for pi in 0..self.parts.len() {
+            let Some(r) = self.parts[pi].repeat.clone() else {
+                continue;
+            };
+            // a broken repeat is left for `validate` to report
+            let len = self.lines.len();
+            let Some(src) = self
+                .repeat_source(pi)
+                .filter(|s| s.end <= len && self.parts[pi].end <= len)
+            else {
+                continue;
+            };
+            let lines: Vec<TextSegment> = self.lines[src.start..src.end]
+                .iter()
+                .map(|l| {
+                    let mut copy = l.clone();
+                    copy.start += r.offset;
+                    copy.end += r.offset;
+                    copy
+                })
+                .collect();
+            self.splice_part_lines(pi, lines);
+        }
+    }
+
+    // call after editing `line_idx`: edits made on a repeat are written back
+    // to the source, then all repeats are refreshed.
+    // moving the first line of a repeat moves the whole repeat instead.
+    pub fn sync_repeats_from(&mut self, line_idx: usize) {
+        if line_idx < self.lines.len()
+            && let Some((src_idx, mut offset)) = self.source_line(line_idx)
+        {
+            if let Some(pi) = self.part_at(line_idx)
+                && self.parts[pi].start == line_idx
+                && let Some(r) = &mut self.parts[pi].repeat
+            {
+                offset = self.lines[line_idx].start - self.lines[src_idx].start;
+                r.offset = offset;
+            }
+            let mut line = self.lines[line_idx].clone();
+            line.start -= offset;
+            line.end -= offset;
+            self.lines[src_idx] = line;
+        }
+        if self.parts.iter().any(|p| p.repeat.is_some()) {
+            self.sync_repeats();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::AnimationData;
+
+    #[test]
+    fn test_repeat_follows_source() {
+        let mut data = AnimationData::default();
+        data.add_line("Verse line", 0.0, 2.0);
+        data.add_line("Chorus tpyo", 2.0, 4.0);
+        data.add_line("Chorus end", 4.0, 6.0);
+        data.add_line("Bridge", 6.0, 8.0);
+        data.add_part("Verse", 0, 1);
+        data.add_part("Chorus", 1, 3);
+        data.add_part("Bridge", 3, 4);
+
+        let pos = data.repeat_part(1, 4, 10.0).unwrap();
+        assert_eq!(pos, 3);
+        assert_eq!(data.lines.len(), 6);
+        assert_eq!((data.parts[3].start, data.parts[3].end), (4, 6));
+        assert_eq!(data.lines[4].start, 10.0);
+        assert_eq!(data.lines[5].end, 14.0);
+
+        // fixing the typo in the repeat fixes the source
+        data.lines[4].text = "Chorus typo".to_string();
+        data.sync_repeats_from(4);
+        assert_eq!(data.lines[1].text, "Chorus typo");
+        assert_eq!(data.lines[1].start, 2.0);
+
+        let compiled = data.compile();
+        assert!(compiled.contains("[prt]"));
+        let parsed = compiled.parse::<AnimationData>().unwrap();
+        assert_eq!(parsed.parts, data.parts);
+        assert_eq!(parsed.lines[4].text, "Chorus typo");
+
+        // growing the source grows every repeat
+        let extra = data.lines[2].clone();
+        data.insert_line(3, extra);
+        data.sync_repeats();
+        assert_eq!(data.parts[1].len(), 3);
+        assert_eq!(data.parts[3].len(), 3);
+        assert_eq!(data.lines.len(), 8);
+    }
+
+    #[test]
+    fn test_line_moves_inside_repeat() {
+        let mut data = AnimationData::default();
+        data.add_line("Verse", 0.0, 2.0);
+        data.add_line("Chorus one", 2.0, 4.0);
+        data.add_line("Chorus two", 4.0, 6.0);
+        data.add_part("Chorus", 1, 3);
+        data.repeat_part(0, 3, 10.0);
+        let texts = |d: &AnimationData| d.lines.iter().map(|l| l.text.clone()).collect::<Vec<_>>();
+
+        // swapping in the repeat reorders the source, not just the copy
+        assert!(data.swap_lines(3));
+        assert_eq!(
+            texts(&data),
+            [
+                "Verse",
+                "Chorus two",
+                "Chorus one",
+                "Chorus two",
+                "Chorus one"
+            ]
+        );
+        assert_eq!(data.lines[1].start, 4.0);
+        assert!(!data.swap_lines(2));
+
+        // merging in the repeat merges the source lines once
+        assert_eq!(data.join_lines(4), Some(2));
+        assert_eq!(
+            texts(&data),
+            ["Verse", "Chorus twoChorus one", "Chorus twoChorus one"]
+        );
+        assert_eq!((data.parts[1].start, data.parts[1].end), (2, 3));
+        assert_eq!(data.join_lines(2), None);
+    }
+
+    #[test]
+    fn test_delete_lines_of_a_repeat() {
+        let mut data = AnimationData::default();
+        data.add_line("Chorus one", 0.0, 2.0);
+        data.add_line("Chorus two", 2.0, 4.0);
+        data.add_line("Bridge", 4.0, 6.0);
+        data.add_part("Chorus", 0, 2);
+        data.repeat_part(0, 3, 10.0);
+        assert_eq!(data.lines.len(), 5);
+
+        // half a repeat would come back on the next sync
+        assert!(data.delete_lines(3..4).is_err());
+        assert!(data.delete_lines(2..4).is_err());
+        assert_eq!(data.lines.len(), 5);
+
+        // all of it takes the repeat along
+        data.delete_lines(2..5).unwrap();
+        assert_eq!(data.lines.len(), 2);
+        assert_eq!(data.parts.len(), 1);
+        assert!(data.parts[0].repeat.is_none());
+    }
+
+    #[test]
+    fn test_set_part_over_existing_parts() {
+        let mut data = AnimationData::default();
+        for i in 0..6 {
+            data.add_line("line", i as f32, i as f32 + 1.0);
+        }
+        data.add_part("Verse", 0, 4);
+        data.add_part("Chorus", 4, 6);
+
+        // inside the verse splits it around the new part
+        data.set_part(1..3, "Hook");
+        let parts: Vec<_> = data
+            .parts
+            .iter()
+            .map(|p| (p.name.as_str(), p.start, p.end))
+            .collect();
+        assert_eq!(
+            parts,
+            [
+                ("Verse", 0, 1),
+                ("Hook", 1, 3),
+                ("Verse", 3, 4),
+                ("Chorus", 4, 6)
+            ]
+        );
+
+        // across a boundary trims one and drops what it covers
+        data.set_part(2..6, "Outro");
+        let parts: Vec<_> = data
+            .parts
+            .iter()
+            .map(|p| (p.name.as_str(), p.start, p.end))
+            .collect();
+        assert_eq!(parts, [("Verse", 0, 1), ("Hook", 1, 2), ("Outro", 2, 6)]);
+
+        data.set_part(0..6, "");
+        assert!(data.parts.is_empty());
+    }
+}
@@ -0,0 +1,166 @@
+// where the units of a line start, as char indexes, for tapping keyframes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Split {
+    // runs of non-whitespace, a hyphen also ends one: "to-night" is two
+    Words,
+    // words cut before each syllable. CJK and kana count one per char, latin
+    // words are cut between vowel groups: "twin|kle", "won|der", "ba|by"
+    Syllables,
+}
+
+fn is_vowel(c: char) -> bool {
+    "aeiouyAEIOUYàáâäèéêëìíîïòóôöùúûüÀÁÂÄÈÉÊËÌÍÎÏÒÓÔÖÙÚÛÜ".contains(c)
+}
+
+// one syllable per char: CJK ideographs, kana and hangul
+fn is_syllabic(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30ff}'
+        | '\u{3400}'..='\u{4dbf}'
+        | '\u{4e00}'..='\u{9fff}'
+        | '\u{ac00}'..='\u{d7af}'
+        | '\u{f900}'..='\u{faff}')
+}
+
+// small kana join the char before: "きょ" is one
+fn is_small_kana(c: char) -> bool {
+    "ぁぃぅぇぉゃゅょゎっァィゥェォャュョヮッー".contains(c)
+}
+
+// syllable starts within one latin word, relative to its first char
+fn latin_syllables(word: &[char]) -> Vec<usize> {
+    let mut starts = vec![0];
+    let groups: Vec<(usize, usize)> = {
+        let mut groups = Vec::new();
+        let mut i = 0;
+        while i < word.len() {
+            if is_vowel(word[i]) && !(word[i] == 'y' && i == 0) {
+                let start = i;
+                while i < word.len() && is_vowel(word[i]) {
+                    i += 1;
+                }
+                groups.push((start, i));
+            } else {
+                i += 1;
+            }
+        }
+        // a final "e" after a consonant is silent, except in "-le"
+        if let [.., (_, _), (s, e)] = groups[..]
+            && e == word.len()
+            && e - s == 1
+            && word[s].eq_ignore_ascii_case(&'e')
+            && s >= 2
+            && !is_vowel(word[s - 1])
+            && !word[s - 1].eq_ignore_ascii_case(&'l')
+        {
+            groups.pop();
+        }
+        groups
+    };
+
+    for pair in groups.windows(2) {
+        let (prev_end, next_start) = (pair[0].1, pair[1].0);
+        let consonants = next_start - prev_end;
+        // "ba|by", "twin|kle": a single consonant goes with the next vowel,
+        // of several the first stays with the syllable before
+        let cut = if consonants <= 1 {
+            prev_end
+        } else {
+            prev_end + 1
+        };
+        starts.push(cut);
+    }
+    starts
+}
+
+// where char `idx` of `text` starts in bytes, keyframe indexes count bytes
+pub fn byte_offset(text: &str, idx: usize) -> usize {
+    text.char_indices().nth(idx).map_or(text.len(), |(b, _)| b)
+}
+
+pub fn split_points(text: &str, split: Split) -> Vec<usize> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut points = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i].is_whitespace() || chars[i] == '-' {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < chars.len() && !chars[i].is_whitespace() && chars[i] != '-' {
+            i += 1;
+        }
+        let word = &chars[start..i];
+        if split == Split::Words {
+            points.push(start);
+            continue;
+        }
+
+        // runs of latin between syllabic chars are split on their own
+        let mut j = 0;
+        while j < word.len() {
+            if is_syllabic(word[j]) || is_small_kana(word[j]) {
+                if !(is_small_kana(word[j]) && j > 0) {
+                    points.push(start + j);
+                }
+                j += 1;
+                continue;
+            }
+            let run = j;
+            while j < word.len() && !is_syllabic(word[j]) && !is_small_kana(word[j]) {
+                j += 1;
+            }
+            let letters = &word[run..j];
+            if letters.iter().any(|c| c.is_alphanumeric()) {
+                points.extend(
+                    latin_syllables(letters)
+                        .into_iter()
+                        .map(|p| start + run + p),
+                );
+            }
+        }
+    }
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Split, byte_offset, split_points};
+
+    #[test]
+    fn test_split_points() {
+        assert_eq!(split_points("how I wonder", Split::Words), [0, 4, 6]);
+        assert_eq!(split_points(" to-night ", Split::Words), [1, 4]);
+
+        let syllables = |text: &str| -> Vec<String> {
+            let chars: Vec<char> = text.chars().collect();
+            let points = split_points(text, Split::Syllables);
+            points
+                .iter()
+                .enumerate()
+                .map(|(i, &p)| {
+                    let end = points.get(i + 1).copied().unwrap_or(chars.len());
+                    chars[p..end].iter().collect::<String>().trim().to_string()
+                })
+                .collect()
+        };
+        assert_eq!(
+            syllables("Twinkle little star"),
+            ["Twin", "kle", "lit", "tle", "star"]
+        );
+        assert_eq!(syllables("make a baby"), ["make", "a", "ba", "by"]);
+        assert_eq!(syllables("きょうは晴れ"), ["きょ", "う", "は", "晴", "れ"]);
+    }
+
+    #[test]
+    fn test_points_as_byte_offsets() {
+        let text = "東京 タワー";
+        let points = split_points(text, Split::Syllables);
+        // the long vowel mark goes with the kana before it
+        assert_eq!(points, [0, 1, 3, 4]);
+        let offsets: Vec<usize> = points.iter().map(|&p| byte_offset(text, p)).collect();
+        assert_eq!(offsets, [0, 3, 7, 10]);
+        assert_eq!(byte_offset(text, 6), text.len());
+    }
+}
@@ -0,0 +1,234 @@
+use std::ops::Range;
+
+use super::AnimationData;
+
+// maps time through (old, new) anchor pairs, linear between anchors
+// and shifted by the nearest anchor outside of them
+pub fn remap_time(anchors: &[(f32, f32)], t: f32) -> f32 {
+    let Some(first) = anchors.first() else {
+        return t;
+    };
+    if t <= first.0 {
+        return t + (first.1 - first.0);
+    }
+    for pair in anchors.windows(2) {
+        let ((o1, n1), (o2, n2)) = (pair[0], pair[1]);
+        if t <= o2 {
+            let k = if o2 > o1 { (t - o1) / (o2 - o1) } else { 1.0 };
+            return n1 + (n2 - n1) * k;
+        }
+    }
+    let last = anchors[anchors.len() - 1];
+    t + (last.1 - last.0)
+}
+
+impl AnimationData {
+    // moves line start/end and every keyframe of `range` through `f`.
+    // keyframes are relative, so they are mapped as absolute times and re-based.
+    // lines of a repeat move their source line, unless that is in `range` too
+    pub fn transform_times(&mut self, range: Range<usize>, f: impl Fn(f32) -> f32) {
+        let range = range.start.min(self.lines.len())..range.end.min(self.lines.len());
+        let map = |t: f32| f(t).max(0.0);
+
+        for line in &mut self.lines[range.clone()] {
+            let old_start = line.start;
+            line.start = map(old_start);
+            line.end = map(line.end).max(line.start);
+
+            for kf in &mut line.keyframes {
+                kf.time = (map(old_start + kf.time) - line.start).max(0.0);
+            }
+            for ruby in &mut line.ruby {
+                for kf in &mut ruby.keyframes {
+                    kf.time = (map(old_start + kf.time) - line.start).max(0.0);
+                }
+            }
+        }
+
+        // repeats follow wherever their first line ended up
+        for pi in 0..self.parts.len() {
+            let part = &self.parts[pi];
+            let Some(r) = &part.repeat else {
+                continue;
+            };
+            let Some(src) = self.parts.get(r.source) else {
+                continue;
+            };
+            if let (Some(first), Some(src_first)) =
+                (self.lines.get(part.start), self.lines.get(src.start))
+                && part.start < part.end
+            {
+                let offset = first.start - src_first.start;
+                if let Some(r) = &mut self.parts[pi].repeat {
+                    r.offset = offset;
+                }
+            }
+        }
+        // the other lines of a repeat would be regenerated from their source
+        for idx in range.clone() {
+            if let Some((src_idx, offset)) = self.source_line(idx)
+                && !range.contains(&src_idx)
+                && self
+                    .part_at(idx)
+                    .is_some_and(|pi| self.parts[pi].start != idx)
+            {
+                let mut line = self.lines[idx].clone();
+                line.start -= offset;
+                line.end -= offset;
+                self.lines[src_idx] = line;
+            }
+        }
+        self.sync_repeats();
+    }
+
+    // trims one line to `start..end`, its keyframes stay where they are in time
+    pub fn set_line_bounds(&mut self, idx: usize, start: f32, end: f32) {
+        let Some(line) = self.lines.get_mut(idx) else {
+            return;
+        };
+        let start = start.max(0.0);
+        let shift = line.start - start;
+        line.start = start;
+        line.end = end.max(start);
+        let ruby = line.ruby.iter_mut().flat_map(|r| r.keyframes.iter_mut());
+        for kf in line.keyframes.iter_mut().chain(ruby) {
+            kf.time = (kf.time + shift).max(0.0);
+        }
+        self.sync_repeats_from(idx);
+    }
+
+    // spreads `range` over `from..to` in equal slots, one line each. keyframes
+    // stretch along with their line
+    pub fn distribute(&mut self, range: Range<usize>, from: f32, to: f32) -> Result<(), String> {
+        if !(from.is_finite() && to.is_finite()) || to <= from {
+            return Err(format!("Cannot distribute from {} to {}", from, to));
+        }
+        let range = range.start.min(self.lines.len())..range.end.min(self.lines.len());
+        if range.is_empty() {
+            return Err("No lines to distribute".to_string());
+        }
+        // a repeat takes the times of its source, both cannot get a slot of their own
+        if range.clone().any(|idx| {
+            self.source_line(idx)
+                .is_some_and(|(src, _)| range.contains(&src))
+        }) {
+            return Err("Cannot distribute a repeat along with its source".to_string());
+        }
+        let slot = (to - from) / range.len() as f32;
+        for (k, idx) in range.enumerate() {
+            let (start, end) = (self.lines[idx].start, self.lines[idx].end);
+            let new_start = from.max(0.0) + slot * k as f32;
+            let factor = if end > start {
+                slot / (end - start)
+            } else {
+                1.0
+            };
+            self.transform_times(idx..idx + 1, |t| new_start + (t - start) * factor);
+            self.set_line_bounds(idx, new_start, new_start + slot);
+        }
+        Ok(())
+    }
+
+    pub fn shift_times(&mut self, range: Range<usize>, offset: f32) {
+        self.transform_times(range, |t| t + offset);
+    }
+
+    // tempo change: stretches times away from (or towards) `pivot`
+    pub fn scale_times(
+        &mut self,
+        range: Range<usize>,
+        pivot: f32,
+        factor: f32,
+    ) -> Result<(), String> {
+        if factor <= 0.0 || !factor.is_finite() {
+            return Err(format!("Scale factor must be positive, got {}", factor));
+        }
+        self.transform_times(range, |t| pivot + (t - pivot) * factor);
+        Ok(())
+    }
+
+    // piecewise-linear remap through old->new anchor pairs
+    pub fn remap_times(
+        &mut self,
+        range: Range<usize>,
+        anchors: &[(f32, f32)],
+    ) -> Result<(), String> {
+        let mut anchors = anchors.to_vec();
+        anchors.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+        if anchors.windows(2).any(|p| p[1].1 < p[0].1) {
+            return Err("Remap anchors must keep their order".to_string());
+        }
+        self.transform_times(range, |t| remap_time(&anchors, t));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::AnimationData;
+
+    fn sample() -> AnimationData {
+        let mut data = AnimationData::default();
+        data.add_line("intro", 10.0, 12.0)
+            .add_keyframe(0.0, 0.0)
+            .add_keyframe(2.0, 5.0);
+        data.add_line("verse", 20.0, 24.0)
+            .add_keyframe(0.0, 0.0)
+            .add_keyframe(4.0, 5.0);
+        data
+    }
+
+    #[test]
+    fn test_shift_scale_remap() {
+        let mut data = sample();
+        data.shift_times(1..2, -5.0);
+        assert_eq!((data.lines[0].start, data.lines[1].start), (10.0, 15.0));
+        assert_eq!(data.lines[1].keyframes[1].time, 4.0);
+
+        let mut data = sample();
+        data.scale_times(0..2, 10.0, 0.5).unwrap();
+        assert_eq!((data.lines[1].start, data.lines[1].end), (15.0, 17.0));
+        assert_eq!(data.lines[1].keyframes[1].time, 2.0);
+        assert!(data.scale_times(0..2, 0.0, 0.0).is_err());
+
+        let mut data = sample();
+        data.remap_times(0..2, &[(10.0, 12.0), (20.0, 32.0)])
+            .unwrap();
+        assert_eq!((data.lines[0].start, data.lines[0].end), (12.0, 16.0));
+        assert_eq!(data.lines[0].keyframes[1].time, 4.0);
+        // after the last anchor everything moves by its offset
+        assert_eq!((data.lines[1].start, data.lines[1].end), (32.0, 36.0));
+        assert!(data.remap_times(0..2, &[(0.0, 5.0), (1.0, 2.0)]).is_err());
+    }
+
+    #[test]
+    fn test_distribute() {
+        let mut data = sample();
+        data.distribute(0..2, 0.0, 10.0).unwrap();
+        assert_eq!((data.lines[0].start, data.lines[0].end), (0.0, 5.0));
+        assert_eq!((data.lines[1].start, data.lines[1].end), (5.0, 10.0));
+        assert_eq!(data.lines[0].keyframes[1].time, 5.0);
+        assert!((data.lines[1].keyframes[1].time - 5.0).abs() < 1e-4);
+        assert!(data.distribute(0..2, 4.0, 4.0).is_err());
+    }
+
+    #[test]
+    fn test_times_inside_repeat() {
+        let mut data = AnimationData::default();
+        data.add_line("one", 0.0, 2.0);
+        data.add_line("two", 2.0, 4.0);
+        data.add_part("Chorus", 0, 2);
+        data.repeat_part(0, 2, 10.0).unwrap();
+
+        // a line inside the repeat moves its source, so the move sticks
+        data.shift_times(3..4, 1.0);
+        assert_eq!((data.lines[3].start, data.lines[3].end), (13.0, 15.0));
+        assert_eq!((data.lines[1].start, data.lines[1].end), (3.0, 5.0));
+
+        data.distribute(2..4, 20.0, 30.0).unwrap();
+        assert_eq!((data.lines[2].start, data.lines[2].end), (20.0, 25.0));
+        assert_eq!((data.lines[3].start, data.lines[3].end), (25.0, 30.0));
+        assert_eq!((data.lines[1].start, data.lines[1].end), (5.0, 10.0));
+        assert!(data.distribute(0..4, 0.0, 8.0).is_err());
+    }
+}
@@ -1,8 +1,297 @@
+use lyrics_helper_core::{
+    Agent, AgentStore, AgentType, AnnotatedTrack, ContentType, FuriganaSyllable, LyricLine,
+    LyricSyllable, LyricTrack, MetadataStore, TtmlGenerationOptions, TtmlParsingOptions, Word,
+};
+use ttml_processor::{generate_ttml, parse_ttml};
+
+use super::{AnimationData, Part, Ruby, TextSegment};
+
+fn ms_to_secs(ms: u64) -> f32 {
+    ms as f32 / 1000.0
+}
+
+fn secs_to_ms(secs: f32) -> u64 {
+    (secs.max(0.0) * 1000.0).round() as u64
+}
+
+impl TextSegment {
+    // inverse of get_current_index: relative time at which `index` is reached
+    pub fn time_at_index(&self, index: f32) -> f32 {
+        if self.keyframes.is_empty() {
+            let len = self.text.len().max(1) as f32;
+            return (self.end - self.start) * (index / len).clamp(0.0, 1.0);
+        }
+        for pair in self.keyframes.windows(2) {
+            let (k1, k2) = (&pair[0], &pair[1]);
+            if index >= k1.index && index <= k2.index {
+                if k2.index == k1.index {
+                    return k1.time;
+                }
+                let t = (index - k1.index) / (k2.index - k1.index);
+                return k1.time + (k2.time - k1.time) * t;
+            }
+        }
+        self.keyframes.last().map(|k| k.time).unwrap_or(0.0)
+    }
+
+    // syllables become keyframes, word furigana becomes ruby over the word's chars
+    pub fn from_lyric_line(line: &LyricLine) -> Self {
+        let mut segment = TextSegment::new(
+            String::new(),
+            ms_to_secs(line.start_ms),
+            ms_to_secs(line.end_ms),
+        );
+        segment.agent = line.agent.clone();
+        let Some(track) = line.main_track() else {
+            return segment;
+        };
+        let timed = track.content.syllables().count() > 1;
+
+        for word in &track.content.words {
+            let word_start = segment.text.chars().count();
+            for syl in &word.syllables {
+                if timed {
+                    let time = ms_to_secs(syl.start_ms.saturating_sub(line.start_ms));
+                    let index = segment.text.len() as f32;
+                    segment.add_keyframe(time, index);
+                }
+                segment.text.push_str(&syl.text);
+                if syl.ends_with_space {
+                    segment.text.push(' ');
+                }
+            }
+            let word_end = segment.text.trim_end().chars().count().max(word_start);
+
+            if let Some(furigana) = &word.furigana {
+                let reading: String = furigana.iter().map(|f| f.text.as_str()).collect();
+                let ruby = segment.add_ruby(word_start, word_end, &reading);
+                let mut reading_idx = 0;
+                for syl in furigana {
+                    if let Some((start, _)) = syl.timing {
+                        let time = ms_to_secs(start.saturating_sub(line.start_ms));
+                        ruby.add_keyframe(time, reading_idx as f32);
+                    }
+                    reading_idx += syl.text.chars().count();
+                }
+                if let Some((_, end)) = furigana.last().and_then(|f| f.timing) {
+                    let time = ms_to_secs(end.saturating_sub(line.start_ms));
+                    ruby.add_keyframe(time, reading_idx as f32);
+                }
+            }
+        }
+
+        segment.text = segment.text.trim_end().to_string();
+        if let Some(last) = track.content.syllables().last().filter(|_| timed) {
+            let time = ms_to_secs(last.end_ms.saturating_sub(line.start_ms));
+            let index = segment.text.len() as f32;
+            segment.add_keyframe(time, index);
+        }
+        segment
+    }
+
+    // words are split on whitespace and ruby boundaries so every ruby maps onto one word
+    pub fn to_lyric_line(&self) -> LyricLine {
+        let chars: Vec<char> = self.text.chars().collect();
+        let byte_offsets: Vec<usize> = self
+            .text
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain(std::iter::once(self.text.len()))
+            .collect();
+        let abs_ms = |char_idx: usize| {
+            secs_to_ms(self.start + self.time_at_index(byte_offsets[char_idx] as f32))
+        };
+
+        let mut cuts: Vec<usize> = self
+            .ruby
+            .iter()
+            .flat_map(|r| [r.start.min(chars.len()), r.end.min(chars.len())])
+            .collect();
+        cuts.sort_unstable();
+
+        let mut words = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            if chars[i].is_whitespace() {
+                i += 1;
+                continue;
+            }
+            let mut end = i + 1;
+            while end < chars.len() && !chars[end].is_whitespace() && !cuts.contains(&end) {
+                end += 1;
+            }
+
+            let syllable = LyricSyllable {
+                text: chars[i..end].iter().collect(),
+                start_ms: abs_ms(i),
+                end_ms: abs_ms(end),
+                ends_with_space: chars.get(end).is_some_and(|c| c.is_whitespace()),
+                ..Default::default()
+            };
+            let furigana = self
+                .ruby
+                .iter()
+                .find(|r| r.start == i && r.end == end)
+                .map(|r| self.ruby_to_furigana(r));
+
+            words.push(Word {
+                syllables: vec![syllable],
+                furigana,
+            });
+            i = end;
+        }
+
+        let mut line = LyricLine::new(secs_to_ms(self.start), secs_to_ms(self.end));
+        line.agent = self.agent.clone();
+        line.add_track(AnnotatedTrack {
+            content_type: ContentType::Main,
+            content: LyricTrack {
+                words,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        line
+    }
+
+    fn ruby_to_furigana(&self, ruby: &Ruby) -> Vec<FuriganaSyllable> {
+        if ruby.keyframes.len() < 2 {
+            return vec![FuriganaSyllable {
+                text: ruby.text.clone(),
+                timing: None,
+            }];
+        }
+
+        let reading: Vec<char> = ruby.text.chars().collect();
+        ruby.keyframes
+            .windows(2)
+            .filter_map(|pair| {
+                let (k1, k2) = (&pair[0], &pair[1]);
+                let from = (k1.index.round() as usize).min(reading.len());
+                let to = (k2.index.round() as usize).min(reading.len());
+                (to > from).then(|| FuriganaSyllable {
+                    text: reading[from..to].iter().collect(),
+                    timing: Some((
+                        secs_to_ms(self.start + k1.time),
+                        secs_to_ms(self.start + k2.time),
+                    )),
+                })
+            })
+            .collect()
+    }
+}
+
+impl AnimationData {
+    pub fn from_lyric_lines(lines: &[LyricLine]) -> Self {
+        let mut data = AnimationData::default();
+        let mut current_part: Option<&String> = None;
+        for (i, line) in lines.iter().enumerate() {
+            if line.song_part.as_ref() != current_part {
+                if let Some(open) = data.parts.last_mut().filter(|p| p.end == p.start) {
+                    open.end = i;
+                }
+                current_part = line.song_part.as_ref();
+                if let Some(name) = current_part {
+                    data.parts.push(Part::new(name, i, i));
+                }
+            }
+            data.lines.push(TextSegment::from_lyric_line(line));
+        }
+        if let Some(open) = data.parts.last_mut().filter(|p| p.end == p.start) {
+            open.end = lines.len();
+        }
+        data
+    }
+
+    pub fn to_lyric_lines(&self) -> Vec<LyricLine> {
+        self.lines
+            .iter()
+            .enumerate()
+            .map(|(i, segment)| {
+                let mut line = segment.to_lyric_line();
+                line.song_part = self.part_at(i).map(|pi| self.parts[pi].name.clone());
+                line
+            })
+            .collect()
+    }
+
+    // every agent a line refers to. lines without one are written as "v1",
+    // which needs declaring too once there are others
+    fn agent_store(&self) -> AgentStore {
+        let mut store = AgentStore::new();
+        if self.lines.iter().all(|l| l.agent.is_none()) {
+            return store;
+        }
+        let default = self.lines.iter().any(|l| l.agent.is_none()).then_some("v1");
+        let ids = self.lines.iter().filter_map(|l| l.agent.as_deref());
+        for id in ids.chain(default) {
+            store.agents_by_id.insert(
+                id.to_string(),
+                Agent {
+                    id: id.to_string(),
+                    name: None,
+                    agent_type: AgentType::Person,
+                },
+            );
+        }
+        store
+    }
+
+    // ttml_processor has no <ruby> support yet, ruby only survives through the LyricLine mapping
+    pub fn from_ttml(input: &str) -> Result<Self, String> {
+        let parsed =
+            parse_ttml(input, &TtmlParsingOptions::default()).map_err(|e| e.to_string())?;
+        Ok(Self::from_lyric_lines(&parsed.lines))
+    }
+
+    pub fn to_ttml(&self) -> Result<String, String> {
+        generate_ttml(
+            &self.to_lyric_lines(),
+            &MetadataStore::new(),
+            &self.agent_store(),
+            &TtmlGenerationOptions::default(),
+        )
+        .map_err(|e| e.to_string())
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::model::{AnimationData, TextSegment};
     use lyrics_helper_core::{ContentType, TtmlParsingOptions};
     use ttml_processor::parse_ttml;
 
+    #[test]
+    fn test_ruby_lyric_line_round_trip() {
+        let mut segment = TextSegment::new("東京 タワー".to_string(), 1.0, 3.0);
+        segment.add_keyframe(0.0, 0.0);
+        segment.add_keyframe(2.0, segment.text.len() as f32);
+        segment
+            .add_ruby(0, 2, "とうきょう")
+            .add_keyframe(0.0, 0.0)
+            .add_keyframe(0.4, 2.0)
+            .add_keyframe(0.8, 5.0);
+
+        let line = segment.to_lyric_line();
+        let words = &line.main_track().unwrap().content.words;
+        assert_eq!(words.len(), 2);
+        let furigana = words[0].furigana.as_ref().expect("furigana on first word");
+        assert_eq!(furigana.len(), 2);
+        assert_eq!(furigana[0].text, "とう");
+        assert_eq!(furigana[0].timing, Some((1000, 1400)));
+        assert!(words[1].furigana.is_none());
+
+        let back = AnimationData::from_lyric_lines(&[line]);
+        assert_eq!(back.lines[0].text, "東京 タワー");
+        assert_eq!(back.lines[0].ruby.len(), 1);
+        assert_eq!(back.lines[0].ruby[0].text, "とうきょう");
+        assert_eq!(
+            (back.lines[0].ruby[0].start, back.lines[0].ruby[0].end),
+            (0, 2)
+        );
+        assert_eq!(back.lines[0].ruby[0].keyframes.len(), 3);
+    }
+
     #[test]
     fn test_ttml_parsing_accuracy() {
         let ttml_content = r#"
@@ -90,12 +379,10 @@ mod tests {
             .find(|t| t.content_type == ContentType::Main)
             .expect("Main track missing on first line");
 
-        // Line is "City of stars" -> 3 words
-        assert_eq!(main_track.content.words.len(), 3);
+        // Line timed <p> without spans is a single syllable holding the whole line
+        assert_eq!(main_track.content.text(), "City of stars");
 
         let first_word = &main_track.content.words[0];
-        // Depending on your tokenizer, the first word is "City"
-        assert_eq!(first_word.syllables[0].text, "City");
         assert_eq!(first_word.syllables[0].start_ms, 9327);
 
         // 3. Verify Last Line (sanity check for full parsing)
@@ -0,0 +1,510 @@
+use std::fmt;
+
+use super::{
+    AnimationData, DATA_SECTION_SPLIT_MARKER, LINE_BY_LINE_TIMESTAMP_MARKER, LINE_RUBY_MARKER,
+    LINE_SYLABLE_KEYFRAME_MARKER, Part, SONG_PART_MARKER, format, split_top_level,
+};
+
+// keyframes may sit a hair past the line end because of the 3 decimal rounding
+const TIME_EPSILON: f32 = 0.0005;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DiagnosticKind {
+    EndBeforeStart,
+    KeyframeOutOfRange { keyframe: usize },
+    NonMonotonicKeyframes { keyframe: usize },
+    OverlappingLines { next: usize },
+    TimestampCountMismatch { lines: usize, entries: usize },
+    KeyframeCountMismatch { lines: usize, entries: usize },
+    RubyCountMismatch { lines: usize, entries: usize },
+    RubyOutOfRange { ruby: usize },
+    PartOutOfRange { part: usize },
+    InvalidRepeatSource { part: usize },
+}
+
+// a suggested edit that resolves a diagnostic
+#[derive(Clone, Debug, PartialEq)]
+pub enum Fix {
+    SetEnd { line: usize, end: f32 },
+    ClampKeyframes { line: usize },
+    MonotonicKeyframes { line: usize },
+    DropRuby { line: usize, ruby: usize },
+    SetPartEnd { part: usize, end: usize },
+    DropRepeat { part: usize },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub kind: DiagnosticKind,
+    pub line: Option<usize>,
+    pub message: String,
+    pub fix: Option<Fix>,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}: line {}: {}", self.severity, line + 1, self.message)?,
+            None => write!(f, "{}: {}", self.severity, self.message)?,
+        }
+        if let Some(fix) = &self.fix {
+            write!(f, " (fix: {})", fix.describe())?;
+        }
+        Ok(())
+    }
+}
+
+impl Fix {
+    pub fn describe(&self) -> String {
+        match self {
+            Fix::SetEnd { end, .. } => format!("set end to {:.3}", end),
+            Fix::ClampKeyframes { .. } => "clamp keyframes into the line".to_string(),
+            Fix::MonotonicKeyframes { .. } => "raise indices to keep them increasing".to_string(),
+            Fix::DropRuby { .. } => "remove the ruby".to_string(),
+            Fix::SetPartEnd { end, .. } => format!("end part at line {}", end),
+            Fix::DropRepeat { .. } => "turn the repeat into a plain part".to_string(),
+        }
+    }
+
+    pub fn apply(&self, data: &mut AnimationData) {
+        match *self {
+            Fix::SetEnd { line, end } => {
+                if let Some(l) = data.lines.get_mut(line) {
+                    l.end = end;
+                }
+            }
+            Fix::ClampKeyframes { line } => {
+                if let Some(l) = data.lines.get_mut(line) {
+                    let duration = (l.end - l.start).max(0.0);
+                    let len = l.text.len() as f32;
+                    for kf in &mut l.keyframes {
+                        kf.time = kf.time.clamp(0.0, duration);
+                        kf.index = kf.index.clamp(0.0, len);
+                    }
+                    l.sort_keyframes();
+                }
+            }
+            Fix::MonotonicKeyframes { line } => {
+                if let Some(l) = data.lines.get_mut(line) {
+                    let mut max = 0.0f32;
+                    for kf in &mut l.keyframes {
+                        max = max.max(kf.index);
+                        kf.index = max;
+                    }
+                }
+            }
+            Fix::DropRuby { line, ruby } => {
+                if let Some(l) = data.lines.get_mut(line)
+                    && ruby < l.ruby.len()
+                {
+                    l.ruby.remove(ruby);
+                }
+            }
+            Fix::SetPartEnd { part, end } => {
+                if let Some(p) = data.parts.get_mut(part) {
+                    p.end = end.max(p.start);
+                }
+            }
+            Fix::DropRepeat { part } => {
+                if let Some(p) = data.parts.get_mut(part) {
+                    p.repeat = None;
+                }
+            }
+        }
+    }
+}
+
+impl Diagnostic {
+    fn new(severity: Severity, kind: DiagnosticKind, line: Option<usize>, message: String) -> Self {
+        Self {
+            severity,
+            kind,
+            line,
+            message,
+            fix: None,
+        }
+    }
+
+    fn with_fix(mut self, fix: Fix) -> Self {
+        self.fix = Some(fix);
+        self
+    }
+}
+
+impl AnimationData {
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+
+        for (i, line) in self.lines.iter().enumerate() {
+            let duration = line.end - line.start;
+            if duration < 0.0 {
+                diagnostics.push(
+                    Diagnostic::new(
+                        Severity::Error,
+                        DiagnosticKind::EndBeforeStart,
+                        Some(i),
+                        format!(
+                            "ends at {:.3} before it starts at {:.3}",
+                            line.end, line.start
+                        ),
+                    )
+                    .with_fix(Fix::SetEnd {
+                        line: i,
+                        end: line.start,
+                    }),
+                );
+            }
+
+            let len = line.text.len() as f32;
+            if let Some(k) = line.keyframes.iter().position(|kf| {
+                kf.time < -TIME_EPSILON
+                    || kf.time > duration.max(0.0) + TIME_EPSILON
+                    || kf.index < 0.0
+                    || kf.index > len
+            }) {
+                diagnostics.push(
+                    Diagnostic::new(
+                        Severity::Warning,
+                        DiagnosticKind::KeyframeOutOfRange { keyframe: k },
+                        Some(i),
+                        format!(
+                            "keyframe {} lies outside [0, {:.3}] or past the text",
+                            k,
+                            duration.max(0.0)
+                        ),
+                    )
+                    .with_fix(Fix::ClampKeyframes { line: i }),
+                );
+            }
+
+            if let Some(k) = line
+                .keyframes
+                .windows(2)
+                .position(|pair| pair[1].index < pair[0].index)
+            {
+                diagnostics.push(
+                    Diagnostic::new(
+                        Severity::Warning,
+                        DiagnosticKind::NonMonotonicKeyframes { keyframe: k + 1 },
+                        Some(i),
+                        format!("keyframe {} moves backwards through the text", k + 1),
+                    )
+                    .with_fix(Fix::MonotonicKeyframes { line: i }),
+                );
+            }
+
+            let chars = line.text.chars().count();
+            for (r, ruby) in line.ruby.iter().enumerate() {
+                if ruby.start >= ruby.end || ruby.end > chars {
+                    diagnostics.push(
+                        Diagnostic::new(
+                            Severity::Warning,
+                            DiagnosticKind::RubyOutOfRange { ruby: r },
+                            Some(i),
+                            format!(
+                                "ruby \"{}\" covers {}..{} of a {} char line",
+                                ruby.text, ruby.start, ruby.end, chars
+                            ),
+                        )
+                        .with_fix(Fix::DropRuby { line: i, ruby: r }),
+                    );
+                }
+            }
+
+            // gap lines have no text and may overlap freely, so do lines two
+            // different singers sing at once
+            if let Some(next) = self.lines.get(i + 1)
+                && !line.text.trim().is_empty()
+                && !next.text.trim().is_empty()
+                && !matches!((&line.agent, &next.agent), (Some(a), Some(b)) if a != b)
+                && line.end > next.start + TIME_EPSILON
+            {
+                diagnostics.push(
+                    Diagnostic::new(
+                        Severity::Warning,
+                        DiagnosticKind::OverlappingLines { next: i + 1 },
+                        Some(i),
+                        format!("overlaps the next line by {:.3}s", line.end - next.start),
+                    )
+                    .with_fix(Fix::SetEnd {
+                        line: i,
+                        end: next.start.max(line.start),
+                    }),
+                );
+            }
+        }
+
+        for (pi, part) in self.parts.iter().enumerate() {
+            let limit = self
+                .parts
+                .get(pi + 1)
+                .map_or(self.lines.len(), |n| n.start)
+                .min(self.lines.len());
+            if part.end > limit {
+                diagnostics.push(
+                    Diagnostic::new(
+                        Severity::Error,
+                        DiagnosticKind::PartOutOfRange { part: pi },
+                        Some(part.start),
+                        format!(
+                            "part [{}] runs to line {} past line {}",
+                            part.name, part.end, limit
+                        ),
+                    )
+                    .with_fix(Fix::SetPartEnd {
+                        part: pi,
+                        end: limit,
+                    }),
+                );
+            }
+
+            if let Some(r) = &part.repeat
+                && self.repeat_source(pi).is_none()
+            {
+                diagnostics.push(
+                    Diagnostic::new(
+                        Severity::Error,
+                        DiagnosticKind::InvalidRepeatSource { part: pi },
+                        Some(part.start),
+                        format!(
+                            "part [{}] repeats part {} which is not an original part",
+                            part.name, r.source
+                        ),
+                    )
+                    .with_fix(Fix::DropRepeat { part: pi }),
+                );
+            }
+        }
+
+        diagnostics
+    }
+
+    // applies every suggested fix, returns how many were applied
+    pub fn apply_fixes(&mut self, diagnostics: &[Diagnostic]) -> usize {
+        // reverse order keeps ruby indices valid while removing
+        let mut applied = 0;
+        for fix in diagnostics.iter().rev().filter_map(|d| d.fix.as_ref()) {
+            fix.apply(self);
+            applied += 1;
+        }
+        applied
+    }
+}
+
+fn count_mismatch(
+    diagnostics: &mut Vec<Diagnostic>,
+    severity: Severity,
+    kind: DiagnosticKind,
+    marker: &str,
+    lines: usize,
+    entries: usize,
+) {
+    diagnostics.push(Diagnostic::new(
+        severity,
+        kind,
+        None,
+        format!("{} has {} entries for {} lines", marker, entries, lines),
+    ));
+}
+
+// parses `input` and also reports the problems the parser silently skips over
+pub fn validate_document(input: &str) -> Result<(AnimationData, Vec<Diagnostic>), String> {
+    let data = input.parse::<AnimationData>()?;
+    let mut diagnostics = Vec::new();
+    let lines = data.lines.len();
+
+    let data_section = input
+        .split(DATA_SECTION_SPLIT_MARKER)
+        .nth(1)
+        .unwrap_or("")
+        .trim();
+
+    let lbl = data.extract_section(data_section, LINE_BY_LINE_TIMESTAMP_MARKER)?;
+    let lbl = format::strip_compact(&lbl).unwrap_or(&lbl);
+    let entries = if lbl.trim().is_empty() {
+        0
+    } else {
+        lbl.split(',').count()
+    };
+    if entries != lines {
+        // lines without a timestamp are left at 0.000/0.000
+        let severity = if entries < lines {
+            Severity::Error
+        } else {
+            Severity::Warning
+        };
+        let kind = DiagnosticKind::TimestampCountMismatch { lines, entries };
+        count_mismatch(
+            &mut diagnostics,
+            severity,
+            kind,
+            LINE_BY_LINE_TIMESTAMP_MARKER,
+            lines,
+            entries,
+        );
+    }
+
+    let lsk = data.extract_section(data_section, LINE_SYLABLE_KEYFRAME_MARKER)?;
+    let lsk = format::strip_compact(&lsk).unwrap_or(&lsk);
+    let entries = split_top_level(lsk, ',').len();
+    if entries != lines {
+        let kind = DiagnosticKind::KeyframeCountMismatch { lines, entries };
+        count_mismatch(
+            &mut diagnostics,
+            Severity::Warning,
+            kind,
+            LINE_SYLABLE_KEYFRAME_MARKER,
+            lines,
+            entries,
+        );
+    }
+
+    if let Ok(rby) = data.extract_section(data_section, LINE_RUBY_MARKER) {
+        let entries = split_top_level(&rby, ',').len();
+        if entries != lines {
+            let kind = DiagnosticKind::RubyCountMismatch { lines, entries };
+            count_mismatch(
+                &mut diagnostics,
+                Severity::Warning,
+                kind,
+                LINE_RUBY_MARKER,
+                lines,
+                entries,
+            );
+        }
+    }
+
+    // the parser cuts [prt] entries to fit the lines read before repeats are
+    // filled in, what they said is only seen here
+    if let Ok(prt) = data.extract_section(data_section, SONG_PART_MARKER) {
+        let mut read = AnimationData::default();
+        read.parse_lines(
+            &input
+                .split(DATA_SECTION_SPLIT_MARKER)
+                .next()
+                .unwrap_or("")
+                .replace("\n\n\n", "\n\n"),
+        );
+        let lines = read.lines.len();
+        for ((pi, part), entry) in data.parts.iter().enumerate().zip(prt.split(',')) {
+            let mut raw = Part::new(&part.name, 0, 0);
+            if raw.apply_string_entry(entry).is_none() {
+                continue;
+            }
+            if raw.end > lines {
+                diagnostics.push(Diagnostic::new(
+                    Severity::Error,
+                    DiagnosticKind::PartOutOfRange { part: pi },
+                    Some(part.start),
+                    format!(
+                        "{} gives part [{}] lines {}..{} of {}, read as {}..{}",
+                        SONG_PART_MARKER,
+                        part.name,
+                        raw.start,
+                        raw.end,
+                        lines,
+                        part.start,
+                        part.end
+                    ),
+                ));
+            }
+            if let Some(r) = raw.repeat
+                && part.repeat.is_none()
+            {
+                diagnostics.push(Diagnostic::new(
+                    Severity::Error,
+                    DiagnosticKind::InvalidRepeatSource { part: pi },
+                    Some(part.start),
+                    format!(
+                        "{} repeats part {} in [{}] which is not an original part, read as a plain part",
+                        SONG_PART_MARKER, r.source, part.name
+                    ),
+                ));
+            }
+        }
+    }
+
+    diagnostics.extend(data.validate());
+    Ok((data, diagnostics))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_reports_and_fixes() {
+        let input = "a\nb\n\n\n\n[//]\n[lbl][0.000/2.000,1.500/1.000]\n[lsk][(0.000/0.000,3.000/0.500,0.500/1.000),()]\n";
+        let (mut data, diagnostics) = validate_document(input).unwrap();
+
+        let kinds: Vec<&DiagnosticKind> = diagnostics.iter().map(|d| &d.kind).collect();
+        assert!(kinds.contains(&&DiagnosticKind::TimestampCountMismatch {
+            lines: 3,
+            entries: 2
+        }));
+        assert!(kinds.contains(&&DiagnosticKind::KeyframeCountMismatch {
+            lines: 3,
+            entries: 2
+        }));
+        assert!(kinds.contains(&&DiagnosticKind::EndBeforeStart));
+        assert!(kinds.contains(&&DiagnosticKind::KeyframeOutOfRange { keyframe: 2 }));
+        assert!(kinds.contains(&&DiagnosticKind::NonMonotonicKeyframes { keyframe: 2 }));
+        assert!(kinds.contains(&&DiagnosticKind::OverlappingLines { next: 1 }));
+
+        data.apply_fixes(&diagnostics);
+        let remaining = data.validate();
+        assert!(remaining.is_empty(), "{:?}", remaining);
+    }
+
+    #[test]
+    fn test_duet_lines_may_overlap() {
+        let mut data = AnimationData::default();
+        data.add_line("first voice", 0.0, 3.0);
+        data.add_line("second voice", 2.0, 4.0);
+        data.add_line("second again", 3.5, 5.0);
+        data.lines[0].agent = Some("v1".to_string());
+        data.lines[1].agent = Some("v2".to_string());
+        data.lines[2].agent = Some("v2".to_string());
+
+        let kinds: Vec<DiagnosticKind> = data.validate().into_iter().map(|d| d.kind).collect();
+        assert_eq!(kinds, [DiagnosticKind::OverlappingLines { next: 2 }]);
+    }
+
+    #[test]
+    fn test_parts_past_the_lines() {
+        let input = "[A]\na\n[B]\nb\n\n\n\n[//]\n[lbl][0.000/1.000,1.000/2.000]\n[lsk][(),()]\n[prt][0/5,1/2/0/5]\n";
+        let (data, diagnostics) = validate_document(input).unwrap();
+        // a, b and the trailing blank line, then the repeat of all three
+        assert_eq!((data.parts[0].start, data.parts[0].end), (0, 3));
+        assert!(data.parts[1].repeat.is_some());
+        assert_eq!(data.lines.len(), 6);
+
+        let kinds: Vec<&DiagnosticKind> = diagnostics.iter().map(|d| &d.kind).collect();
+        assert!(kinds.contains(&&DiagnosticKind::PartOutOfRange { part: 0 }));
+
+        let input = input.replace("1/2/0/5", "1/2/1/5");
+        let (data, diagnostics) = validate_document(&input).unwrap();
+        assert!(data.parts[1].repeat.is_none());
+        let kinds: Vec<&DiagnosticKind> = diagnostics.iter().map(|d| &d.kind).collect();
+        assert!(kinds.contains(&&DiagnosticKind::InvalidRepeatSource { part: 1 }));
+    }
+}
@@ -4,14 +4,59 @@ use axum::{
         State,
         ws::{Message, WebSocket, WebSocketUpgrade},
     },
+    http::{HeaderMap, StatusCode, header},
+    response::IntoResponse,
     routing::get,
 };
 use futures_util::{SinkExt, StreamExt};
-use std::net::SocketAddr;
+use serde::Deserialize;
+use std::net::{IpAddr, SocketAddr};
 use tokio::sync::broadcast;
 use tower_http::services::ServeDir;
 
-pub fn start_server(port: u16) -> broadcast::Sender<String> {
+use crate::tui::Action;
+
+// clients drive the editor with `{"action": "play_pause"}`, `{"action": {"seek": 12.5}}`
+#[derive(Deserialize)]
+struct RemoteAction {
+    action: Action,
+}
+
+pub fn parse_action(text: &str) -> Option<Action> {
+    serde_json::from_str::<RemoteAction>(text)
+        .ok()
+        .map(|r| r.action)
+}
+
+// browsers send the page's origin, only the client page served from here may
+// connect. the host has to be an address, a domain could be rebound to ours.
+// other clients send no origin and are let through
+fn from_own_page(headers: &HeaderMap) -> bool {
+    let Some(origin) = headers.get(header::ORIGIN) else {
+        return true;
+    };
+    let (Ok(origin), Some(Ok(host))) = (
+        origin.to_str(),
+        headers.get(header::HOST).map(|h| h.to_str()),
+    ) else {
+        return false;
+    };
+    let own = ["http://", "https://"]
+        .iter()
+        .any(|scheme| origin.strip_prefix(scheme) == Some(host));
+    let name = match host.rsplit_once(':') {
+        Some((name, port)) if !port.contains(']') => name,
+        _ => host,
+    };
+    own && (name == "localhost"
+        || name
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>()
+            .is_ok())
+}
+
+pub fn start_server(addr: SocketAddr) -> broadcast::Sender<String> {
     // Create a broadcast channel for commands
     let (tx, _rx) = broadcast::channel::<String>(16);
     let tx_clone = tx.clone();
@@ -23,7 +68,12 @@ pub fn start_server(port: u16) -> broadcast::Sender<String> {
             .route(
                 "/ws",
                 get(
-                    |ws: WebSocketUpgrade, State(tx): State<broadcast::Sender<String>>| async move {
+                    |ws: WebSocketUpgrade,
+                     headers: HeaderMap,
+                     State(tx): State<broadcast::Sender<String>>| async move {
+                        if !from_own_page(&headers) {
+                            return StatusCode::FORBIDDEN.into_response();
+                        }
                         ws.on_upgrade(move |socket| handle_socket(socket, tx))
                     },
                 ),
@@ -35,7 +85,6 @@ pub fn start_server(port: u16) -> broadcast::Sender<String> {
             )));
 
         // Run it
-        let addr = SocketAddr::from(([127, 0, 0, 1], port));
         let listener = tokio::net::TcpListener::bind(addr).await;
         match listener {
             Ok(listener) => {
@@ -87,3 +136,42 @@ async fn handle_socket(socket: WebSocket, tx: broadcast::Sender<String>) {
     };
     let _ = tx.send("Disconnected".to_string());
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(origin: Option<&str>, host: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::HOST, host.parse().unwrap());
+        if let Some(origin) = origin {
+            headers.insert(header::ORIGIN, origin.parse().unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn test_only_own_page_connects() {
+        assert!(from_own_page(&headers(
+            Some("http://127.0.0.1:3000"),
+            "127.0.0.1:3000"
+        )));
+        assert!(from_own_page(&headers(
+            Some("http://[::1]:3000"),
+            "[::1]:3000"
+        )));
+        assert!(from_own_page(&headers(
+            Some("http://localhost:3000"),
+            "localhost:3000"
+        )));
+        assert!(from_own_page(&headers(None, "127.0.0.1:3000")));
+        assert!(!from_own_page(&headers(
+            Some("https://evil.example"),
+            "127.0.0.1:3000"
+        )));
+        assert!(!from_own_page(&headers(
+            Some("http://evil.example:3000"),
+            "evil.example:3000"
+        )));
+    }
+}
@@ -0,0 +1,466 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::model::AnimationData;
+use crate::tui::app::App;
+use crate::tui::history::History;
+use crate::tui::types::ViewMode;
+
+// everything the editor can be asked to do. keys, pastes, macros, scripts and the
+// remote all go through `App::apply`. unit actions are named as in the `[keys.*]`
+// config tables, action logs hold one JSON action per line: "save", {"seek": 12.5}
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Quit,
+    Save,
+    SaveAs,
+    PlayPause,
+    EditText,
+    FocusMode,
+    SeekBack,
+    SeekForward,
+    ToggleView,
+    Undo,
+    Redo,
+    RecordMacro,
+    PlayMacro,
+
+    PrevDraft,
+    NextDraft,
+    OpenDraft,
+    NewDraft,
+    RestoreJournal,
+    DiffJournal,
+    DiscardJournal,
+    RestoreUntitled,
+
+    ScrollUp,
+    ScrollDown,
+    PrevLine,
+    NextLine,
+    Prompt,
+    ToggleDiagnostics,
+    ApplyFixes,
+    RepeatPart,
+    TapSync,
+    // grow the selection of lines, from the cursor if there is none yet
+    SelectUp,
+    SelectDown,
+    DeleteLines,
+    CopyLines,
+    PasteLines,
+    // lines copied by `CopyLines`, in the draft format
+    InsertLines(String),
+    Search,
+    NextMatch,
+    PrevMatch,
+    GoTo,
+
+    WordSync,
+    ToggleSplit,
+    Tap,
+    TapRelease,
+    UndoTap,
+
+    Timeline,
+    ZoomIn,
+    ZoomOut,
+    PanLeft,
+    PanRight,
+    // columns, negative towards the start
+    Pan(i32),
+    FollowPlayhead,
+    MoveEarlier,
+    MoveLater,
+    StartEarlier,
+    StartLater,
+    EndEarlier,
+    EndLater,
+
+    ToggleEditMode,
+    AddKeyframe,
+    DeleteKeyframe,
+    NudgeUp,
+    NudgeDown,
+    PrevKeyframe,
+    NextKeyframe,
+    // clicked in the keyframe editor
+    SelectKeyframe(usize),
+
+    FinishEditing,
+    CursorLeft,
+    CursorRight,
+    CursorUp,
+    CursorDown,
+    MoveLineUp,
+    MoveLineDown,
+    Backspace,
+    SplitLine,
+    PasteClipboard,
+    Type(char),
+    InsertText(String),
+    // line and char index, clicked in the text editor
+    PlaceCursor(usize, usize),
+
+    PromptInput(char),
+    PromptBackspace,
+    PromptCancel,
+    PromptSubmit,
+    // a `:` command line without going through the prompt
+    Run(String),
+    // absolute, in seconds
+    Seek(f32),
+    // an action with the files it reads, read beforehand by `dispatch`
+    Read {
+        action: Box<Action>,
+        files: BTreeMap<String, Result<String, String>>,
+    },
+}
+
+impl Action {
+    // the remote may play, seek and look around, nothing that edits the
+    // document or touches files
+    pub fn allowed_remotely(&self) -> bool {
+        matches!(
+            self,
+            Action::PlayPause
+                | Action::SeekBack
+                | Action::SeekForward
+                | Action::Seek(_)
+                | Action::ScrollUp
+                | Action::ScrollDown
+                | Action::PrevLine
+                | Action::NextLine
+                | Action::ZoomIn
+                | Action::ZoomOut
+                | Action::PanLeft
+                | Action::PanRight
+                | Action::Pan(_)
+                | Action::FollowPlayhead
+        )
+    }
+
+    // plain cursor movement ends a selection
+    fn clears_selection(&self) -> bool {
+        matches!(
+            self,
+            Action::ScrollUp
+                | Action::ScrollDown
+                | Action::PrevLine
+                | Action::NextLine
+                | Action::CursorUp
+                | Action::CursorDown
+                | Action::PlaceCursor(_, _)
+        )
+    }
+
+    fn is_text_edit(&self) -> bool {
+        matches!(
+            self,
+            Action::FinishEditing
+                | Action::CursorLeft
+                | Action::CursorRight
+                | Action::CursorUp
+                | Action::CursorDown
+                | Action::MoveLineUp
+                | Action::MoveLineDown
+                | Action::Backspace
+                | Action::SplitLine
+                | Action::Type(_)
+                | Action::InsertText(_)
+                | Action::PlaceCursor(_, _)
+        )
+    }
+}
+
+impl App {
+    // the prompt and the text editor take plain characters as input
+    pub fn action_for(&self, key: &KeyEvent) -> Option<Action> {
+        // only tap sync cares how long a key is held
+        if key.kind != KeyEventKind::Press {
+            let bound = self.config.keys.action(self.view_mode, key);
+            return match (self.view_mode, key.kind, bound) {
+                (ViewMode::TapSync, KeyEventKind::Release, Some(Action::Tap)) => {
+                    Some(Action::TapRelease)
+                }
+                (ViewMode::TapSync, _, Some(Action::Tap)) | (_, KeyEventKind::Release, _) => None,
+                _ => self.action_for(&KeyEvent {
+                    kind: KeyEventKind::Press,
+                    ..*key
+                }),
+            };
+        }
+        let typed = match key.code {
+            KeyCode::Char(c)
+                if !key
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                Some(c)
+            }
+            _ => None,
+        };
+
+        if self.prompt.is_some() {
+            return match key.code {
+                KeyCode::Esc => Some(Action::PromptCancel),
+                KeyCode::Backspace => Some(Action::PromptBackspace),
+                KeyCode::Enter => Some(Action::PromptSubmit),
+                _ => typed.map(Action::PromptInput),
+            };
+        }
+        let bound = self.config.keys.action(self.view_mode, key);
+        if self.view_mode == ViewMode::TextEdit {
+            return bound.or(typed.map(Action::Type));
+        }
+        bound
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) {
+        if let Some(action) = self.action_for(&key) {
+            self.dispatch(action);
+        }
+    }
+
+    // runs one action against the editor state, whatever it changed becomes one undo step.
+    // i/o is left in `effects` for the caller, the clipboard already read into the action
+    pub fn apply(&mut self, action: Action) {
+        // a replay is recorded as the action that started it
+        if let Some(recording) = &mut self.recording
+            && self.replay_depth == 0
+            && !matches!(action, Action::RecordMacro | Action::PlayMacro)
+        {
+            recording.push(action.clone());
+        }
+        let action = match action {
+            Action::Read { action, files } => {
+                self.files.extend(files);
+                *action
+            }
+            action => action,
+        };
+        if action != Action::Quit {
+            self.quit_armed = false;
+        }
+        if action.clears_selection() {
+            self.selection = None;
+        }
+
+        match action {
+            Action::Quit => self.should_quit = self.confirm_quit(),
+            Action::Save => self.save(),
+            Action::SaveAs => self.open_save_as(),
+            Action::PlayPause => {
+                self.is_playing = !self.is_playing;
+
+                if let Some(last_line) = self.data.lines.last()
+                    && self.current_time > last_line.end
+                {
+                    self.set_time(0.0);
+                    self.is_playing = false;
+                    self.server_status = "EOF reached".to_string();
+                }
+
+                let cmd = if self.is_playing { "play" } else { "pause" };
+                self.send_remote(cmd.to_string());
+            }
+            Action::EditText => {
+                self.view_mode = ViewMode::TextEdit;
+                if self.focus_line_index.is_none() {
+                    self.focus_line_index = Some(self.scroll_offset);
+                }
+                self.keep_in_bounds();
+                if let Some(idx) = self.focus_line_index {
+                    self.cursor_col = self.data.lines[idx].text.chars().count();
+                }
+            }
+            Action::FocusMode => {
+                self.manual_scroll = false;
+                self.focus_line_index = self.get_active_line_index();
+                self.active_kf_index = None;
+                self.view_mode = ViewMode::Line;
+            }
+            Action::SeekBack | Action::SeekForward => {
+                let step = if action == Action::SeekBack {
+                    -self.config.steps.seek
+                } else {
+                    self.config.steps.seek
+                };
+                self.active_kf_index = None;
+                self.set_time((self.current_time + step).max(0.0));
+                if self.view_mode == ViewMode::Line {
+                    self.focus_line_index = self.get_active_line_index();
+                }
+            }
+            Action::ToggleView => self.toggle_view_mode(),
+            Action::Undo => self.undo(),
+            Action::Redo => self.redo(),
+
+            Action::PrevDraft if self.selected_draft > 0 => {
+                self.selected_draft -= 1;
+                self.recovery_diff = None;
+            }
+            Action::NextDraft if self.selected_draft + 1 < self.drafts.len() => {
+                self.selected_draft += 1;
+                self.recovery_diff = None;
+            }
+            Action::OpenDraft | Action::NewDraft if self.is_dirty() => {
+                self.server_status = "Unsaved changes, [S]ave them first".to_string();
+            }
+            Action::OpenDraft => {
+                if !self.drafts.is_empty() {
+                    let path = self.drafts[self.selected_draft].clone();
+                    self.load_file(&path);
+                } else {
+                    self.view_mode = ViewMode::List;
+                }
+            }
+            Action::NewDraft => {
+                self.data = AnimationData::new();
+                self.saved = self.data.clone();
+                self.history = History::new(&self.data);
+                self.file_path = None;
+                self.view_mode = ViewMode::List;
+            }
+            Action::RestoreJournal
+            | Action::DiffJournal
+            | Action::DiscardJournal
+            | Action::RestoreUntitled => self.handle_recovery_action(action),
+
+            Action::PrevLine | Action::NextLine if self.view_mode == ViewMode::Line => {
+                if let Some(curr) = self.focus_line_index {
+                    let next = if action == Action::NextLine {
+                        curr + 1
+                    } else {
+                        curr.wrapping_sub(1)
+                    };
+                    if next < self.data.lines.len() {
+                        self.focus_line_index = Some(next);
+                        self.set_time(self.data.lines[next].start);
+                    }
+                }
+            }
+            Action::ScrollUp | Action::ScrollDown if self.view_mode == ViewMode::Timeline => {
+                self.handle_timeline_action(action)
+            }
+            Action::PrevLine => self.seek_list(-1),
+            Action::NextLine => self.seek_list(1),
+            Action::ScrollUp => {
+                self.manual_scroll = true;
+                if self.scroll_offset > 0 {
+                    self.scroll_offset -= 1;
+                }
+            }
+            Action::ScrollDown => {
+                self.manual_scroll = true;
+                if self.scroll_offset + 1 < self.data.lines.len() {
+                    self.scroll_offset += 1;
+                }
+            }
+            Action::Prompt => self.open_prompt(),
+            Action::ToggleDiagnostics => {
+                self.show_diagnostics = !self.show_diagnostics;
+                self.server_status = Self::diagnostics_summary(&self.data.validate());
+            }
+            Action::ApplyFixes => {
+                let diagnostics = self.data.validate();
+                if !diagnostics.is_empty() {
+                    let applied = self.data.apply_fixes(&diagnostics);
+                    self.data.sync_repeats();
+                    self.server_status = format!(
+                        "Applied {} fixes, {}",
+                        applied,
+                        Self::diagnostics_summary(&self.data.validate())
+                    );
+                }
+            }
+            Action::RepeatPart => {
+                // repeat the selected line's part at the playhead
+                if let Some(pi) = self.data.part_at(self.scroll_offset) {
+                    let at = self
+                        .data
+                        .lines
+                        .iter()
+                        .position(|l| l.start >= self.current_time)
+                        .unwrap_or(self.data.lines.len());
+                    if let Some(new_pi) = self.data.repeat_part(pi, at, self.current_time) {
+                        self.scroll_offset = self.data.parts[new_pi].start;
+                        self.server_status = format!("Repeated [{}]", self.data.parts[new_pi].name);
+                    }
+                }
+            }
+
+            Action::SelectUp
+            | Action::SelectDown
+            | Action::DeleteLines
+            | Action::CopyLines
+            | Action::InsertLines(_) => self.handle_selection_action(action),
+
+            Action::Search | Action::NextMatch | Action::PrevMatch | Action::GoTo => {
+                self.handle_search_action(action)
+            }
+
+            Action::ToggleEditMode
+            | Action::AddKeyframe
+            | Action::DeleteKeyframe
+            | Action::NudgeUp
+            | Action::NudgeDown
+            | Action::PrevKeyframe
+            | Action::NextKeyframe
+            | Action::SelectKeyframe(_) => self.handle_keyframe_action(action),
+
+            Action::TapSync
+            | Action::WordSync
+            | Action::ToggleSplit
+            | Action::Tap
+            | Action::TapRelease
+            | Action::UndoTap => self.handle_tap_action(action),
+
+            Action::Timeline
+            | Action::ZoomIn
+            | Action::ZoomOut
+            | Action::PanLeft
+            | Action::PanRight
+            | Action::Pan(_)
+            | Action::FollowPlayhead
+            | Action::MoveEarlier
+            | Action::MoveLater
+            | Action::StartEarlier
+            | Action::StartLater
+            | Action::EndEarlier
+            | Action::EndLater => self.handle_timeline_action(action),
+
+            Action::RecordMacro => self.toggle_recording(),
+            Action::PlayMacro => self.play_macro(),
+            Action::PromptInput(_)
+            | Action::PromptBackspace
+            | Action::PromptCancel
+            | Action::PromptSubmit => self.prompt_action(action),
+            Action::Run(command) => {
+                self.server_status = match self.run_command(&command) {
+                    Ok(msg) => msg,
+                    Err(e) => format!("Error: {}", e),
+                };
+            }
+            Action::Seek(time) => {
+                self.active_kf_index = None;
+                self.set_time(time.max(0.0));
+                if self.view_mode == ViewMode::Line {
+                    self.focus_line_index = self.get_active_line_index();
+                }
+            }
+            Action::PasteClipboard | Action::PasteLines => {
+                self.server_status = "Nothing pasted, the clipboard was not read".to_string();
+            }
+            action if action.is_text_edit() => self.text_action(action),
+            _ => {}
+        }
+        self.keep_in_bounds();
+        self.record_edit();
+        if self.replay_depth == 0 {
+            self.files.clear();
+        }
+    }
+}
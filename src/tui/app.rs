@@ -1,6 +1,17 @@
-use crate::model::AnimationData;
+use crate::config::Config;
+use crate::convert;
+use crate::model::{AnimationData, Diagnostic, Severity};
+use crate::tui::action::Action;
+use crate::tui::clock::Clock;
+use crate::tui::effect::Effect;
+use crate::tui::history::History;
+use crate::tui::mouse::Drag;
+use crate::tui::search::Search;
+use crate::tui::tap_sync::{TapSync, WordSync};
+use crate::tui::timeline::Timeline;
 use crate::tui::types::{EditMode, ViewMode};
-use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
 use std::time::Instant;
 
 pub struct App {
@@ -12,33 +23,79 @@ pub struct App {
     pub edit_mode: EditMode,
     pub scroll_offset: usize,
     pub manual_scroll: bool,
+    pub clock: Clock,
     pub last_tick: Instant,
 
     pub focus_line_index: Option<usize>,
     pub active_kf_index: Option<usize>,
     pub cursor_col: usize,
-    pub history: Vec<AnimationData>,
-    pub history_index: usize,
+    // where a selection of lines was started, the cursor line is its other end
+    pub selection: Option<usize>,
+    pub tap: TapSync,
+    pub words: WordSync,
+    pub timeline: Timeline,
+    // the terminal reports key releases, for lines held in tap sync
+    pub key_releases: bool,
+    pub drag: Option<Drag>,
+    pub history: History,
     pub remote_tx: tokio::sync::broadcast::Sender<String>,
+    // left by `apply` for `run_effects`
+    pub effects: Vec<Effect>,
+    // what `dispatch` read for the action being applied, by path
+    pub files: BTreeMap<String, Result<String, String>>,
+    // effects may only write files under here, the test harness keeps to its folder
+    pub write_root: Option<PathBuf>,
     pub server_status: String,
 
+    pub config: Config,
+
+    // paths of the documents in the drafts roots
     pub drafts: Vec<String>,
     pub selected_draft: usize,
+    pub show_diagnostics: bool,
+    pub prompt: Option<String>,
+    pub search: Search,
+    // lines whose timings were interpolated by `retime`
+    pub review_lines: Vec<usize>,
+
+    // where `s` saves to, None for a new draft
+    pub file_path: Option<String>,
+    // the document as last loaded or saved, to tell unsaved changes
+    pub saved: AnimationData,
+    pub quit_armed: bool,
+    // set by `Action::Quit` once there is nothing left to save
+    pub should_quit: bool,
+    // actions since `m` while recording, and the last finished macro
+    pub recording: Option<Vec<Action>>,
+    pub last_macro: Vec<Action>,
+    pub replay_depth: usize,
+    // the document as last written to the recovery journal
+    pub journaled: Option<AnimationData>,
+    pub last_autosave: Instant,
+    // saved file vs journal of the selected draft, shown in the selector
+    pub recovery_diff: Option<Vec<String>>,
 }
 
 impl App {
-    pub fn new(remote_tx: tokio::sync::broadcast::Sender<String>) -> Self {
+    pub fn new(remote_tx: tokio::sync::broadcast::Sender<String>, config: Config) -> Self {
         let mut drafts = Vec::new();
-        if let Ok(entries) = std::fs::read_dir("drafts") {
-            for entry in entries.flatten() {
-                if let Ok(name) = entry.file_name().into_string() {
-                    if name.ends_with(".txt") {
-                        drafts.push(name);
-                    }
-                }
-            }
+        for root in &config.drafts.roots {
+            let Ok(entries) = std::fs::read_dir(root) else {
+                continue;
+            };
+            let mut found: Vec<String> = entries
+                .flatten()
+                .filter_map(|entry| entry.file_name().into_string().ok())
+                .filter(|name| {
+                    [".txt", ".json", ".ttml", ".lrc"]
+                        .iter()
+                        .any(|ext| name.ends_with(ext))
+                })
+                .map(|name| format!("{}/{}", root.trim_end_matches('/'), name))
+                .collect();
+            found.sort();
+            drafts.extend(found);
         }
-        drafts.sort();
 
         Self {
             data: AnimationData::new(),
@@ -48,51 +105,156 @@ impl App {
             edit_mode: EditMode::Time,
             scroll_offset: 0,
             manual_scroll: false,
+            clock: Clock::System,
             last_tick: Instant::now(),
             focus_line_index: None,
             active_kf_index: None,
             cursor_col: 0,
-            history: vec![],
-            history_index: 0,
+            selection: None,
+            tap: TapSync::default(),
+            words: WordSync::default(),
+            timeline: Timeline::default(),
+            key_releases: false,
+            drag: None,
+            history: History::new(&AnimationData::new()),
             remote_tx,
+            effects: Vec::new(),
+            files: BTreeMap::new(),
+            write_root: None,
             server_status: "Listening...".to_string(),
+            config,
             drafts,
             selected_draft: 0,
+            show_diagnostics: false,
+            prompt: None,
+            search: Search::default(),
+            review_lines: Vec::new(),
+            file_path: None,
+            saved: AnimationData::new(),
+            quit_armed: false,
+            should_quit: false,
+            recording: None,
+            last_macro: Vec::new(),
+            replay_depth: 0,
+            journaled: None,
+            last_autosave: Instant::now(),
+            recovery_diff: None,
         }
     }
 
-    pub fn push_history(&mut self) {
-        if self.history_index < self.history.len() {
-            self.history.truncate(self.history_index);
+    // called after every input, whatever it changed becomes one undo step
+    pub fn record_edit(&mut self) {
+        self.history.record(&self.data, self.clock.now());
+    }
+
+    // cursors left pointing past the document by deletes, undo or a reload
+    // are pulled back in, so every input starts from valid indexes
+    pub fn keep_in_bounds(&mut self) {
+        let len = self.data.lines.len();
+        self.scroll_offset = self.scroll_offset.min(len.saturating_sub(1));
+        self.focus_line_index = self.focus_line_index.filter(|&i| i < len);
+        if !matches!(self.view_mode, ViewMode::List | ViewMode::TextEdit) {
+            self.selection = None;
+        }
+        self.selection = self.selection.filter(|&i| i < len);
+        if self.view_mode == ViewMode::TextEdit && self.focus_line_index.is_none() {
+            if len == 0 {
+                self.data
+                    .add_line("", self.current_time, self.current_time + 2.0);
+            }
+            self.focus_line_index = Some(self.scroll_offset);
+        }
+        if let Some(idx) = self.focus_line_index {
+            self.cursor_col = self
+                .cursor_col
+                .min(self.data.lines[idx].text.chars().count());
+        }
+        let line = self
+            .focus_line_index
+            .or(self.get_active_line_index())
+            .and_then(|i| self.data.lines.get(i));
+        if let Some(kf) = self.active_kf_index {
+            let count = line.map_or(0, |l| l.keyframes.len());
+            self.active_kf_index = (kf < count).then_some(kf);
+        }
+        self.selected_draft = self.selected_draft.min(self.drafts.len().saturating_sub(1));
+        if self.view_mode == ViewMode::TapSync {
+            self.tap.keep_in_bounds(len);
+        } else {
+            self.tap = TapSync::default();
+        }
+        if self.view_mode == ViewMode::WordSync && !self.words.keep_in_bounds(len) {
+            self.view_mode = ViewMode::Line;
         }
-        self.history.push(self.data.clone());
-        self.history_index += 1;
     }
 
     pub fn undo(&mut self) {
-        if self.history_index > 0 {
-            self.history_index -= 1;
-            if let Some(state) = self.history.get(self.history_index) {
-                self.data = state.clone();
-            }
+        self.record_edit();
+        match self.history.undo(&mut self.data) {
+            Some(at) => self.after_history_step(at, "Undone"),
+            None => self.server_status = "Nothing to undo".to_string(),
         }
     }
 
-    pub fn load_draft(&mut self, filename: &str) {
-        let path = format!("drafts/{}", filename);
-        match std::fs::read_to_string(path) {
-            Ok(content) => match content.parse::<AnimationData>() {
-                Ok(data) => {
-                    self.data = data;
-                    self.view_mode = ViewMode::List;
-                    self.server_status = format!("Loaded {}", filename);
-                }
-                Err(e) => {
-                    self.server_status = format!("Failed to parse draft: {}", e);
-                }
-            },
+    pub fn redo(&mut self) {
+        self.record_edit();
+        match self.history.redo(&mut self.data) {
+            Some(at) => self.after_history_step(at, "Redone"),
+            None => self.server_status = "Nothing to redo".to_string(),
+        }
+    }
+
+    // keeps the selection inside the document and shows the changed line
+    fn after_history_step(&mut self, at: usize, status: &str) {
+        let last = self.data.lines.len().saturating_sub(1);
+        self.scroll_offset = at.min(last);
+        if self.focus_line_index.is_some() {
+            self.focus_line_index = Some(at.min(last));
+        }
+        self.active_kf_index = None;
+        let line_len = self
+            .data
+            .lines
+            .get(self.scroll_offset)
+            .map_or(0, |l| l.text.chars().count());
+        self.cursor_col = self.cursor_col.min(line_len);
+        self.server_status = status.to_string();
+    }
+
+    // the text `dispatch` read for `path`
+    pub fn read(&self, path: &str) -> Result<String, String> {
+        self.files
+            .get(path)
+            .cloned()
+            .unwrap_or_else(|| Err(format!("{}: not read", path)))
+    }
+
+    pub fn read_document(&self, path: &str) -> Result<AnimationData, String> {
+        let input = self.read(path)?;
+        convert::read(&input, convert::Format::from_path(path))
+            .map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn load_file(&mut self, path: &str) {
+        match self.read_document(path) {
+            Ok(data) => {
+                self.journaled = None;
+                self.saved = data.clone();
+                self.history = History::new(&data);
+                self.data = data;
+                self.file_path = Some(path.to_string());
+                self.view_mode = ViewMode::List;
+                self.server_status = if self.journal_read(Some(path)) {
+                    format!(
+                        "Loaded {}, unsaved changes found: `recover` to restore",
+                        path
+                    )
+                } else {
+                    format!("Loaded {}", path)
+                };
+            }
             Err(e) => {
-                self.server_status = format!("Failed to read draft: {}", e);
+                self.server_status = format!("Failed to load draft: {}", e);
             }
         }
     }
@@ -103,21 +265,16 @@ impl App {
 
     pub fn set_time(&mut self, time: f32) {
         self.current_time = time;
-        if self
-            .remote_tx
-            .send(format!(
-                r#"{{"command": "seek", "time": {}}}"#,
-                self.current_time
-            ))
-            .is_err()
-        {
-            self.server_status = "Sending failed".to_string();
-        }
+        self.send_remote(format!(
+            r#"{{"command": "seek", "time": {}}}"#,
+            self.current_time
+        ));
     }
 
     pub fn update(&mut self) {
-        let delta = self.last_tick.elapsed().as_secs_f32();
-        self.last_tick = Instant::now();
+        let now = self.clock.now();
+        let delta = now.duration_since(self.last_tick).as_secs_f32();
+        self.last_tick = now;
 
         if self.is_playing {
             self.update_time(self.current_time + delta);
@@ -126,23 +283,14 @@ impl App {
                 if let Some(idx) = self.focus_line_index {
                     let line = &self.data.lines[idx];
 
-                    if self.current_time > line.end {
-                        self.set_time(line.start);
-                    } else if self.current_time < line.start {
+                    if self.current_time > line.end || self.current_time < line.start {
                         self.set_time(line.start);
                     }
                 } else {
                     self.focus_line_index = self.get_active_line_index();
                 }
-            } else {
+            } else if !matches!(self.view_mode, ViewMode::TextEdit | ViewMode::WordSync) {
                 self.focus_line_index = None;
-
-                if let Some(last_line) = self.data.lines.last() {
-                    // if self.current_time > last_line.end {
-                    //     self.is_playing = false;
-                    //     let _ = self.remote_tx.send("pause".to_string());
-                    // }
-                }
             }
         }
 
@@ -162,6 +310,9 @@ impl App {
                 self.scroll_offset = closest;
             }
         }
+
+        self.autosave();
+        self.run_effects();
     }
 
     pub fn get_active_line_index(&self) -> Option<usize> {
@@ -171,188 +322,16 @@ impl App {
             .position(|l| self.current_time >= l.start && self.current_time <= l.end)
     }
 
-    pub fn handle_control_input(&mut self, key: KeyEvent) {
-        if key.kind == KeyEventKind::Release {
-            return;
-        }
-
-        // Global keys — apply in all non-DraftSelector modes
-        if self.view_mode != ViewMode::DraftSelector {
-            match key.code {
-                KeyCode::Char('s') => {
-                    let _ = self.data.compile();
-                }
-                KeyCode::Char(' ') => {
-                    self.is_playing = !self.is_playing;
-
-                    if let Some(last_line) = self.data.lines.last() {
-                        if self.current_time > last_line.end {
-                            self.set_time(0.0);
-                            self.is_playing = false;
-                            self.server_status = "EOF reached".to_string();
-                        }
-                    }
-
-                    let cmd = if self.is_playing { "play" } else { "pause" };
-                    let _ = self.remote_tx.send(cmd.to_string());
-                }
-                KeyCode::Char('e') => {
-                    self.view_mode = ViewMode::TextEdit;
-                    if self.focus_line_index.is_none() {
-                        self.focus_line_index = Some(self.scroll_offset);
-                    }
-                    if let Some(idx) = self.focus_line_index {
-                        self.cursor_col = self.data.lines[idx].text.chars().count();
-                    }
-                }
-                KeyCode::Char('j') => {
-                    self.manual_scroll = false;
-                    self.focus_line_index = self.get_active_line_index();
-                    self.active_kf_index = None;
-                    self.view_mode = ViewMode::Line;
-                }
-                KeyCode::Left => {
-                    self.active_kf_index = None;
-                    self.set_time((self.current_time - 0.5).max(0.0));
-                    if self.view_mode == ViewMode::Line {
-                        self.focus_line_index = self.get_active_line_index();
-                    }
-                }
-                KeyCode::Right => {
-                    self.active_kf_index = None;
-                    self.set_time(self.current_time + 0.5);
-                    if self.view_mode == ViewMode::Line {
-                        self.focus_line_index = self.get_active_line_index();
-                    }
-                }
-                KeyCode::Esc => self.toggle_view_mode(),
-                _ => {}
-            }
-        }
-
-        // Mode-specific keys
-        match self.view_mode {
-            ViewMode::DraftSelector => match key.code {
-                KeyCode::Up => {
-                    if self.selected_draft > 0 {
-                        self.selected_draft -= 1;
-                    }
-                }
-                KeyCode::Down => {
-                    if !self.drafts.is_empty() && self.selected_draft < self.drafts.len() - 1 {
-                        self.selected_draft += 1;
-                    }
-                }
-                KeyCode::Enter => {
-                    if !self.drafts.is_empty() {
-                        let filename = self.drafts[self.selected_draft].clone();
-                        self.load_draft(&filename);
-                    } else {
-                        self.view_mode = ViewMode::List;
-                    }
-                }
-                KeyCode::Char('n') => {
-                    self.data = AnimationData::new();
-                    self.view_mode = ViewMode::List;
-                }
-
-                KeyCode::Esc => self.toggle_view_mode(),
-                _ => {}
-            },
-
-            ViewMode::Line => match key.code {
-                KeyCode::Char('n') => {
-                    if let Some(curr) = self.focus_line_index {
-                        if curr + 1 < self.data.lines.len() {
-                            self.focus_line_index = Some(curr + 1);
-                            self.set_time(self.data.lines[curr + 1].start);
-                        }
-                    }
-                }
-                KeyCode::Char('p') => {
-                    if let Some(curr) = self.focus_line_index {
-                        if curr > 0 {
-                            self.focus_line_index = Some(curr - 1);
-                            self.set_time(self.data.lines[curr - 1].start);
-                        }
-                    }
-                }
-                _ => {
-                    self.handle_keyframe_editor_keys(key.code);
-                }
-            },
-
-            ViewMode::List => match key.code {
-                KeyCode::Up => {
-                    self.manual_scroll = true;
-                    if self.scroll_offset > 0 {
-                        self.scroll_offset -= 1;
-                    }
-                }
-                KeyCode::Down => {
-                    self.manual_scroll = true;
-                    if self.scroll_offset < self.data.lines.len() - 1 {
-                        self.scroll_offset += 1;
-                    }
-                }
-                KeyCode::PageUp => self.seek_list(-1),
-                KeyCode::PageDown => self.seek_list(1),
-                KeyCode::End => {
-                    // set end time stamp of previous line to current_time
-                    if let Some(idx) = self.get_active_line_index() {
-                        self.push_history();
-                        let duration = self.data.lines[idx].start - self.current_time;
-                        self.data.lines[idx].end = self.current_time + duration;
-                    }
-                }
-                KeyCode::Home => {
-                    if self.scroll_offset < self.data.lines.len() {
-                        self.push_history();
-                        let current_time = self.current_time;
-                        {
-                            let idx = self.scroll_offset;
-
-                            let (before, current_and_after) = self.data.lines.split_at_mut(idx);
-                            let line = &mut current_and_after[0];
-
-                            let duration = line.end - line.start;
-                            line.start = current_time;
-                            line.end = current_time + duration;
-
-                            if idx > 0 {
-                                let prev_line = &mut before[idx - 1];
-                                if prev_line.keyframes.len() <= 2 {
-                                    prev_line.end = line.start - 0.01;
-                                }
-                            }
-                        }
-
-                        if (self.scroll_offset < self.data.lines.len() - 1) {
-                            if (self.data.lines[self.scroll_offset + 1]
-                                .text
-                                .trim()
-                                .is_empty())
-                            {
-                                if (self.scroll_offset < self.data.lines.len() - 2) {
-                                    self.scroll_offset += 2;
-                                } else {
-                                    self.scroll_offset += 1;
-                                }
-                            } else {
-                                self.scroll_offset += 1;
-                            }
-                        } else {
-                            self.scroll_offset = 0;
-                        }
-
-                        self.set_time(current_time);
-                    }
-                }
-                _ => {}
-            },
-
-            _ => {}
-        }
+    pub fn diagnostics_summary(diagnostics: &[Diagnostic]) -> String {
+        let errors = diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+            .count();
+        format!(
+            "Lint: {} errors, {} warnings",
+            errors,
+            diagnostics.len() - errors
+        )
     }
 
     pub fn toggle_view_mode(&mut self) {
@@ -362,17 +341,31 @@ impl App {
                 ViewMode::List
             }
             ViewMode::List => {
+                // back to following the playhead
                 self.manual_scroll = false;
+                self.selection = None;
+                self.search = Search::default();
                 self.focus_line_index = self.get_active_line_index();
                 self.active_kf_index = None;
-                // ViewMode::Line
-                ViewMode::DraftSelector
+                ViewMode::List
             }
             ViewMode::TextEdit => {
                 self.focus_line_index = None;
                 ViewMode::List
             }
             ViewMode::DraftSelector => ViewMode::List,
+            ViewMode::TapSync => {
+                self.stop_tap_sync();
+                ViewMode::List
+            }
+            ViewMode::WordSync => {
+                self.stop_word_sync();
+                ViewMode::Line
+            }
+            ViewMode::Timeline => {
+                self.manual_scroll = false;
+                ViewMode::List
+            }
         };
     }
 
@@ -397,9 +390,8 @@ impl App {
             .min_by(|(_, a), (_, b)| {
                 (a.time - rel_time)
                     .abs()
-                    .partial_cmp(&(b.time - rel_time).abs())
-                    .unwrap()
+                    .total_cmp(&(b.time - rel_time).abs())
             })
             .map(|(i, _)| i)
     }
-}
\ No newline at end of file
+}
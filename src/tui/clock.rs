@@ -0,0 +1,36 @@
+use std::time::Instant;
+
+// the time `App` plays back and autosaves on. tests swap in a clock that only
+// moves when told to
+#[derive(Clone, Copy)]
+pub enum Clock {
+    System,
+    #[cfg(test)]
+    Manual(Instant),
+}
+
+impl Clock {
+    pub fn now(&self) -> Instant {
+        match self {
+            Clock::System => Instant::now(),
+            #[cfg(test)]
+            Clock::Manual(now) => *now,
+        }
+    }
+
+    #[cfg(test)]
+    pub fn advance(&mut self, by: std::time::Duration) {
+        if let Clock::Manual(now) = self {
+            *now += by;
+        }
+    }
+}
+
+#[cfg(test)]
+impl crate::tui::app::App {
+    pub fn set_clock(&mut self, clock: Clock) {
+        self.clock = clock;
+        self.last_tick = clock.now();
+        self.last_autosave = clock.now();
+    }
+}
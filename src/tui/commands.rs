@@ -0,0 +1,257 @@
+use crate::convert::{self, Format};
+use crate::model::{TimeNotation, WriteOptions};
+use crate::tui::action::Action;
+use crate::tui::app::App;
+use crate::tui::effect::Effect;
+use crate::tui::macros;
+use std::ops::Range;
+
+impl App {
+    pub fn open_prompt(&mut self) {
+        self.prompt = Some(String::new());
+    }
+
+    pub fn prompt_action(&mut self, action: Action) {
+        let Some(input) = self.prompt.as_mut() else {
+            return;
+        };
+
+        match action {
+            Action::PromptCancel => {
+                self.prompt = None;
+                self.cancel_search();
+            }
+            Action::PromptBackspace => {
+                input.pop();
+                self.prompt_changed();
+            }
+            Action::PromptInput(c) => {
+                input.push(c);
+                self.prompt_changed();
+            }
+            Action::PromptSubmit => {
+                let command = self.prompt.take().unwrap_or_default();
+                self.server_status = match self.run_command(&command) {
+                    Ok(msg) => msg,
+                    Err(e) => format!("Error: {}", e),
+                };
+            }
+            _ => {}
+        }
+    }
+
+    // marked lines, or a manually selected line meaning "from here on",
+    // otherwise the whole song
+    pub fn selection_range(&self) -> Range<usize> {
+        if let Some(range) = self.marked_lines() {
+            range
+        } else if self.manual_scroll {
+            self.scroll_offset..self.data.lines.len()
+        } else {
+            0..self.data.lines.len()
+        }
+    }
+
+    pub fn run_command(&mut self, command: &str) -> Result<String, String> {
+        // searches and replaces keep their spaces
+        if let Some(query) = command.strip_prefix('/') {
+            return self.finish_search(query);
+        }
+        if command.starts_with("s/") {
+            return self.replace(command);
+        }
+        let mut args = command.split_whitespace();
+        let Some(name) = args.next() else {
+            return Ok(String::new());
+        };
+        let args: Vec<&str> = args.collect();
+        let range = self.selection_range();
+        let count = range.len();
+        let lines = self.target_lines();
+
+        let parse_secs = |s: &str| {
+            s.parse::<f32>()
+                .map_err(|_| format!("'{}' is not a number", s))
+        };
+
+        match name {
+            "shift" => {
+                let offset = parse_secs(args.first().ok_or("usage: shift <seconds>")?)?;
+                self.data.shift_times(range, offset);
+                Ok(format!("Shifted {} lines by {:+.3}s", count, offset))
+            }
+            "scale" => {
+                let factor = parse_secs(args.first().ok_or("usage: scale <factor> [pivot]")?)?;
+                let pivot = match args.get(1) {
+                    Some(p) => parse_secs(p)?,
+                    None => self.data.lines.get(range.start).map_or(0.0, |l| l.start),
+                };
+                let mut scaled = self.data.clone();
+                scaled.scale_times(range, pivot, factor)?;
+                self.data = scaled;
+                Ok(format!(
+                    "Scaled {} lines by {} around {:.3}s",
+                    count, factor, pivot
+                ))
+            }
+            "remap" => {
+                let anchors = args
+                    .iter()
+                    .map(|pair| {
+                        let (old, new) = pair
+                            .split_once('=')
+                            .ok_or(format!("'{}' should look like old=new", pair))?;
+                        Ok((parse_secs(old)?, parse_secs(new)?))
+                    })
+                    .collect::<Result<Vec<(f32, f32)>, String>>()?;
+                if anchors.is_empty() {
+                    return Err("usage: remap <old>=<new> ...".to_string());
+                }
+                let mut remapped = self.data.clone();
+                remapped.remap_times(range, &anchors)?;
+                self.data = remapped;
+                Ok(format!(
+                    "Remapped {} lines through {} anchors",
+                    count,
+                    anchors.len()
+                ))
+            }
+            "delete" => self.delete_lines(lines),
+            "part" => {
+                let name = args.join(" ");
+                self.data.set_part(lines.clone(), &name);
+                if name.is_empty() {
+                    Ok(format!("Took {} lines out of their parts", lines.len()))
+                } else {
+                    Ok(format!("Made {} lines [{}]", lines.len(), name))
+                }
+            }
+            "agent" => {
+                let agent = args.first().map(|a| a.to_string());
+                for line in &mut self.data.lines[lines.clone()] {
+                    line.agent = agent.clone();
+                }
+                for idx in lines.clone() {
+                    self.data.sync_repeats_from(idx);
+                }
+                Ok(format!(
+                    "Set the agent of {} lines to {}",
+                    lines.len(),
+                    agent.as_deref().unwrap_or("none")
+                ))
+            }
+            "clear-keyframes" => {
+                for line in &mut self.data.lines[lines.clone()] {
+                    line.keyframes.clear();
+                }
+                for idx in lines.clone() {
+                    self.data.sync_repeats_from(idx);
+                }
+                Ok(format!("Cleared the keyframes of {} lines", lines.len()))
+            }
+            "distribute" => {
+                let usage = "usage: distribute <from> <to>";
+                let from = parse_secs(args.first().ok_or(usage)?)?;
+                let to = parse_secs(args.get(1).ok_or(usage)?)?;
+                let mut distributed = self.data.clone();
+                distributed.distribute(lines.clone(), from, to)?;
+                self.data = distributed;
+                Ok(format!(
+                    "Distributed {} lines over {:.3}s - {:.3}s",
+                    lines.len(),
+                    from,
+                    to
+                ))
+            }
+            "goto" => self.goto(args.first().ok_or("usage: goto <time> | goto #<line>")?),
+            "retime" => {
+                let path = args.first().ok_or("usage: retime <lyrics.txt>")?;
+                let text = self
+                    .read(path)
+                    .map_err(|e| format!("Could not read {}", e))?;
+                let result = self.data.realign(&text);
+                self.data = result.data;
+                self.review_lines = result.review;
+                let mut msg = format!(
+                    "Retimed to {} lines, {} need review",
+                    self.data.lines.len(),
+                    self.review_lines.len()
+                );
+                if !result.dropped.is_empty() {
+                    msg.push_str(&format!(", dropped {}", result.dropped.join(", ")));
+                }
+                Ok(msg)
+            }
+            "w" | "save" => match args.first() {
+                Some(path) => self.save_as(path),
+                None => match self.file_path.clone() {
+                    Some(path) => self.save_as(&path),
+                    None => Err("usage: save <file>".to_string()),
+                },
+            },
+            "save-as" => {
+                let path = args.first().ok_or("usage: save-as <file>")?;
+                if path.ends_with('/') {
+                    return Err("save-as needs a file name".to_string());
+                }
+                self.save_as(path)
+            }
+            "recover" => {
+                let path = self.file_path.clone();
+                self.restore_journal(path.as_deref())
+            }
+            "import" => {
+                let path = args.first().ok_or("usage: import <file>")?;
+                let data = self.read_document(path)?;
+                self.data = data;
+                Ok(format!("Imported {}", path))
+            }
+            "export" => {
+                let path = args
+                    .iter()
+                    .find(|a| !a.starts_with("--"))
+                    .ok_or("usage: export <file> [--compact] [--clock]")?;
+                let options = WriteOptions {
+                    compact: args.contains(&"--compact"),
+                    notation: if args.contains(&"--clock") {
+                        TimeNotation::Clock
+                    } else {
+                        TimeNotation::Seconds
+                    },
+                };
+                let content = convert::write(&self.data, Format::from_path(path), &options)?;
+                self.effects.push(Effect::Write {
+                    path: path.to_string(),
+                    content,
+                    done: format!("Exported {}", path),
+                });
+                Ok(format!("Exporting {}", path))
+            }
+            "play" => {
+                let path = args.first().ok_or("usage: play <actions.jsonl>")?;
+                let count = self.replay(self.read_actions(path)?)?;
+                Ok(format!("Played {} actions from {}", count, path))
+            }
+            "macro" => match args[..] {
+                ["save", path] => {
+                    self.effects.push(Effect::Write {
+                        path: path.to_string(),
+                        content: macros::format_actions(&self.last_macro)?,
+                        done: format!("Saved {} actions to {}", self.last_macro.len(), path),
+                    });
+                    Ok(format!(
+                        "Saving {} actions to {}",
+                        self.last_macro.len(),
+                        path
+                    ))
+                }
+                ["load", path] => {
+                    self.last_macro = self.read_actions(path)?;
+                    Ok(format!("Loaded {} actions", self.last_macro.len()))
+                }
+                _ => Err("usage: macro save|load <file>".to_string()),
+            },
+            _ => Err(format!("Unknown command '{}'", name)),
+        }
+    }
+}
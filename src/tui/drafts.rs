@@ -1,24 +1,30 @@
+use crate::tui::action::Action;
 use crate::tui::app::App;
 use crate::tui::edit_line::UI;
+use crate::tui::types::ViewMode;
 use ratatui::Frame;
 use ratatui::layout::Rect;
-use ratatui::style::Color;
 use ratatui::style::{Modifier, Style};
 use ratatui::text::{Line as TuiLine, Span};
 use ratatui::widgets::{Block, Borders, Paragraph};
 
 impl UI {
     pub fn render_draft_selector(f: &mut Frame, app: &App, area: Rect) {
+        let theme = &app.config.theme;
+        let key = |action| app.config.keys.hint(ViewMode::DraftSelector, action);
         let mut tui_lines = Vec::new();
 
         if app.drafts.is_empty() {
             tui_lines.push(TuiLine::from(vec![Span::styled(
-                " No drafts found in drafts/ folder ",
-                Style::default().fg(Color::Gray),
+                format!(
+                    " No drafts found in {} ",
+                    app.config.drafts.roots.join(", ")
+                ),
+                Style::default().fg(theme.dim),
             )]));
             tui_lines.push(TuiLine::from(vec![Span::styled(
-                " Press [N] to start a new project ",
-                Style::default().fg(Color::Yellow),
+                format!(" Press [{}] to start a new project ", key(Action::NewDraft)),
+                Style::default().fg(theme.highlight),
             )]));
         } else {
             for (i, draft) in app.drafts.iter().enumerate() {
@@ -26,16 +32,53 @@ impl UI {
                 let prefix = if is_selected { " -> " } else { "    " };
                 let style = if is_selected {
                     Style::default()
-                        .fg(Color::Blue)
+                        .fg(theme.accent)
                         .add_modifier(Modifier::BOLD)
                 } else {
-                    Style::default().fg(Color::White)
+                    Style::default().fg(theme.text)
                 };
 
-                tui_lines.push(TuiLine::from(vec![
-                    Span::styled(prefix, Style::default().fg(Color::Blue)),
+                let mut spans = vec![
+                    Span::styled(prefix, Style::default().fg(theme.accent)),
                     Span::styled(draft, style),
-                ]));
+                ];
+                if app.has_journal(Some(draft)) {
+                    spans.push(Span::styled(
+                        if is_selected {
+                            format!(
+                                "  unsaved changes: [{}] restore [{}] diff [{}] discard",
+                                key(Action::RestoreJournal),
+                                key(Action::DiffJournal),
+                                key(Action::DiscardJournal)
+                            )
+                        } else {
+                            "  unsaved changes".to_string()
+                        },
+                        Style::default().fg(theme.highlight),
+                    ));
+                }
+                tui_lines.push(TuiLine::from(spans));
+            }
+        }
+
+        if app.has_journal(None) {
+            tui_lines.push(TuiLine::from(""));
+            tui_lines.push(TuiLine::from(vec![Span::styled(
+                format!(
+                    " A new draft has unsaved changes, press [{}] to restore it ",
+                    key(Action::RestoreUntitled)
+                ),
+                Style::default().fg(theme.highlight),
+            )]));
+        }
+
+        if let Some(diff) = &app.recovery_diff {
+            tui_lines.push(TuiLine::from(""));
+            for change in diff {
+                tui_lines.push(TuiLine::from(vec![Span::styled(
+                    format!("    {}", change),
+                    Style::default().fg(theme.info),
+                )]));
             }
         }
 
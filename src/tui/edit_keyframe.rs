@@ -1,8 +1,8 @@
 use crate::model::{Keyframe, TextSegment};
+use crate::tui::action::Action;
 use crate::tui::app::App;
 use crate::tui::edit_line::UI;
-use crate::tui::types::EditMode;
-use crossterm::event::KeyCode;
+use crate::tui::types::{EditMode, ViewMode};
 use ratatui::Frame;
 use ratatui::layout::{Alignment, Rect};
 use ratatui::style::{Color, Modifier, Style};
@@ -10,21 +10,20 @@ use ratatui::text::{Line as TuiLine, Span};
 use ratatui::widgets::{Block, Borders, Paragraph};
 
 impl App {
-    pub fn handle_keyframe_editor_keys(&mut self, code: KeyCode) {
+    pub fn handle_keyframe_action(&mut self, action: Action) {
         let Some(idx) = self.focus_line_index.or(self.get_active_line_index()) else {
             return;
         };
         let rel_time = self.current_time - self.data.lines[idx].start;
-        let line_len = self.data.lines[idx].text.len() as f32;
 
-        match code {
-            KeyCode::Char('t') => {
+        match action {
+            Action::ToggleEditMode => {
                 self.edit_mode = match self.edit_mode {
                     EditMode::Progress => EditMode::Time,
                     EditMode::Time => EditMode::Progress,
                 }
             }
-            KeyCode::Char('f') => {
+            Action::AddKeyframe => {
                 let target_idx = self.data.lines[idx].get_current_index(rel_time);
 
                 if self.data.lines[idx].keyframes.is_empty() {
@@ -59,31 +58,49 @@ impl App {
 
                 self.data.lines[idx].sort_keyframes();
             }
-            KeyCode::Char('g') | KeyCode::Delete => {
-                if self.data.lines[idx].keyframes.len() > 1 {
-                    if let Some(ki) = self.find_closest_kf_idx(idx, rel_time) {
-                        self.data.lines[idx].keyframes.remove(ki);
-                    }
+            Action::DeleteKeyframe => {
+                if self.data.lines[idx].keyframes.len() > 1
+                    && let Some(ki) = self.find_closest_kf_idx(idx, rel_time)
+                {
+                    self.data.lines[idx].keyframes.remove(ki);
                 }
             }
-            KeyCode::Up | KeyCode::Down => {
+            Action::NudgeUp | Action::NudgeDown => {
                 let Some(ki) = self.active_kf_index else {
                     return;
                 };
 
-                let mult = if code == KeyCode::Up { 1.0 } else { -1.0 };
+                let mult = if action == Action::NudgeUp { 1.0 } else { -1.0 };
 
                 match self.edit_mode {
                     EditMode::Progress => {
-                        self.data.edit_keyframe_position(idx, ki, 0.5 * mult);
+                        self.data.edit_keyframe_position(
+                            idx,
+                            ki,
+                            self.config.steps.keyframe_position * mult,
+                        );
                     }
                     EditMode::Time => {
-                        self.data.edit_keyframe_time(idx, ki, 0.5 * mult);
+                        self.data.edit_keyframe_time(
+                            idx,
+                            ki,
+                            self.config.steps.keyframe_time * mult,
+                        );
                     }
                 }
             }
 
-            KeyCode::Char('k') => {
+            Action::SelectKeyframe(ki) => {
+                let line = &self.data.lines[idx];
+                if let Some(kf) = line.keyframes.get(ki) {
+                    self.focus_line_index = Some(idx);
+                    self.active_kf_index = Some(ki);
+                    self.set_time(line.start + kf.time);
+                    self.is_playing = false;
+                }
+            }
+
+            Action::NextKeyframe => {
                 let line = &self.data.lines[idx];
                 let rel_time = self.current_time - line.start;
 
@@ -109,7 +126,7 @@ impl App {
                 self.is_playing = false;
             }
 
-            KeyCode::Char('j') => {
+            Action::PrevKeyframe => {
                 let line = &self.data.lines[idx];
                 let rel_time = self.current_time - line.start;
 
@@ -144,19 +161,22 @@ impl App {
             }
             _ => {}
         }
+
+        self.data.sync_repeats_from(idx);
     }
 }
 
 impl UI {
-    pub fn render_keyframe_editor_panel(f: &mut Frame, app: &App, idx: usize, area: Rect) {
+    // one " [KFn: time|progress] " per keyframe, as laid out in the editor panel
+    pub fn keyframe_chips(app: &App, idx: usize) -> Vec<Vec<Span<'static>>> {
         let line = &app.data.lines[idx];
         let rel_time = app.current_time - line.start;
+        let theme = &app.config.theme;
 
-        let kfs = line
-            .keyframes
+        line.keyframes
             .iter()
             .enumerate()
-            .flat_map(|(ki, k)| {
+            .map(|(ki, k)| {
                 let is_near = if let Some(aki) = app.active_kf_index {
                     ki == aki
                 } else {
@@ -164,19 +184,19 @@ impl UI {
                 };
 
                 let base_fg = if is_near {
-                    Color::Yellow
+                    theme.highlight
                 } else {
-                    Color::DarkGray
+                    theme.muted
                 };
 
                 let time_style = if app.edit_mode == EditMode::Time && is_near {
-                    Style::default().bg(Color::Blue).fg(Color::White)
+                    Style::default().bg(theme.accent).fg(theme.accent_text)
                 } else {
                     Style::default().fg(base_fg)
                 };
 
                 let progress_style = if app.edit_mode == EditMode::Progress && is_near {
-                    Style::default().bg(Color::Blue).fg(Color::White)
+                    Style::default().bg(theme.accent).fg(theme.accent_text)
                 } else {
                     Style::default().fg(base_fg)
                 };
@@ -192,7 +212,11 @@ impl UI {
                     Span::styled("] ", Style::default().fg(base_fg)),
                 ]
             })
-            .collect::<Vec<Span>>();
+            .collect()
+    }
+
+    pub fn render_keyframe_editor_panel(f: &mut Frame, app: &App, idx: usize, area: Rect) {
+        let kfs: Vec<Span> = Self::keyframe_chips(app, idx).concat();
 
         let toggle_edit = if app.edit_mode == EditMode::Time {
             "Edit Position"
@@ -200,11 +224,19 @@ impl UI {
             "Edit Time"
         };
 
+        let key = |action| app.config.keys.hint(ViewMode::Line, action);
         let ui_info = vec![
             TuiLine::from(kfs),
             TuiLine::from(format!(
-                " [T] {} | [F] Add | [G/Del] Delete | [J/K] Jump | [UP/DOWN] Adjust Value",
-                toggle_edit
+                " [{}] {} | [{}] Add | [{}] Delete | [{}/{}] Jump | [{}/{}] Adjust Value",
+                key(Action::ToggleEditMode),
+                toggle_edit,
+                key(Action::AddKeyframe),
+                key(Action::DeleteKeyframe),
+                key(Action::PrevKeyframe),
+                key(Action::NextKeyframe),
+                key(Action::NudgeUp),
+                key(Action::NudgeDown),
             )),
         ];
 
@@ -256,12 +288,65 @@ impl UI {
             .collect()
     }
 
+    // ruby readings padded to the width of the base line so both center the same way
+    pub fn gen_ruby_line_spans<'a>(line: &'a TextSegment, current_time: f32) -> Vec<Span<'a>> {
+        let rel_time = current_time - line.start;
+        let base_idx = line.get_current_char_index(rel_time);
+        let widths: Vec<usize> = line
+            .text
+            .chars()
+            .map(|c| Span::raw(c.to_string()).width())
+            .collect();
+
+        let mut spans = Vec::new();
+        let mut col = 0;
+        for ruby in &line.ruby {
+            let base_col: usize = widths.iter().take(ruby.start).sum();
+            let base_width: usize = widths
+                .iter()
+                .skip(ruby.start)
+                .take(ruby.end.saturating_sub(ruby.start))
+                .sum();
+            let reading_width = Span::raw(ruby.text.as_str()).width();
+            let target = (base_col + base_width / 2)
+                .saturating_sub(reading_width / 2)
+                .max(col);
+            spans.push(Span::raw(" ".repeat(target - col)));
+
+            let reading_idx = ruby.get_current_index(rel_time, base_idx);
+            spans.extend(ruby.text.chars().enumerate().map(|(i, c)| {
+                let color = if (i as f32) < reading_idx {
+                    Color::Rgb(255, 255, 255)
+                } else {
+                    Color::Rgb(60, 60, 100)
+                };
+                Span::styled(c.to_string(), Style::default().fg(color))
+            }));
+            col = target + reading_width;
+        }
+
+        let total: usize = widths.iter().sum();
+        if col < total {
+            spans.push(Span::raw(" ".repeat(total - col)));
+        }
+        spans
+    }
+
     pub fn render_active_line_anim(f: &mut Frame, app: &App, idx: usize, area: Rect) {
         let line = &app.data.lines[idx];
         let spans = Self::gen_animated_line_spans(line, app.current_time, true);
 
+        let mut tui_lines = Vec::new();
+        if !line.ruby.is_empty() {
+            tui_lines.push(TuiLine::from(Self::gen_ruby_line_spans(
+                line,
+                app.current_time,
+            )));
+        }
+        tui_lines.push(TuiLine::from(spans));
+
         f.render_widget(
-            Paragraph::new(TuiLine::from(spans))
+            Paragraph::new(tui_lines)
                 .alignment(Alignment::Center)
                 .block(Block::default().borders(Borders::NONE)),
             area,
@@ -1,21 +1,30 @@
+use std::rc::Rc;
+
 use ratatui::{
     Frame,
     layout::{Alignment, Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style},
+    style::{Modifier, Style},
     text::{Line as TuiLine, Span},
     widgets::{Block, Borders, Paragraph},
 };
 
+use crate::model::{Diagnostic, Severity};
+use crate::tui::action::Action;
 use crate::tui::app::App;
 use crate::tui::types::ViewMode;
 pub struct UI;
 
 impl UI {
-    pub fn draw(f: &mut Frame, app: &App) {
-        let chunks = Layout::default()
+    // the header, and the view below it
+    pub fn chunks(area: Rect) -> Rc<[Rect]> {
+        Layout::default()
             .direction(Direction::Vertical)
-            .constraints([Constraint::Length(3), Constraint::Min(0)])
-            .split(f.area());
+            .constraints([Constraint::Length(4), Constraint::Min(0)])
+            .split(area)
+    }
+
+    pub fn draw(f: &mut Frame, app: &App) {
+        let chunks = Self::chunks(f.area());
 
         Self::render_header(f, app, chunks[0]);
 
@@ -23,46 +32,188 @@ impl UI {
             ViewMode::Line => Self::render_focus_mode(f, app, chunks[1]),
             ViewMode::List => Self::render_list_mode(f, app, chunks[1]),
             ViewMode::TextEdit => Self::render_list_mode(f, app, chunks[1]),
+            ViewMode::TapSync => Self::render_list_mode(f, app, chunks[1]),
+            ViewMode::WordSync => Self::render_focus_mode(f, app, chunks[1]),
+            ViewMode::Timeline => Self::render_timeline(f, app, chunks[1]),
             ViewMode::DraftSelector => Self::render_draft_selector(f, app, chunks[1]),
         }
     }
 
     fn render_header(f: &mut Frame, app: &App, area: Rect) {
+        let theme = &app.config.theme;
+        let key = |action| app.config.keys.hint(app.view_mode, action);
         let mode_str = match app.view_mode {
-            ViewMode::List => {
-                "LINE MODE [ESC] | TEXT EDIT [E] | KEYFRAME EDIT [J]  | [Q] Quit | [SPACE] Play"
-            }
-            ViewMode::Line => "LIST MODE [ESC] | [Q] Quit | [SPACE] Play",
-            ViewMode::TextEdit => "DONE [ESC] | [SHIFT+INSERT] Paste",
-            ViewMode::DraftSelector => "SELECT DRAFT [UP/DOWN] | LOAD [ENTER] | NEW [N]",
+            ViewMode::List => format!(
+                "LINE MODE [{}] | TEXT EDIT [{}] | KEYFRAME EDIT [{}] | TAP SYNC [{}] | TIMELINE [{}] | REPEAT PART [{}] | LINT [{}] | SAVE [{}] | UNDO [{}] REDO [{}] | [{}] Quit | [{}] Play",
+                key(Action::ToggleView),
+                key(Action::EditText),
+                key(Action::FocusMode),
+                key(Action::TapSync),
+                key(Action::Timeline),
+                key(Action::RepeatPart),
+                key(Action::ToggleDiagnostics),
+                key(Action::Save),
+                key(Action::Undo),
+                key(Action::Redo),
+                key(Action::Quit),
+                key(Action::PlayPause),
+            ),
+            ViewMode::Line => format!(
+                "LIST MODE [{}] | WORD SYNC [{}] | UNDO [{}] REDO [{}] | [{}] Quit | [{}] Play",
+                key(Action::ToggleView),
+                key(Action::WordSync),
+                key(Action::Undo),
+                key(Action::Redo),
+                key(Action::Quit),
+                key(Action::PlayPause),
+            ),
+            ViewMode::TextEdit => format!(
+                "DONE [ESC] | [SHIFT+INSERT] Paste | UNDO [CTRL+Z] REDO [CTRL+Y] | MARK [{}/{}]",
+                key(Action::SelectUp),
+                key(Action::SelectDown)
+            ),
+            ViewMode::TapSync => format!(
+                "TAP [{}] | TAKE BACK [{}] | DONE [{}] | [{}] Play",
+                key(Action::Tap),
+                key(Action::UndoTap),
+                key(Action::ToggleView),
+                key(Action::PlayPause),
+            ),
+            ViewMode::WordSync => format!(
+                "TAP [{}] | TAKE BACK [{}] | WORDS/SYLLABLES [{}] | DONE [{}] | [{}] Play",
+                key(Action::Tap),
+                key(Action::UndoTap),
+                key(Action::ToggleSplit),
+                key(Action::ToggleView),
+                key(Action::PlayPause),
+            ),
+            ViewMode::Timeline => format!(
+                "LIST MODE [{}] | ZOOM [{}/{}] | PAN [{}/{}] | UNDO [{}] | [{}] Play",
+                key(Action::ToggleView),
+                key(Action::ZoomIn),
+                key(Action::ZoomOut),
+                key(Action::PanLeft),
+                key(Action::PanRight),
+                key(Action::Undo),
+                key(Action::PlayPause),
+            ),
+            ViewMode::DraftSelector => format!(
+                "SELECT DRAFT [{}/{}] | LOAD [{}] | NEW [{}]",
+                key(Action::PrevDraft),
+                key(Action::NextDraft),
+                key(Action::OpenDraft),
+                key(Action::NewDraft),
+            ),
         };
 
         let status_color = if app.view_mode == ViewMode::List || app.view_mode == ViewMode::TextEdit
         {
-            Color::Blue
+            theme.accent
         } else if app.is_playing {
-            Color::Green
+            theme.playing
         } else {
-            Color::Yellow
+            theme.highlight
         };
         let rel_time = app
             .focus_line_index
             .or(app.get_active_line_index())
             .map(|idx| {
                 let line = &app.data.lines[idx];
-                (app.current_time - line.start).clamp(0.0, line.end - line.start)
+                // `clamp` panics on lines that end before they start
+                (app.current_time - line.start)
+                    .min(line.end - line.start)
+                    .max(0.0)
             })
             .unwrap_or(0.0);
 
+        let file = app.file_path.as_deref().unwrap_or("[new draft]");
+        let dirty = if app.is_dirty() { " [+]" } else { "" };
         let info = format!(
-            " {} | Time: {:7.2}s |  Relative: {:7.2}s ",
-            mode_str, app.current_time, rel_time
+            " {}{} | {} | Time: {:7.2}s |  Relative: {:7.2}s ",
+            file, dirty, mode_str, app.current_time, rel_time
         );
 
-        let sub_info = if app.view_mode == ViewMode::List && app.manual_scroll {
-            " MANUAL SCROLLING (Press ESC to Auto)".to_string()
+        let sub_info = if let Some(input) = &app.prompt {
+            // a search shows as its `/`
+            let colon = if input.starts_with('/') { "" } else { ":" };
+            match app.prompt_preview() {
+                Some(preview) => format!(" {}{}_ | {}", colon, input, preview),
+                None => format!(" {}{}_", colon, input),
+            }
+        } else if let Some(marked) = app.marked_lines()
+            && app.view_mode == ViewMode::TextEdit
+        {
+            format!(
+                " {} LINES MARKED | COPY [{}]",
+                marked.len(),
+                key(Action::CopyLines)
+            )
+        } else if let Some(marked) = app.marked_lines() {
+            format!(
+                " {} LINES MARKED | [{}] delete | part <name> | agent [id] | clear-keyframes | distribute <from> <to> | shift/scale/remap | DELETE [{}] COPY [{}] PASTE [{}]",
+                marked.len(),
+                key(Action::Prompt),
+                key(Action::DeleteLines),
+                key(Action::CopyLines),
+                key(Action::PasteLines)
+            )
+        } else if app.view_mode == ViewMode::List && app.manual_scroll {
+            format!(
+                " MANUAL SCROLLING (Press {} to Auto) | [{}] shift/scale/remap from here on | MARK [{}/{}] | SEARCH [{}] NEXT [{}/{}] | GO TO [{}]",
+                key(Action::ToggleView),
+                key(Action::Prompt),
+                key(Action::SelectUp),
+                key(Action::SelectDown),
+                key(Action::Search),
+                key(Action::NextMatch),
+                key(Action::PrevMatch),
+                key(Action::GoTo)
+            )
+        } else if app.view_mode == ViewMode::List {
+            format!(
+                " [{}] shift <s> | scale <factor> [pivot] | remap <old>=<new> ... | s/<regex>/<with>/ | goto <time>|#<line> | retime <file> | import/export <file> | recover | play <file> | MACRO [{}] PLAY [{}]",
+                key(Action::Prompt),
+                key(Action::RecordMacro),
+                key(Action::PlayMacro)
+            )
+        } else if app.view_mode == ViewMode::TapSync {
+            let hold = if app.key_releases {
+                "hold the key through a line to stamp its end on release"
+            } else {
+                "each tap also ends the line before"
+            };
+            format!(
+                " Next: line {} of {} | {}",
+                (app.tap.next + 1).min(app.data.lines.len()),
+                app.data.lines.len(),
+                hold
+            )
+        } else if app.view_mode == ViewMode::Timeline {
+            let follow = if app.manual_scroll {
+                format!("PINNED (Press {} to follow)", key(Action::FollowPlayhead))
+            } else {
+                "following the playhead".to_string()
+            };
+            format!(
+                " SELECT [{}/{}] | MOVE [{}/{}] | START [{}/{}] | END [{}/{}] | one column a step, edges snap to the lines around | {}",
+                key(Action::ScrollUp),
+                key(Action::ScrollDown),
+                key(Action::MoveEarlier),
+                key(Action::MoveLater),
+                key(Action::StartEarlier),
+                key(Action::StartLater),
+                key(Action::EndEarlier),
+                key(Action::EndLater),
+                follow
+            )
+        } else if app.view_mode == ViewMode::WordSync {
+            format!(" Line {}: {}", app.words.line + 1, Self::word_units(app))
         } else if app.view_mode == ViewMode::Line {
-            " [N] Next Line | [P] Prev Line".to_string()
+            format!(
+                " [{}] Next Line | [{}] Prev Line",
+                key(Action::NextLine),
+                key(Action::PrevLine)
+            )
         } else {
             " ".to_string()
         };
@@ -76,11 +227,41 @@ impl UI {
         f.render_widget(p, area);
     }
 
-    fn render_focus_mode(f: &mut Frame, app: &App, area: Rect) {
-        let chunks = Layout::default()
+    // "Twin·kle·[lit]·tle·star" with the unit tapped next in brackets
+    fn word_units(app: &App) -> String {
+        let Some(line) = app.data.lines.get(app.words.line) else {
+            return String::new();
+        };
+        let chars: Vec<char> = line.text.chars().collect();
+        let points = &app.words.points;
+        let units: Vec<String> = points
+            .iter()
+            .enumerate()
+            .map(|(i, &p)| {
+                let end = points
+                    .get(i + 1)
+                    .map_or(chars.len(), |&e| e.min(chars.len()));
+                let unit: String = chars[p.min(end)..end].iter().collect();
+                if i == app.words.next {
+                    format!("[{}]", unit.trim())
+                } else {
+                    unit.trim().to_string()
+                }
+            })
+            .collect();
+        units.join("\u{b7}")
+    }
+
+    // the animated line, and the keyframe editor below it
+    pub fn focus_chunks(area: Rect) -> Rc<[Rect]> {
+        Layout::default()
             .direction(Direction::Vertical)
             .constraints([Constraint::Length(10), Constraint::Min(5)])
-            .split(area);
+            .split(area)
+    }
+
+    fn render_focus_mode(f: &mut Frame, app: &App, area: Rect) {
+        let chunks = Self::focus_chunks(area);
 
         let active_idx = app.focus_line_index.or(app.get_active_line_index());
 
@@ -95,53 +276,128 @@ impl UI {
         }
     }
 
+    // the time, marker, lint, review and part columns ahead of a line's text
+    pub fn list_line_prefix(
+        app: &App,
+        i: usize,
+        active_idx: Option<usize>,
+        diagnostics: &[Diagnostic],
+    ) -> Vec<Span<'static>> {
+        let theme = &app.config.theme;
+        let is_playing = Some(i) == active_idx;
+        let is_editing = app.view_mode == ViewMode::TextEdit && app.focus_line_index == Some(i);
+        let is_selected = (app.manual_scroll && i == app.scroll_offset) || is_editing;
+        let is_next_tap = app.view_mode == ViewMode::TapSync && app.tap.next == i;
+        let is_marked = app.marked_lines().is_some_and(|r| r.contains(&i));
+
+        let prefix = if is_next_tap {
+            " => "
+        } else if is_playing {
+            " >> "
+        } else if is_selected {
+            " -> "
+        } else if is_marked {
+            " \u{2503}  "
+        } else {
+            "    "
+        };
+
+        let mut line_spans = vec![
+            Span::styled(
+                format!("[{:7.2}] ", app.data.lines[i].start),
+                Style::default().fg(theme.dim),
+            ),
+            Span::styled(
+                prefix,
+                if is_next_tap {
+                    Style::default().fg(theme.highlight)
+                } else if is_playing {
+                    Style::default().fg(theme.playing)
+                } else {
+                    Style::default().fg(theme.accent)
+                },
+            ),
+        ];
+
+        if let Some(worst) = diagnostics
+            .iter()
+            .filter(|d| d.line == Some(i))
+            .map(|d| d.severity)
+            .max()
+        {
+            let color = if worst == Severity::Error {
+                theme.error
+            } else {
+                theme.warning
+            };
+            line_spans.push(Span::styled("! ", Style::default().fg(color)));
+        }
+
+        if app.review_lines.contains(&i) {
+            line_spans.push(Span::styled("? ", Style::default().fg(theme.info)));
+        }
+
+        if let Some(part) = app.data.part_starting_at(i) {
+            let label = if part.repeat.is_some() {
+                format!("[{} \u{21bb}] ", part.name)
+            } else {
+                format!("[{}] ", part.name)
+            };
+            let mut style = Style::default().fg(theme.part);
+            if !app.search.find(&part.name).is_empty() {
+                style = style.add_modifier(Modifier::REVERSED);
+            }
+            line_spans.push(Span::styled(label, style));
+        }
+        line_spans
+    }
+
+    // the first line shown in list mode, keeping a few above the selection
+    pub fn list_scroll(app: &App) -> usize {
+        let target = if app.view_mode == ViewMode::TextEdit {
+            app.focus_line_index.unwrap_or(app.scroll_offset)
+        } else if app.view_mode == ViewMode::TapSync {
+            app.tap.next
+        } else {
+            app.scroll_offset
+        };
+        target.saturating_sub(5)
+    }
+
     fn render_list_mode(f: &mut Frame, app: &App, area: Rect) {
         let active_idx = app.get_active_line_index();
         let display_idx = app.scroll_offset;
         let is_text_editor = app.view_mode == ViewMode::TextEdit;
+        let theme = &app.config.theme;
+        let marked = app.marked_lines();
+        let highlights = app.highlighter();
 
         let mut tui_lines = Vec::new();
+        let diagnostics = if app.show_diagnostics {
+            app.data.validate()
+        } else {
+            Vec::new()
+        };
 
         for (i, lyric) in app.data.lines.iter().enumerate() {
             let is_playing = Some(i) == active_idx;
             let is_editing = is_text_editor && app.focus_line_index == Some(i);
             let is_selected = (app.manual_scroll && i == display_idx) || is_editing;
-
-            let prefix = if is_playing {
-                " >> "
-            } else if is_selected {
-                " -> "
-            } else {
-                "    "
-            };
-
-            let mut line_spans = vec![
-                Span::styled(
-                    format!("[{:7.2}] ", lyric.start),
-                    Style::default().fg(Color::Gray),
-                ),
-                Span::styled(
-                    prefix,
-                    if is_playing {
-                        Style::default().fg(Color::Green)
-                    } else {
-                        Style::default().fg(Color::Blue)
-                    },
-                ),
-            ];
+            let is_marked = marked.as_ref().is_some_and(|r| r.contains(&i));
+            let mut line_spans = Self::list_line_prefix(app, i, active_idx, &diagnostics);
 
             if is_editing {
                 let text_chars: Vec<char> = lyric.text.chars().collect();
                 for (char_idx, c) in text_chars.iter().enumerate() {
                     let char_style = if char_idx == app.cursor_col {
-                        Style::default().bg(Color::Blue).fg(Color::White)
+                        Style::default().bg(theme.accent).fg(theme.accent_text)
                     } else {
-                        Style::default().fg(Color::White)
+                        Style::default().fg(theme.text)
                     };
                     line_spans.push(Span::styled(c.to_string(), char_style));
                 }
                 if app.cursor_col >= text_chars.len() {
-                    line_spans.push(Span::styled(" ", Style::default().bg(Color::Blue)));
+                    line_spans.push(Span::styled(" ", Style::default().bg(theme.accent)));
                 }
             } else if is_playing {
                 let animated_content =
@@ -150,37 +406,46 @@ impl UI {
             } else {
                 let style = if is_selected {
                     Style::default()
-                        .fg(Color::Blue)
+                        .fg(theme.accent)
                         .add_modifier(Modifier::REVERSED)
+                } else if is_marked {
+                    Style::default().fg(theme.accent)
                 } else {
-                    Style::default().fg(Color::DarkGray)
+                    Style::default().fg(theme.muted)
                 };
-                line_spans.push(Span::styled(lyric.text.clone(), style));
+                // matches of the search or pending replace stand out
+                let found = Style::default()
+                    .fg(theme.highlight)
+                    .add_modifier(Modifier::REVERSED);
+                let chars: Vec<char> = lyric.text.chars().collect();
+                let mut at = 0;
+                for range in highlights.find(&lyric.text) {
+                    let text: String = chars[at..range.start].iter().collect();
+                    line_spans.push(Span::styled(text, style));
+                    let text: String = chars[range.clone()].iter().collect();
+                    line_spans.push(Span::styled(text, found));
+                    at = range.end;
+                }
+                let text: String = chars[at..].iter().collect();
+                line_spans.push(Span::styled(text, style));
             }
 
             tui_lines.push(TuiLine::from(line_spans));
         }
 
-        let scroll_target = if is_text_editor {
-            app.focus_line_index.unwrap_or(display_idx)
-        } else {
-            display_idx
-        };
-        let scroll_pos = if scroll_target > 5 {
-            (scroll_target - 5) as u16
-        } else {
-            0
-        };
-
         let title = if is_text_editor {
             " Editing ".to_string()
         } else {
-            format!(" [E] EDIT | Status: {} ", app.server_status)
+            format!(
+                " [{}] EDIT | Status: {} ",
+                app.config.keys.hint(app.view_mode, Action::EditText),
+                app.server_status
+            )
         };
 
         let p = Paragraph::new(tui_lines)
             .alignment(Alignment::Left)
-            .scroll((scroll_pos, 0))
+            .scroll((Self::list_scroll(app) as u16, 0))
             .block(Block::default().borders(Borders::ALL).title(title));
 
         f.render_widget(p, area);
@@ -0,0 +1,190 @@
+use std::collections::BTreeMap;
+use std::path::{Component, Path};
+
+use crate::convert;
+use crate::tui::action::Action;
+use crate::tui::app::App;
+use crate::tui::macros::{self, MAX_REPLAY_DEPTH};
+
+// what `apply` leaves for its caller to do. the editor state changes on its own,
+// talking to the clipboard, the disk and the remote happens in `run_effects`
+#[derive(Clone, Debug, PartialEq)]
+pub enum Effect {
+    Remote(String),
+    // the document, to the file it then belongs to
+    Save(String),
+    // `done` is the status once written
+    Write {
+        path: String,
+        content: String,
+        done: String,
+    },
+    Copy {
+        text: String,
+        done: String,
+    },
+    Remove(String),
+}
+
+impl App {
+    pub fn send_remote(&mut self, msg: String) {
+        self.effects.push(Effect::Remote(msg));
+    }
+
+    // the files `action` reads when applied
+    fn reads(&self, action: &Action, depth: usize) -> Vec<String> {
+        let journal = |file: Option<&str>| self.journal_path(file).to_string_lossy().into_owned();
+        match action {
+            Action::OpenDraft
+            | Action::RestoreJournal
+            | Action::DiffJournal
+            | Action::DiscardJournal => match self.drafts.get(self.selected_draft) {
+                Some(draft) => vec![draft.clone(), journal(Some(draft))],
+                None => Vec::new(),
+            },
+            Action::RestoreUntitled => vec![journal(None)],
+            Action::PromptSubmit if depth == 0 => match &self.prompt {
+                Some(command) => self.command_reads(command, depth),
+                None => Vec::new(),
+            },
+            Action::Run(command) => self.command_reads(command, depth),
+            _ => Vec::new(),
+        }
+    }
+
+    fn command_reads(&self, command: &str, depth: usize) -> Vec<String> {
+        let args: Vec<&str> = command.split_whitespace().collect();
+        match args[..] {
+            ["retime" | "import", path, ..] | ["macro", "load", path] => vec![path.to_string()],
+            ["recover", ..] => {
+                let file = self.file_path.as_deref();
+                let journal = self.journal_path(file).to_string_lossy().into_owned();
+                file.map(str::to_string)
+                    .into_iter()
+                    .chain([journal])
+                    .collect()
+            }
+            // and what the script itself reads, as far as `replay` would go
+            ["play", path, ..] => {
+                let mut paths = vec![path.to_string()];
+                if depth < MAX_REPLAY_DEPTH
+                    && let Ok(input) = std::fs::read_to_string(path)
+                    && let Ok(actions) = macros::parse_actions(&input)
+                {
+                    for action in &actions {
+                        paths.extend(self.reads(action, depth + 1));
+                    }
+                }
+                paths
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn read_files(paths: Vec<String>) -> BTreeMap<String, Result<String, String>> {
+        paths
+            .into_iter()
+            .map(|path| {
+                let read = std::fs::read_to_string(&path).map_err(|e| format!("{}: {}", path, e));
+                (path, read)
+            })
+            .collect()
+    }
+
+    // the file given on the command line, read the way `dispatch` reads
+    pub fn open_file(&mut self, path: &str) {
+        let journal = self.journal_path(Some(path)).to_string_lossy().into_owned();
+        self.files = Self::read_files(vec![path.to_string(), journal]);
+        self.load_file(path);
+        self.files.clear();
+    }
+
+    // an action from a key, the mouse or the remote. the clipboard and files
+    // are read here once, so a replay of what was recorded sees the same text
+    pub fn dispatch(&mut self, action: Action) {
+        let action = match action {
+            Action::PasteClipboard | Action::PasteLines => {
+                match arboard::Clipboard::new().and_then(|mut cb| cb.get_text()) {
+                    Ok(text) if action == Action::PasteClipboard => Action::InsertText(text),
+                    Ok(text) => Action::InsertLines(text),
+                    Err(e) => {
+                        self.server_status = format!("Paste err: {}", e);
+                        return;
+                    }
+                }
+            }
+            action => action,
+        };
+        let paths = self.reads(&action, 0);
+        let action = if paths.is_empty() {
+            action
+        } else {
+            Action::Read {
+                action: Box::new(action),
+                files: Self::read_files(paths),
+            }
+        };
+        self.apply(action);
+        self.run_effects();
+    }
+
+    fn writable(&self, path: &str) -> Result<(), String> {
+        let Some(root) = &self.write_root else {
+            return Ok(());
+        };
+        let path = Path::new(path);
+        if path.starts_with(root) && !path.components().any(|c| c == Component::ParentDir) {
+            Ok(())
+        } else {
+            Err(format!("{} is outside {}", path.display(), root.display()))
+        }
+    }
+
+    pub fn run_effects(&mut self) {
+        for effect in std::mem::take(&mut self.effects) {
+            match effect {
+                Effect::Remote(msg) => {
+                    if self.remote_tx.send(msg).is_err() {
+                        self.server_status = "Sending failed".to_string();
+                    }
+                }
+                Effect::Save(path) => {
+                    let saved = self.writable(&path).and_then(|()| self.write_save(&path));
+                    self.server_status = match saved {
+                        Ok(msg) => msg,
+                        Err(e) => format!("Error: {}", e),
+                    }
+                }
+                Effect::Write {
+                    path,
+                    content,
+                    done,
+                } => {
+                    // a crash halfway leaves the old file, like saving does
+                    let written = self.writable(&path).and_then(|()| {
+                        convert::write_atomic(&path, &content, 0)
+                            .map_err(|e| format!("Could not write {}", e))
+                    });
+                    self.server_status = match written {
+                        Ok(()) => done,
+                        Err(e) => format!("Error: {}", e),
+                    }
+                }
+                Effect::Remove(path) => {
+                    if let Err(e) = self.writable(&path) {
+                        self.server_status = format!("Error: {}", e);
+                    } else {
+                        let _ = std::fs::remove_file(path);
+                    }
+                }
+                Effect::Copy { text, done } => {
+                    self.server_status =
+                        match arboard::Clipboard::new().and_then(|mut cb| cb.set_text(text)) {
+                            Ok(()) => done,
+                            Err(e) => format!("Copy err: {}", e),
+                        }
+                }
+            }
+        }
+    }
+}
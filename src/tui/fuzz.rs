@@ -0,0 +1,242 @@
+// random key sequences and pastes against `App`, rendering after every one.
+// FUZZ_RUNS and FUZZ_SEED go further than the default run:
+//   FUZZ_RUNS=5000 cargo test fuzz
+use std::panic::{AssertUnwindSafe, catch_unwind};
+
+use crossterm::event::{
+    KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEventKind,
+};
+
+use crate::model::AnimationData;
+use crate::tui::action::Action;
+use crate::tui::harness::{Harness, sample_document};
+use crate::tui::history::History;
+use crate::tui::types::ViewMode;
+
+const STEPS: usize = 250;
+
+const KEYS: &[KeyCode] = &[
+    KeyCode::Up,
+    KeyCode::Down,
+    KeyCode::Left,
+    KeyCode::Right,
+    KeyCode::Home,
+    KeyCode::End,
+    KeyCode::PageUp,
+    KeyCode::PageDown,
+    KeyCode::Enter,
+    KeyCode::Esc,
+    KeyCode::Tab,
+    KeyCode::Backspace,
+    KeyCode::Delete,
+];
+
+// every default binding plus text, wide and combined characters
+const CHARS: &str = "qsSejuUmMnNrdxvV:/ptfgkwlh+-,.  aZ0[]éé日本🎵";
+
+const MOUSE: &[MouseEventKind] = &[
+    MouseEventKind::Down(MouseButton::Left),
+    MouseEventKind::Drag(MouseButton::Left),
+    MouseEventKind::Up(MouseButton::Left),
+    MouseEventKind::ScrollUp,
+    MouseEventKind::ScrollDown,
+];
+
+const PASTES: &[&str] = &[
+    "",
+    "\n",
+    "\n\n",
+    "word",
+    "日本語",
+    "two\nlines",
+    "[Chorus]\nla la\n",
+    "[Bridge]",
+    "a\r\nb",
+];
+
+const COMMANDS: &[&str] = &[
+    "shift -3",
+    "shift 2.5",
+    "scale 0.5",
+    "scale 2 1",
+    "remap 1=3 4=2",
+    "delete",
+    "part Chorus",
+    "part",
+    "agent v2",
+    "agent",
+    "clear-keyframes",
+    "distribute 1 9",
+    "distribute 5 2",
+    "/star",
+    "/",
+    "s/t(w)/T$1/",
+    "s/[/x/",
+    "s/.*//",
+    "goto 5",
+    "goto 1:02.5",
+    "goto #2",
+    "goto #99",
+    "retime missing.txt",
+    "recover",
+    "play missing.log",
+    "macro save {dir}/macro.log",
+    "macro load {dir}/macro.log",
+    "export {dir}/export.lrc",
+    "export fuzz-export.lrc",
+    "save {dir}/drafts/saved.txt",
+    "save ../fuzz.txt",
+    "import missing.json",
+    "bogus",
+];
+
+// a chorus of multibyte lines, one with keyframes past its end
+fn wide_document() -> AnimationData {
+    let mut data = AnimationData::default();
+    data.add_line("日本の歌 🎵", 0.5, 2.0)
+        .add_keyframe(0.0, 0.0);
+    data.add_line("ça va", 2.0, 1.0).add_keyframe(3.0, 20.0);
+    data.add_line(" ", 4.0, 6.0);
+    data.start_part_at(0, "[Chorus]");
+    data
+}
+
+// splitmix64, so a failing seed replays exactly
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+fn step(h: &mut Harness, rng: &mut Rng, log: &mut Vec<String>) {
+    match rng.below(11) {
+        0..=3 => {
+            let c = CHARS.chars().nth(rng.below(CHARS.chars().count())).unwrap();
+            let modifiers = if c.is_uppercase() {
+                KeyModifiers::SHIFT
+            } else {
+                KeyModifiers::NONE
+            };
+            // terminals with the kitty protocol also report repeats and releases
+            let kind = [
+                KeyEventKind::Press,
+                KeyEventKind::Repeat,
+                KeyEventKind::Release,
+            ][rng.below(3)];
+            log.push(format!("key {:?} {:?}", c, kind));
+            h.app
+                .handle_key(KeyEvent::new_with_kind(KeyCode::Char(c), modifiers, kind));
+        }
+        4..=6 => {
+            let code = KEYS[rng.below(KEYS.len())];
+            let modifiers = [
+                KeyModifiers::NONE,
+                KeyModifiers::ALT,
+                KeyModifiers::CONTROL,
+                KeyModifiers::SHIFT,
+            ][rng.below(4)];
+            log.push(format!("key {:?} {:?}", code, modifiers));
+            h.app.handle_key(KeyEvent::new(code, modifiers));
+        }
+        7 => {
+            let paste = PASTES[rng.below(PASTES.len())];
+            log.push(format!("paste {:?}", paste));
+            if h.app.view_mode == ViewMode::TextEdit {
+                h.app.dispatch(Action::InsertText(paste.to_string()));
+            }
+        }
+        8 => {
+            let dir = h.app.write_root.clone().unwrap();
+            let command =
+                COMMANDS[rng.below(COMMANDS.len())].replace("{dir}", &dir.to_string_lossy());
+            log.push(format!("run {:?}", command));
+            h.app.dispatch(Action::Run(command));
+        }
+        9 => {
+            let kind = MOUSE[rng.below(MOUSE.len())];
+            // a few cells past the edges of the 60x16 screen too
+            let (column, row) = (rng.below(64) as u16, rng.below(20) as u16);
+            log.push(format!("mouse {:?} {} {}", kind, column, row));
+            h.mouse(kind, column, row);
+        }
+        _ => {
+            let secs = rng.below(40) as f32 * 0.25;
+            log.push(format!("advance {}", secs));
+            h.advance(secs);
+        }
+    }
+    h.render();
+}
+
+fn run(seed: u64) {
+    let mut rng = Rng(seed);
+    let documents = [
+        sample_document(),
+        wide_document(),
+        AnimationData::new(),
+        AnimationData::default(),
+    ];
+    let data = &documents[rng.below(documents.len())];
+    let mut h = Harness::new(&format!("fuzz-{}", seed), &[("song.txt", data)]);
+    // cramped, and quicker to draw every step
+    h.resize(60, 16);
+    if data.lines.is_empty() {
+        // the format can't store a document without lines, but one gets there
+        // once the only line left is a `[part]` header
+        h.app.apply(Action::NewDraft);
+        h.app.data.lines.clear();
+        h.app.saved = h.app.data.clone();
+        h.app.history = History::new(&h.app.data);
+    }
+    let mut log = Vec::new();
+
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        for _ in 0..STEPS {
+            step(&mut h, &mut rng, &mut log);
+        }
+    }));
+    let _ = std::fs::remove_dir_all(format!("target/harness/fuzz-{}", seed));
+    if result.is_err() {
+        panic!(
+            "seed {} panicked after:\n{}",
+            seed,
+            log[log.len().saturating_sub(30)..].join("\n")
+        );
+    }
+}
+
+#[test]
+fn test_fuzz_input_never_panics() {
+    let runs = std::env::var("FUZZ_RUNS")
+        .ok()
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(16);
+    let first: u64 = std::env::var("FUZZ_SEED")
+        .ok()
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(1);
+    let listing = || {
+        let mut names: Vec<_> = std::fs::read_dir(env!("CARGO_MANIFEST_DIR"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        names.sort();
+        names
+    };
+    let before = listing();
+    for seed in first..first + runs {
+        run(seed);
+    }
+    // saves, exports and macros stay in the harness folder
+    assert_eq!(listing(), before);
+}
@@ -0,0 +1,262 @@
+// drives `App` without a terminal: synthetic key events, a clock that only
+// moves when told to, and `UI::draw` rendered into a TestBackend.
+//
+// snapshots live in src/tui/snapshots, run with UPDATE_SNAPSHOTS=1 to write them
+// them after an intended change to the layout
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseEvent, MouseEventKind};
+use ratatui::Terminal;
+use ratatui::backend::TestBackend;
+use tokio::sync::broadcast;
+
+use crate::config::Config;
+use crate::convert;
+use crate::model::{AnimationData, Keyframe, TextSegment, WriteOptions};
+use crate::tui::app::App;
+use crate::tui::clock::Clock;
+use crate::tui::edit_line::UI;
+
+const WIDTH: u16 = 200;
+const HEIGHT: u16 = 24;
+
+pub struct Harness {
+    pub app: App,
+    terminal: Terminal<TestBackend>,
+    // `set_time` reports "Sending failed" once nobody listens
+    _remote_rx: broadcast::Receiver<String>,
+}
+
+impl Harness {
+    // `name` gets a folder of its own under target/, with a drafts root
+    // holding `drafts`. the app may not write files anywhere else
+    pub fn new(name: &str, drafts: &[(&str, &AnimationData)]) -> Self {
+        let _ = std::fs::remove_dir_all(format!("target/harness/{}", name));
+        let root = format!("target/harness/{}/drafts", name);
+        std::fs::create_dir_all(&root).unwrap();
+        for (file, data) in drafts {
+            let path = format!("{}/{}", root, file);
+            convert::write_file(&path, data, &WriteOptions::default(), 0).unwrap();
+        }
+
+        let mut config = Config::default();
+        config.drafts.roots = vec![root];
+        let (remote_tx, remote_rx) = broadcast::channel(100);
+        let mut app = App::new(remote_tx, config);
+        app.write_root = Some(PathBuf::from(format!("target/harness/{}", name)));
+        app.set_clock(Clock::Manual(std::time::Instant::now()));
+
+        Harness {
+            app,
+            terminal: Terminal::new(TestBackend::new(WIDTH, HEIGHT)).unwrap(),
+            _remote_rx: remote_rx,
+        }
+    }
+
+    pub fn resize(&mut self, width: u16, height: u16) {
+        self.terminal.backend_mut().resize(width, height);
+    }
+
+    pub fn key(&mut self, code: KeyCode) {
+        self.app.handle_key(KeyEvent::new(code, KeyModifiers::NONE));
+    }
+
+    pub fn chord(&mut self, code: KeyCode, modifiers: KeyModifiers) {
+        self.app.handle_key(KeyEvent::new(code, modifiers));
+    }
+
+    // plain characters, uppercase ones come with shift like a terminal sends them
+    pub fn keys(&mut self, keys: &str) {
+        for c in keys.chars() {
+            let modifiers = if c.is_uppercase() {
+                KeyModifiers::SHIFT
+            } else {
+                KeyModifiers::NONE
+            };
+            self.app
+                .handle_key(KeyEvent::new(KeyCode::Char(c), modifiers));
+        }
+    }
+
+    pub fn mouse(&mut self, kind: MouseEventKind, column: u16, row: u16) {
+        let mouse = MouseEvent {
+            kind,
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        };
+        let screen = self.terminal.size().unwrap().into();
+        self.app.handle_mouse(mouse, screen);
+    }
+
+    // lets `secs` pass in one tick of the main loop
+    pub fn advance(&mut self, secs: f32) {
+        self.app.clock.advance(Duration::from_secs_f32(secs));
+        self.app.update();
+    }
+
+    pub fn render(&mut self) -> String {
+        self.terminal.draw(|f| UI::draw(f, &self.app)).unwrap();
+        let buffer = self.terminal.backend().buffer();
+        let mut out = String::new();
+        for y in 0..buffer.area.height {
+            let row: String = (0..buffer.area.width)
+                .map(|x| buffer[(x, y)].symbol())
+                .collect();
+            out.push_str(row.trim_end());
+            out.push('\n');
+        }
+        out
+    }
+
+    pub fn assert_snapshot(&mut self, name: &str) {
+        let actual = self.render();
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("src/tui/snapshots")
+            .join(format!("{}.txt", name));
+        // snapshots are only written on request, a missing one fails
+        if std::env::var("UPDATE_SNAPSHOTS").as_deref() == Ok("1") {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, &actual).unwrap();
+            return;
+        }
+        let Ok(expected) = std::fs::read_to_string(&path) else {
+            panic!(
+                "no snapshot {}, UPDATE_SNAPSHOTS=1 writes it\n\n{}",
+                path.display(),
+                actual
+            );
+        };
+        if let Some((i, (want, got))) = expected
+            .lines()
+            .zip(actual.lines())
+            .enumerate()
+            .find(|(_, (want, got))| want != got)
+        {
+            panic!(
+                "snapshot {} differs at row {}\nexpected: {}\n  actual: {}\n\n{}",
+                name,
+                i + 1,
+                want,
+                got,
+                actual
+            );
+        }
+        assert_eq!(
+            expected.lines().count(),
+            actual.lines().count(),
+            "{}",
+            actual
+        );
+    }
+}
+
+// three lines a few seconds apart, the second with keyframes
+pub fn sample_document() -> AnimationData {
+    let mut data = AnimationData::new();
+    data.lines
+        .push(TextSegment::new("Twinkle twinkle".to_string(), 1.0, 4.0));
+    let mut line = TextSegment::new("little star".to_string(), 4.0, 7.0);
+    line.keyframes = vec![
+        Keyframe {
+            time: 0.0,
+            index: 0.0,
+        },
+        Keyframe {
+            time: 1.5,
+            index: 7.0,
+        },
+        Keyframe {
+            time: 3.0,
+            index: 11.0,
+        },
+    ];
+    data.lines.push(line);
+    data.lines
+        .push(TextSegment::new("how I wonder".to_string(), 7.0, 10.0));
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Harness, sample_document};
+    use crate::tui::Action;
+    use crate::tui::effect::Effect;
+    use crossterm::event::KeyCode;
+
+    #[test]
+    fn test_draft_selector_snapshot() {
+        let data = sample_document();
+        let mut h = Harness::new("selector", &[("a.txt", &data), ("b.txt", &data)]);
+        h.key(KeyCode::Down);
+        h.assert_snapshot("draft_selector");
+
+        // unsaved changes are not thrown away for another draft
+        h.key(KeyCode::Enter);
+        h.app.apply(Action::Run("shift 1".to_string()));
+        h.app.apply(Action::NewDraft);
+        h.app.apply(Action::OpenDraft);
+        assert_eq!(
+            h.app.file_path.as_deref(),
+            Some("target/harness/selector/drafts/b.txt")
+        );
+        assert_eq!(h.app.data.lines[1].start, 2.0);
+
+        // applying a save only asks for it, the caller writes the file
+        h.app.apply(Action::Save);
+        let path = "target/harness/selector/drafts/b.txt".to_string();
+        assert_eq!(h.app.effects, [Effect::Save(path)]);
+        assert!(h.app.is_dirty());
+        h.app.run_effects();
+        assert!(!h.app.is_dirty());
+    }
+
+    #[test]
+    fn test_list_mode_snapshot() {
+        let data = sample_document();
+        let mut h = Harness::new("list", &[("song.txt", &data)]);
+        h.key(KeyCode::Enter);
+        h.keys(" ");
+        for _ in 0..10 {
+            h.advance(0.5);
+        }
+        h.keys(" ");
+        assert_eq!(h.app.current_time, 5.0);
+        h.assert_snapshot("list_mode");
+    }
+
+    #[test]
+    fn test_focus_mode_keyframe_panel_snapshot() {
+        let data = sample_document();
+        let mut h = Harness::new("focus", &[("song.txt", &data)]);
+        h.key(KeyCode::Enter);
+        h.keys("jnkk");
+        h.assert_snapshot("focus_mode");
+    }
+
+    #[test]
+    fn test_files_are_read_by_dispatch() {
+        let data = sample_document();
+        let mut h = Harness::new("reads", &[("song.txt", &data)]);
+        h.key(KeyCode::Enter);
+        let opened = h.app.data.clone();
+        assert_eq!(opened.lines.len(), data.lines.len());
+
+        // `apply` on its own doesn't go to the disk
+        let path = "target/harness/reads/drafts/song.txt";
+        let import = Action::Run(format!("import {}", path));
+        h.app.apply(import.clone());
+        assert_eq!(h.app.server_status, format!("Error: {}: not read", path));
+
+        // a recorded import carries the file, so a replay still finds it
+        h.app.apply(Action::RecordMacro);
+        h.app.dispatch(import);
+        h.app.apply(Action::RecordMacro);
+        assert!(matches!(h.app.last_macro[..], [Action::Read { .. }]));
+        std::fs::remove_file(path).unwrap();
+        h.app.apply(Action::Run("shift 1".to_string()));
+        h.app.apply(Action::PlayMacro);
+        assert_eq!(h.app.data, opened);
+    }
+}
@@ -0,0 +1,204 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::model::{AnimationData, Part, TextSegment};
+
+// the oldest edits are dropped past either limit
+const MAX_EDITS: usize = 1000;
+const MAX_BYTES: usize = 8 << 20;
+// keystrokes closer together than this undo as one edit
+const TYPING_PAUSE: Duration = Duration::from_secs(1);
+
+// one reversible change: `before` at `at` was replaced by `after`
+struct Edit {
+    at: usize,
+    before: Vec<TextSegment>,
+    after: Vec<TextSegment>,
+    // only when the parts changed as well
+    parts: Option<(Vec<Part>, Vec<Part>)>,
+    typing: bool,
+    time: Instant,
+}
+
+fn weight(lines: &[TextSegment]) -> usize {
+    lines
+        .iter()
+        .map(|l| {
+            size_of::<TextSegment>()
+                + l.text.len()
+                + l.keyframes.len() * size_of::<crate::model::Keyframe>()
+                + l.ruby.iter().map(|r| r.text.len() + 64).sum::<usize>()
+        })
+        .sum()
+}
+
+impl Edit {
+    fn weight(&self) -> usize {
+        let parts = self
+            .parts
+            .as_ref()
+            .map_or(0, |(a, b)| (a.len() + b.len()) * size_of::<Part>());
+        weight(&self.before) + weight(&self.after) + parts
+    }
+
+    // applies the edit to `data` forwards, or backwards to take it back
+    fn apply(&self, data: &mut AnimationData, forward: bool) {
+        let (from, to) = if forward {
+            (&self.before, &self.after)
+        } else {
+            (&self.after, &self.before)
+        };
+        data.lines
+            .splice(self.at..self.at + from.len(), to.iter().cloned());
+        if let Some((before, after)) = &self.parts {
+            data.parts = if forward { after } else { before }.clone();
+        }
+    }
+}
+
+// undo / redo as a log of line splices rather than whole-document snapshots
+pub struct History {
+    done: VecDeque<Edit>,
+    undone: Vec<Edit>,
+    // the document as of the last recorded edit
+    head: AnimationData,
+    bytes: usize,
+    typing: bool,
+}
+
+impl History {
+    pub fn new(data: &AnimationData) -> Self {
+        History {
+            done: VecDeque::new(),
+            undone: Vec::new(),
+            head: data.clone(),
+            bytes: 0,
+            typing: false,
+        }
+    }
+
+    // the change about to be recorded is a keystroke in the text editor or a
+    // step of a mouse drag, which merge with the ones right before
+    pub fn mark_typing(&mut self) {
+        self.typing = true;
+    }
+
+    // records everything that changed since the last call as one edit
+    pub fn record(&mut self, data: &AnimationData, now: Instant) {
+        let typing = std::mem::take(&mut self.typing);
+        if *data == self.head {
+            return;
+        }
+
+        let (old, new) = (&self.head.lines, &data.lines);
+        let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+        let suffix = old[prefix..]
+            .iter()
+            .rev()
+            .zip(new[prefix..].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+        let edit = Edit {
+            at: prefix,
+            before: old[prefix..old.len() - suffix].to_vec(),
+            after: new[prefix..new.len() - suffix].to_vec(),
+            parts: (self.head.parts != data.parts)
+                .then(|| (self.head.parts.clone(), data.parts.clone())),
+            typing,
+            time: now,
+        };
+        edit.apply(&mut self.head, true);
+        self.undone.clear();
+
+        // keystrokes on the same lines extend the previous edit
+        if let Some(last) = self.done.back_mut()
+            && typing
+            && last.typing
+            && last.parts.is_none()
+            && edit.parts.is_none()
+            && last.at == edit.at
+            && last.after.len() == edit.before.len()
+            && now.duration_since(last.time) < TYPING_PAUSE
+        {
+            self.bytes -= last.weight();
+            last.after = edit.after;
+            last.time = now;
+            self.bytes += last.weight();
+        } else {
+            self.bytes += edit.weight();
+            self.done.push_back(edit);
+        }
+
+        while self.done.len() > MAX_EDITS || (self.bytes > MAX_BYTES && self.done.len() > 1) {
+            if let Some(dropped) = self.done.pop_front() {
+                self.bytes -= dropped.weight();
+            }
+        }
+    }
+
+    // the first line touched, to put the cursor back there
+    pub fn undo(&mut self, data: &mut AnimationData) -> Option<usize> {
+        let edit = self.done.pop_back()?;
+        self.bytes -= edit.weight();
+        edit.apply(data, false);
+        edit.apply(&mut self.head, false);
+        let at = edit.at;
+        self.undone.push(edit);
+        Some(at)
+    }
+
+    pub fn redo(&mut self, data: &mut AnimationData) -> Option<usize> {
+        let edit = self.undone.pop()?;
+        edit.apply(data, true);
+        edit.apply(&mut self.head, true);
+        self.bytes += edit.weight();
+        let at = edit.at;
+        self.done.push_back(edit);
+        Some(at)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::History;
+    use crate::model::{AnimationData, TextSegment};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_undo_redo_groups_typing() {
+        let mut data = AnimationData::default();
+        data.lines
+            .push(TextSegment::new("one".to_string(), 0.0, 1.0));
+        data.lines
+            .push(TextSegment::new("two".to_string(), 1.0, 2.0));
+        let mut history = History::new(&data);
+        let t = Instant::now();
+
+        for (i, c) in "ne".chars().enumerate() {
+            data.lines[1].text.push(c);
+            history.mark_typing();
+            history.record(&data, t + Duration::from_millis(100 * i as u64));
+        }
+        data.lines.remove(0);
+        history.record(&data, t + Duration::from_millis(300));
+
+        assert_eq!(history.undo(&mut data), Some(0));
+        assert_eq!(data.lines[0].text, "one");
+        assert_eq!(data.lines[1].text, "twone");
+        // both keystrokes go at once
+        assert_eq!(history.undo(&mut data), Some(1));
+        assert_eq!(data.lines[1].text, "two");
+        assert_eq!(history.undo(&mut data), None);
+
+        assert_eq!(history.redo(&mut data), Some(1));
+        assert_eq!(history.redo(&mut data), Some(0));
+        assert_eq!(data.lines.len(), 1);
+        assert_eq!(data.lines[0].text, "twone");
+
+        // a new edit drops what was undone
+        history.undo(&mut data);
+        data.lines[0].start = 5.0;
+        history.record(&data, t + Duration::from_secs(5));
+        assert_eq!(history.redo(&mut data), None);
+    }
+}
@@ -0,0 +1,310 @@
+use std::collections::HashMap;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::de::value::StrDeserializer;
+use serde::{Deserialize, Deserializer};
+
+use crate::tui::action::Action;
+use crate::tui::types::ViewMode;
+
+// `global` applies in list, focus, tap and timeline mode, below their own tables. unbound
+// characters type in `text`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    Global,
+    Drafts,
+    List,
+    Line,
+    Text,
+    Tap,
+    Timeline,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct KeyBinding {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    // "q", "S", "space", "pagedown", "ctrl+z", "f5"
+    pub fn parse(input: &str) -> Result<KeyBinding, String> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut key = input;
+        while let Some((modifier, rest)) = key.split_once('+').filter(|(_, r)| !r.is_empty()) {
+            modifiers |= match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(format!("unknown modifier '{}' in '{}'", modifier, input)),
+            };
+            key = rest;
+        }
+
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match key.to_ascii_lowercase().as_str() {
+                "space" => KeyCode::Char(' '),
+                "enter" | "return" => KeyCode::Enter,
+                "esc" | "escape" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" => KeyCode::Insert,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                name => match name.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                    Some(n) => KeyCode::F(n),
+                    None => return Err(format!("unknown key '{}'", input)),
+                },
+            },
+        };
+        // shift only counts for keys that are not characters, "shift+s" is "S"
+        let code = match code {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => {
+                modifiers -= KeyModifiers::SHIFT;
+                KeyCode::Char(c.to_uppercase().next().unwrap_or(c))
+            }
+            code => code,
+        };
+        Ok(KeyBinding { code, modifiers })
+    }
+
+    // shift is part of the character itself, "S" rather than "shift+s"
+    fn from_event(key: &KeyEvent) -> KeyBinding {
+        let mut modifiers = key.modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT);
+        if !matches!(key.code, KeyCode::Char(_)) {
+            modifiers |= key.modifiers & KeyModifiers::SHIFT;
+        }
+        KeyBinding {
+            code: key.code,
+            modifiers,
+        }
+    }
+
+    // as shown in the header hints
+    fn label(&self) -> String {
+        let key = match self.code {
+            KeyCode::Char(' ') => "SPACE".to_string(),
+            KeyCode::Char(c) if c.is_uppercase() => format!("SHIFT+{}", c),
+            KeyCode::Char(c) => c.to_uppercase().to_string(),
+            KeyCode::Delete => "DEL".to_string(),
+            KeyCode::PageUp => "PGUP".to_string(),
+            KeyCode::PageDown => "PGDN".to_string(),
+            code => code.to_string().to_uppercase(),
+        };
+        let mut label = String::new();
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            label.push_str("CTRL+");
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            label.push_str("ALT+");
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            label.push_str("SHIFT+");
+        }
+        label + &key
+    }
+}
+
+pub struct Keymap {
+    bindings: Vec<(Scope, KeyBinding, Action)>,
+}
+
+const DEFAULT_BINDINGS: &[(Scope, &str, Action)] = &[
+    (Scope::Global, "q", Action::Quit),
+    (Scope::Global, "s", Action::Save),
+    (Scope::Global, "S", Action::SaveAs),
+    (Scope::Global, "space", Action::PlayPause),
+    (Scope::Global, "e", Action::EditText),
+    (Scope::Global, "j", Action::FocusMode),
+    (Scope::Global, "left", Action::SeekBack),
+    (Scope::Global, "right", Action::SeekForward),
+    (Scope::Global, "esc", Action::ToggleView),
+    (Scope::Global, "u", Action::Undo),
+    (Scope::Global, "ctrl+z", Action::Undo),
+    (Scope::Global, "U", Action::Redo),
+    (Scope::Global, "ctrl+y", Action::Redo),
+    (Scope::Global, "m", Action::RecordMacro),
+    (Scope::Global, "M", Action::PlayMacro),
+    (Scope::Drafts, "q", Action::Quit),
+    (Scope::Drafts, "up", Action::PrevDraft),
+    (Scope::Drafts, "down", Action::NextDraft),
+    (Scope::Drafts, "enter", Action::OpenDraft),
+    (Scope::Drafts, "n", Action::NewDraft),
+    (Scope::Drafts, "r", Action::RestoreJournal),
+    (Scope::Drafts, "d", Action::DiffJournal),
+    (Scope::Drafts, "x", Action::DiscardJournal),
+    (Scope::Drafts, "u", Action::RestoreUntitled),
+    (Scope::Drafts, "esc", Action::ToggleView),
+    (Scope::List, "up", Action::ScrollUp),
+    (Scope::List, "down", Action::ScrollDown),
+    (Scope::List, "pageup", Action::PrevLine),
+    (Scope::List, "pagedown", Action::NextLine),
+    (Scope::List, ":", Action::Prompt),
+    (Scope::List, "v", Action::ToggleDiagnostics),
+    (Scope::List, "V", Action::ApplyFixes),
+    (Scope::List, "r", Action::RepeatPart),
+    (Scope::List, "t", Action::TapSync),
+    (Scope::List, "l", Action::Timeline),
+    (Scope::List, "shift+up", Action::SelectUp),
+    (Scope::List, "shift+down", Action::SelectDown),
+    (Scope::List, "delete", Action::DeleteLines),
+    (Scope::List, "ctrl+c", Action::CopyLines),
+    (Scope::List, "ctrl+v", Action::PasteLines),
+    (Scope::List, "/", Action::Search),
+    (Scope::List, "n", Action::NextMatch),
+    (Scope::List, "N", Action::PrevMatch),
+    (Scope::List, "g", Action::GoTo),
+    (Scope::Line, "p", Action::PrevLine),
+    (Scope::Line, "n", Action::NextLine),
+    (Scope::Line, "t", Action::ToggleEditMode),
+    (Scope::Line, "f", Action::AddKeyframe),
+    (Scope::Line, "g", Action::DeleteKeyframe),
+    (Scope::Line, "delete", Action::DeleteKeyframe),
+    (Scope::Line, "up", Action::NudgeUp),
+    (Scope::Line, "down", Action::NudgeDown),
+    (Scope::Line, "j", Action::PrevKeyframe),
+    (Scope::Line, "k", Action::NextKeyframe),
+    (Scope::Line, "w", Action::WordSync),
+    (Scope::Tap, "space", Action::Tap),
+    (Scope::Tap, "backspace", Action::UndoTap),
+    (Scope::Tap, "enter", Action::PlayPause),
+    (Scope::Tap, "tab", Action::ToggleSplit),
+    (Scope::Timeline, "+", Action::ZoomIn),
+    (Scope::Timeline, "=", Action::ZoomIn),
+    (Scope::Timeline, "-", Action::ZoomOut),
+    (Scope::Timeline, "pageup", Action::PanLeft),
+    (Scope::Timeline, "pagedown", Action::PanRight),
+    (Scope::Timeline, "home", Action::FollowPlayhead),
+    (Scope::Timeline, "up", Action::ScrollUp),
+    (Scope::Timeline, "down", Action::ScrollDown),
+    (Scope::Timeline, "h", Action::MoveEarlier),
+    (Scope::Timeline, "l", Action::MoveLater),
+    (Scope::Timeline, "[", Action::StartEarlier),
+    (Scope::Timeline, "]", Action::StartLater),
+    (Scope::Timeline, ",", Action::EndEarlier),
+    (Scope::Timeline, ".", Action::EndLater),
+    (Scope::Text, "esc", Action::FinishEditing),
+    (Scope::Text, "left", Action::CursorLeft),
+    (Scope::Text, "right", Action::CursorRight),
+    (Scope::Text, "up", Action::CursorUp),
+    (Scope::Text, "down", Action::CursorDown),
+    (Scope::Text, "alt+up", Action::MoveLineUp),
+    (Scope::Text, "alt+down", Action::MoveLineDown),
+    (Scope::Text, "shift+up", Action::SelectUp),
+    (Scope::Text, "shift+down", Action::SelectDown),
+    (Scope::Text, "ctrl+c", Action::CopyLines),
+    (Scope::Text, "backspace", Action::Backspace),
+    (Scope::Text, "enter", Action::SplitLine),
+    (Scope::Text, "ctrl+z", Action::Undo),
+    (Scope::Text, "ctrl+y", Action::Redo),
+    (Scope::Text, "ctrl+v", Action::PasteClipboard),
+];
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap {
+            bindings: DEFAULT_BINDINGS
+                .iter()
+                .map(|(scope, key, action)| {
+                    let key = KeyBinding::parse(key).expect("default bindings parse");
+                    (*scope, key, action.clone())
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Keymap {
+    fn scopes(mode: ViewMode) -> &'static [Scope] {
+        match mode {
+            ViewMode::DraftSelector => &[Scope::Drafts],
+            ViewMode::List => &[Scope::List, Scope::Global],
+            ViewMode::Line => &[Scope::Line, Scope::Global],
+            ViewMode::TextEdit => &[Scope::Text],
+            ViewMode::TapSync | ViewMode::WordSync => &[Scope::Tap, Scope::Global],
+            ViewMode::Timeline => &[Scope::Timeline, Scope::Global],
+        }
+    }
+
+    pub fn action(&self, mode: ViewMode, key: &KeyEvent) -> Option<Action> {
+        let key = KeyBinding::from_event(key);
+        Self::scopes(mode).iter().find_map(|scope| {
+            self.bindings
+                .iter()
+                .find(|(s, k, _)| s == scope && *k == key)
+                .map(|(_, _, action)| action.clone())
+        })
+    }
+
+    // every key bound to `action` where `mode` would look for it, e.g. "G/DEL"
+    pub fn hint(&self, mode: ViewMode, action: Action) -> String {
+        let action = &action;
+        let keys: Vec<String> = Self::scopes(mode)
+            .iter()
+            .flat_map(|scope| {
+                self.bindings
+                    .iter()
+                    .filter(move |(s, _, a)| s == scope && a == action)
+            })
+            .map(|(_, key, _)| key.label())
+            .collect();
+        if keys.is_empty() {
+            "-".to_string()
+        } else {
+            keys.join("/")
+        }
+    }
+
+    // an action listed in the config loses its default keys in that scope
+    fn rebind(&mut self, scope: Scope, action: Action, keys: Vec<KeyBinding>) {
+        self.bindings
+            .retain(|(s, _, a)| !(*s == scope && *a == action));
+        for key in keys {
+            // the key leaves whatever it was bound to before
+            self.bindings
+                .retain(|(s, k, _)| !(*s == scope && *k == key));
+            self.bindings.push((scope, key, action.clone()));
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Keys {
+    One(String),
+    Many(Vec<String>),
+}
+
+// `[keys.<scope>]` tables of `action = "key"` or `action = ["key", ...]`, over the defaults
+impl<'de> Deserialize<'de> for Keymap {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let tables = HashMap::<Scope, HashMap<String, Keys>>::deserialize(deserializer)?;
+        let mut keymap = Keymap::default();
+        for (scope, table) in tables {
+            for (name, keys) in table {
+                let action = Action::deserialize(StrDeserializer::<D::Error>::new(&name))?;
+                let keys = match keys {
+                    Keys::One(key) => vec![key],
+                    Keys::Many(keys) => keys,
+                };
+                let keys = keys
+                    .iter()
+                    .map(|k| KeyBinding::parse(k))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(serde::de::Error::custom)?;
+                keymap.rebind(scope, action, keys);
+            }
+        }
+        Ok(keymap)
+    }
+}
@@ -0,0 +1,90 @@
+use crate::tui::action::Action;
+use crate::tui::app::App;
+
+// scripts that `play` other scripts stop here
+pub const MAX_REPLAY_DEPTH: usize = 8;
+
+// one JSON action per line, blank lines and `#` comments are skipped
+pub fn parse_actions(input: &str) -> Result<Vec<Action>, String> {
+    input
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(i, line)| serde_json::from_str(line).map_err(|e| format!("line {}: {}", i + 1, e)))
+        .collect()
+}
+
+pub fn format_actions(actions: &[Action]) -> Result<String, String> {
+    let mut out = String::new();
+    for action in actions {
+        out.push_str(&serde_json::to_string(action).map_err(|e| e.to_string())?);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+impl App {
+    pub fn read_actions(&self, path: &str) -> Result<Vec<Action>, String> {
+        parse_actions(&self.read(path)?).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn toggle_recording(&mut self) {
+        match self.recording.take() {
+            Some(actions) => {
+                self.server_status = format!("Recorded a macro of {} actions", actions.len());
+                self.last_macro = actions;
+            }
+            None => {
+                self.recording = Some(Vec::new());
+                self.server_status = "Recording macro...".to_string();
+            }
+        }
+    }
+
+    pub fn play_macro(&mut self) {
+        let actions = self.last_macro.clone();
+        match self.replay(actions) {
+            Ok(count) => self.server_status = format!("Played {} actions", count),
+            Err(e) => self.server_status = format!("Error: {}", e),
+        }
+    }
+
+    pub fn replay(&mut self, actions: Vec<Action>) -> Result<usize, String> {
+        if self.replay_depth >= MAX_REPLAY_DEPTH {
+            return Err("macros nested too deep".to_string());
+        }
+        self.replay_depth += 1;
+        let count = actions.len();
+        for action in actions {
+            self.apply(action);
+        }
+        self.replay_depth -= 1;
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_actions, parse_actions};
+    use crate::tui::Action;
+
+    #[test]
+    fn test_action_log_round_trip() {
+        let actions = vec![
+            Action::EditText,
+            Action::Type('a'),
+            Action::InsertText("b\nc".to_string()),
+            Action::Seek(12.5),
+            Action::Run("shift 0.5".to_string()),
+        ];
+        let log = format_actions(&actions).unwrap();
+        assert_eq!(
+            log.lines().take(2).collect::<Vec<_>>(),
+            ["\"edit_text\"", "{\"type\":\"a\"}"]
+        );
+        let parsed = parse_actions(&format!("# demo\n\n{}", log)).unwrap();
+        assert_eq!(parsed, actions);
+        assert!(parse_actions("\"fly\"").is_err());
+    }
+}
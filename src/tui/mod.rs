@@ -1,10 +1,32 @@
+mod action;
 mod app;
+mod clock;
+mod commands;
 mod drafts;
 mod edit_keyframe;
 mod edit_line;
 mod edit_text;
+mod effect;
+#[cfg(test)]
+mod fuzz;
+#[cfg(test)]
+mod harness;
+mod history;
+mod keymap;
+mod macros;
+mod mouse;
+mod recovery;
+mod save;
+mod search;
+mod selection;
+mod tap_sync;
+mod theme;
+mod timeline;
 mod types;
 
+pub use action::Action;
 pub use app::App;
 pub use edit_line::UI;
+pub use keymap::Keymap;
+pub use theme::Theme;
 pub use types::*;
@@ -0,0 +1,195 @@
+use crossterm::event::{MouseButton, MouseEvent, MouseEventKind};
+use ratatui::layout::{Margin, Position, Rect};
+use ratatui::text::Span;
+
+use crate::tui::action::Action;
+use crate::tui::app::App;
+use crate::tui::edit_line::UI;
+use crate::tui::types::ViewMode;
+
+// a left button drag, with the column the mouse was last seen at
+#[derive(Clone, Copy)]
+pub enum Drag {
+    // nudges the selected keyframe a step per column
+    Keyframe(u16),
+    // pans the timeline along with the mouse
+    Timeline(u16),
+}
+
+// the char under `x` of a line drawn from column 0, the end when past it
+fn char_at(text: &str, x: u16) -> usize {
+    let mut col = 0;
+    for (i, c) in text.chars().enumerate() {
+        col += Span::raw(c.to_string()).width() as u16;
+        if x < col {
+            return i;
+        }
+    }
+    text.chars().count()
+}
+
+impl App {
+    // mouse input becomes the actions a click or drag stands for, hit-tested
+    // against the layout `UI::draw` gives `screen`
+    pub fn handle_mouse(&mut self, mouse: MouseEvent, screen: Rect) {
+        if self.prompt.is_some() {
+            return;
+        }
+        let body = UI::chunks(screen)[1];
+        let at = Position::new(mouse.column, mouse.row);
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                self.drag = None;
+                if let Some(action) = self.click_action(body, at) {
+                    self.dispatch(action);
+                }
+            }
+            MouseEventKind::Drag(MouseButton::Left) => match self.drag {
+                Some(Drag::Keyframe(from)) if self.view_mode == ViewMode::Line => {
+                    let nudge = if mouse.column > from {
+                        Action::NudgeUp
+                    } else {
+                        Action::NudgeDown
+                    };
+                    for _ in 0..mouse.column.abs_diff(from) {
+                        // the whole drag undoes as one edit
+                        self.history.mark_typing();
+                        self.dispatch(nudge.clone());
+                    }
+                    self.drag = Some(Drag::Keyframe(mouse.column));
+                }
+                Some(Drag::Timeline(from)) if self.view_mode == ViewMode::Timeline => {
+                    if mouse.column != from {
+                        self.dispatch(Action::Pan(from as i32 - mouse.column as i32));
+                    }
+                    self.drag = Some(Drag::Timeline(mouse.column));
+                }
+                _ => self.drag = None,
+            },
+            MouseEventKind::Up(_) => self.drag = None,
+            MouseEventKind::ScrollUp | MouseEventKind::ScrollDown => {
+                let up = mouse.kind == MouseEventKind::ScrollUp;
+                let action = match self.view_mode {
+                    ViewMode::List if up => Action::ScrollUp,
+                    ViewMode::List => Action::ScrollDown,
+                    ViewMode::Timeline if up => Action::ZoomIn,
+                    ViewMode::Timeline => Action::ZoomOut,
+                    _ => return,
+                };
+                self.dispatch(action);
+            }
+            _ => {}
+        }
+    }
+
+    fn click_action(&mut self, body: Rect, at: Position) -> Option<Action> {
+        match self.view_mode {
+            ViewMode::List | ViewMode::TextEdit => {
+                let inner = body.inner(Margin::new(1, 1));
+                if !inner.contains(at) {
+                    return None;
+                }
+                let idx = UI::list_scroll(self) + (at.y - inner.y) as usize;
+                let line = self.data.lines.get(idx)?;
+                if self.view_mode == ViewMode::List {
+                    return Some(Action::Seek(line.start));
+                }
+                let diagnostics = if self.show_diagnostics {
+                    self.data.validate()
+                } else {
+                    Vec::new()
+                };
+                let prefix: usize =
+                    UI::list_line_prefix(self, idx, self.get_active_line_index(), &diagnostics)
+                        .iter()
+                        .map(|s| s.width())
+                        .sum();
+                let x = (at.x - inner.x).saturating_sub(prefix as u16);
+                Some(Action::PlaceCursor(idx, char_at(&line.text, x)))
+            }
+            ViewMode::Line => {
+                let idx = self.focus_line_index.or(self.get_active_line_index())?;
+                let panel = UI::focus_chunks(body)[1];
+                // the chips sit centered below the panel's top border
+                if at.y != panel.y + 1 {
+                    return None;
+                }
+                let chips = UI::keyframe_chips(self, idx);
+                let widths: Vec<u16> = chips
+                    .iter()
+                    .map(|chip| chip.iter().map(|s| s.width() as u16).sum())
+                    .collect();
+                let total: u16 = widths.iter().sum();
+                let mut x = panel.x + (panel.width / 2).saturating_sub(total / 2);
+                for (ki, width) in widths.into_iter().enumerate() {
+                    if (x..x + width).contains(&at.x) {
+                        self.drag = Some(Drag::Keyframe(at.x));
+                        return Some(Action::SelectKeyframe(ki));
+                    }
+                    x += width;
+                }
+                None
+            }
+            ViewMode::Timeline => {
+                if UI::timeline_chunks(body)[0].contains(at) {
+                    self.drag = Some(Drag::Timeline(at.x));
+                }
+                None
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tui::harness::{Harness, sample_document};
+    use crossterm::event::{KeyCode, MouseButton, MouseEventKind};
+
+    const DOWN: MouseEventKind = MouseEventKind::Down(MouseButton::Left);
+    const DRAG: MouseEventKind = MouseEventKind::Drag(MouseButton::Left);
+
+    #[test]
+    fn test_mouse_clicks_and_drags() {
+        let data = sample_document();
+        let mut h = Harness::new("mouse", &[("song.txt", &data)]);
+        h.key(KeyCode::Enter);
+
+        // list rows start below the header and the list's border
+        h.mouse(DOWN, 50, 7);
+        assert_eq!(h.app.current_time, 4.0);
+
+        // "[   1.00]     " ahead of the text
+        h.keys("e");
+        h.mouse(DOWN, 1 + 14 + 3, 6);
+        assert_eq!((h.app.focus_line_index, h.app.cursor_col), (Some(1), 3));
+        h.key(KeyCode::Esc);
+
+        // grab KF1 of "little star" and pull it four steps later
+        h.app.apply(crate::tui::Action::Seek(4.5));
+        h.keys("j");
+        let screen = h.render();
+        let (row, col) = screen
+            .lines()
+            .enumerate()
+            .find_map(|(y, l)| l.find("[KF1:").map(|x| (y, l[..x].chars().count())))
+            .unwrap();
+        h.mouse(DOWN, col as u16, row as u16);
+        assert_eq!(h.app.active_kf_index, Some(1));
+        assert_eq!(h.app.current_time, 5.5);
+        h.mouse(DRAG, col as u16 + 4, row as u16);
+        assert!((h.app.data.lines[2].keyframes[1].time - 1.6).abs() < 1e-4);
+        h.keys("u");
+        assert_eq!(h.app.data.lines[2].keyframes[1].time, 1.5);
+
+        // dragging the timeline ten columns left pans 2.5s later
+        h.key(KeyCode::Esc);
+        h.keys("l");
+        h.mouse(DOWN, 100, 7);
+        h.mouse(DRAG, 90, 7);
+        assert!(h.app.manual_scroll);
+        assert_eq!(h.app.timeline.center, h.app.current_time + 2.5);
+        h.mouse(MouseEventKind::ScrollUp, 90, 7);
+        assert_eq!(h.app.timeline.columns_per_sec(), 8.0);
+    }
+}
@@ -0,0 +1,131 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::convert;
+use crate::model::{AnimationData, render_changes};
+use crate::tui::action::Action;
+use crate::tui::app::App;
+use crate::tui::effect::Effect;
+use crate::tui::history::History;
+use crate::tui::types::ViewMode;
+
+const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(5);
+
+impl App {
+    // `<dir>/.<name>.recover` next to the file it recovers, drafts that were
+    // never saved use `untitled` in the first drafts root
+    pub fn journal_path(&self, file: Option<&str>) -> PathBuf {
+        let untitled = format!("{}/untitled", self.config.drafts.roots[0]);
+        let path = Path::new(file.unwrap_or(&untitled));
+        let name = path
+            .file_name()
+            .map_or("untitled".into(), |n| n.to_string_lossy());
+        path.with_file_name(format!(".{}.recover", name))
+    }
+
+    pub fn has_journal(&self, file: Option<&str>) -> bool {
+        self.journal_path(file).exists()
+    }
+
+    // `has_journal` for `apply`, which only sees what `dispatch` read
+    pub fn journal_read(&self, file: Option<&str>) -> bool {
+        self.read(&self.journal_path(file).to_string_lossy())
+            .is_ok()
+    }
+
+    fn read_journal(&self, file: Option<&str>) -> Result<AnimationData, String> {
+        self.read(&self.journal_path(file).to_string_lossy())?
+            .parse::<AnimationData>()
+    }
+
+    // called every tick, writes the journal at most every AUTOSAVE_INTERVAL
+    pub fn autosave(&mut self) {
+        if self.view_mode == ViewMode::DraftSelector
+            || self.clock.now().duration_since(self.last_autosave) < AUTOSAVE_INTERVAL
+        {
+            return;
+        }
+        self.last_autosave = self.clock.now();
+        if !self.is_dirty() || self.journaled.as_ref() == Some(&self.data) {
+            return;
+        }
+
+        let path = self.journal_path(self.file_path.as_deref());
+        let written = convert::write_atomic(&path.to_string_lossy(), &self.data.compile(), 0);
+        match written {
+            Ok(()) => self.journaled = Some(self.data.clone()),
+            Err(e) => self.server_status = format!("Autosave failed: {}", e),
+        }
+    }
+
+    pub fn clear_journal(&mut self, file: Option<&str>) {
+        let _ = std::fs::remove_file(self.journal_path(file));
+        self.journaled = None;
+    }
+
+    // on a clean exit, so a journal is only left behind after a crash. journals
+    // of other sessions or of drafts that weren't restored are kept.
+    pub fn discard_journal(&mut self) {
+        if self.journaled.is_some() {
+            self.clear_journal(self.file_path.clone().as_deref());
+        }
+    }
+
+    // opens the journal in place of `file`, which stays the save target
+    pub fn restore_journal(&mut self, file: Option<&str>) -> Result<String, String> {
+        let data = self.read_journal(file)?;
+        self.saved = match file {
+            Some(path) => self.read_document(path).unwrap_or_default(),
+            None => AnimationData::new(),
+        };
+        self.journaled = Some(data.clone());
+        self.data = data;
+        self.file_path = file.map(str::to_string);
+        self.recovery_diff = None;
+        self.view_mode = ViewMode::List;
+        Ok(format!(
+            "Restored unsaved changes to {}",
+            file.unwrap_or("a new draft")
+        ))
+    }
+
+    pub fn diff_journal(&mut self, file: &str) -> Result<String, String> {
+        let journal = self.read_journal(Some(file))?;
+        let saved = self.read_document(file)?;
+        let changes = saved.diff(&journal);
+        self.recovery_diff = Some(
+            render_changes(&changes)
+                .lines()
+                .map(str::to_string)
+                .collect(),
+        );
+        Ok(format!("{} unsaved changes in {}", changes.len(), file))
+    }
+
+    pub fn handle_recovery_action(&mut self, action: Action) {
+        let selected = self.drafts.get(self.selected_draft).cloned();
+        let result = match (action, selected) {
+            (Action::RestoreUntitled, _) if self.journal_read(None) => {
+                self.history = History::new(&AnimationData::new());
+                self.restore_journal(None)
+            }
+            (Action::RestoreJournal, Some(path)) if self.journal_read(Some(&path)) => {
+                self.history = History::new(&self.read_document(&path).unwrap_or_default());
+                self.restore_journal(Some(&path))
+            }
+            (Action::DiffJournal, Some(path)) if self.journal_read(Some(&path)) => {
+                self.diff_journal(&path)
+            }
+            (Action::DiscardJournal, Some(path)) if self.journal_read(Some(&path)) => {
+                let journal = self.journal_path(Some(&path));
+                self.effects
+                    .push(Effect::Remove(journal.to_string_lossy().into_owned()));
+                self.journaled = None;
+                self.recovery_diff = None;
+                Ok(format!("Discarded unsaved changes to {}", path))
+            }
+            _ => return,
+        };
+        self.server_status = result.unwrap_or_else(|e| format!("Error: {}", e));
+    }
+}
@@ -0,0 +1,68 @@
+use std::path::Path;
+
+use crate::convert::{self, DEFAULT_BACKUPS};
+use crate::model::WriteOptions;
+use crate::tui::app::App;
+use crate::tui::effect::Effect;
+
+impl App {
+    pub fn is_dirty(&self) -> bool {
+        self.data != self.saved
+    }
+
+    // saves to the loaded file, new drafts ask for a name first
+    pub fn save(&mut self) {
+        match self.file_path.clone() {
+            Some(path) => {
+                self.server_status = match self.save_as(&path) {
+                    Ok(msg) => msg,
+                    Err(e) => format!("Error: {}", e),
+                }
+            }
+            None => self.open_save_as(),
+        }
+    }
+
+    pub fn open_save_as(&mut self) {
+        let path = match &self.file_path {
+            Some(path) => path.clone(),
+            None => format!("{}/", self.config.drafts.roots[0].trim_end_matches('/')),
+        };
+        self.prompt = Some(format!("save-as {}", path));
+    }
+
+    // the file is written by `run_effects`
+    pub fn save_as(&mut self, path: &str) -> Result<String, String> {
+        self.effects.push(Effect::Save(path.to_string()));
+        Ok(format!("Saving {}", path))
+    }
+
+    pub fn write_save(&mut self, path: &str) -> Result<String, String> {
+        convert::write_file(path, &self.data, &WriteOptions::default(), DEFAULT_BACKUPS)?;
+        self.saved = self.data.clone();
+        // the journal of an untitled draft goes as well once it has a name
+        let previous = self.file_path.replace(path.to_string());
+        self.clear_journal(previous.as_deref());
+        self.clear_journal(Some(path));
+
+        // a new draft shows up in the selector right away
+        let dir = Path::new(path).parent().unwrap_or(Path::new(""));
+        if self.config.drafts.roots.iter().any(|r| Path::new(r) == dir)
+            && !self.drafts.iter().any(|d| d == path)
+        {
+            self.drafts.push(path.to_string());
+            self.drafts.sort();
+        }
+        Ok(format!("Saved {}", path))
+    }
+
+    // unsaved changes need a second `q`
+    pub fn confirm_quit(&mut self) -> bool {
+        if !self.is_dirty() || self.quit_armed {
+            return true;
+        }
+        self.quit_armed = true;
+        self.server_status = "Unsaved changes, [S]ave or press [Q] again to quit".to_string();
+        false
+    }
+}
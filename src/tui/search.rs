@@ -0,0 +1,409 @@
+use std::ops::Range;
+
+use regex::Regex;
+
+use crate::model::{TextSegment, parse_time};
+use crate::tui::action::Action;
+use crate::tui::app::App;
+
+#[derive(Default)]
+pub struct Search {
+    // the last query, its matches stay highlighted until esc
+    pub query: String,
+    // the cursor line when `/` was pressed, cancelling goes back there
+    origin: Option<usize>,
+}
+
+impl Search {
+    // char ranges of `text` matching the query, ignoring case
+    pub fn find(&self, text: &str) -> Vec<Range<usize>> {
+        let fold = |c: char| c.to_lowercase().next().unwrap_or(c);
+        let query: Vec<char> = self.query.chars().map(fold).collect();
+        let text: Vec<char> = text.chars().map(fold).collect();
+        let mut found = Vec::new();
+        if query.is_empty() {
+            return found;
+        }
+        let mut i = 0;
+        while i + query.len() <= text.len() {
+            if text[i..i + query.len()] == query[..] {
+                found.push(i..i + query.len());
+                i += query.len();
+            } else {
+                i += 1;
+            }
+        }
+        found
+    }
+}
+
+// compiled once per frame, a pattern that does not parse yet shows nothing
+pub enum Highlighter<'a> {
+    Search(&'a Search),
+    Replace(Option<Regex>),
+}
+
+impl Highlighter<'_> {
+    pub fn find(&self, text: &str) -> Vec<Range<usize>> {
+        match self {
+            Highlighter::Search(search) => search.find(text),
+            Highlighter::Replace(pattern) => pattern
+                .as_ref()
+                .map_or(Vec::new(), |pattern| char_ranges(text, pattern)),
+        }
+    }
+}
+
+// `line` with every match of `pattern` replaced, None if nothing changes.
+// readings close up around what was taken out like they do when typing, and
+// keyframes move along with the text after each match
+fn replace_line(line: &TextSegment, pattern: &Regex, replacement: &str) -> Option<TextSegment> {
+    let text = &line.text;
+    // byte ranges of each match, in the old and in the new text
+    let mut edits = Vec::new();
+    let mut new = String::new();
+    let mut last = 0;
+    for caps in pattern.captures_iter(text) {
+        let m = caps.get(0)?;
+        new.push_str(&text[last..m.start()]);
+        let at = new.len();
+        caps.expand(replacement, &mut new);
+        edits.push((m.range(), at..new.len()));
+        last = m.end();
+    }
+    new.push_str(&text[last..]);
+    if new == *text {
+        return None;
+    }
+
+    let mut line = line.clone();
+    for (old, replaced) in edits.iter().rev() {
+        let at = text[..old.start].chars().count();
+        line.ruby_removed(at..at + text[old.clone()].chars().count());
+        line.ruby_inserted(at, new[replaced.clone()].chars().count());
+    }
+    let map = |i: f32| {
+        let mut shift = 0.0;
+        for (old, replaced) in &edits {
+            if i <= old.start as f32 {
+                break;
+            }
+            if i < old.end as f32 {
+                return (replaced.start as f32 + i - old.start as f32).min(replaced.end as f32);
+            }
+            shift = replaced.end as f32 - old.end as f32;
+        }
+        i + shift
+    };
+    for kf in &mut line.keyframes {
+        kf.index = map(kf.index);
+    }
+    line.text = new;
+    Some(line)
+}
+
+// "s/pattern/replacement/", a `\/` stands for a slash
+fn parse_replace(input: &str) -> Result<(Regex, String), String> {
+    let body = input
+        .strip_prefix("s/")
+        .ok_or("usage: s/<regex>/<replacement>/")?;
+    let mut fields = vec![String::new()];
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('/') => fields.last_mut().unwrap().push('/'),
+                Some(c) => {
+                    let field = fields.last_mut().unwrap();
+                    field.push('\\');
+                    field.push(c);
+                }
+                None => fields.last_mut().unwrap().push('\\'),
+            },
+            '/' => fields.push(String::new()),
+            c => fields.last_mut().unwrap().push(c),
+        }
+    }
+    if fields.len() > 3 || fields.get(2).is_some_and(|f| !f.is_empty()) {
+        return Err("usage: s/<regex>/<replacement>/".to_string());
+    }
+    let pattern = Regex::new(&fields[0]).map_err(|e| format!("bad regex: {}", e))?;
+    Ok((pattern, fields.get(1).cloned().unwrap_or_default()))
+}
+
+// byte ranges of a regex match to char ranges
+fn char_ranges(text: &str, pattern: &Regex) -> Vec<Range<usize>> {
+    let at = |byte: usize| text[..byte].chars().count();
+    pattern
+        .find_iter(text)
+        .filter(|m| !m.is_empty())
+        .map(|m| at(m.start())..at(m.end()))
+        .collect()
+}
+
+impl App {
+    pub fn open_search(&mut self) {
+        self.search = Search {
+            query: String::new(),
+            origin: Some(self.scroll_offset),
+        };
+        self.prompt = Some("/".to_string());
+    }
+
+    pub fn open_goto(&mut self) {
+        self.prompt = Some("goto ".to_string());
+    }
+
+    // typing in the prompt: a search follows every key
+    pub fn prompt_changed(&mut self) {
+        let Some(origin) = self.search.origin else {
+            return;
+        };
+        match self.prompt.as_deref().and_then(|p| p.strip_prefix('/')) {
+            Some(query) => {
+                self.search.query = query.to_string();
+                self.jump_to_match(origin, 1, false);
+            }
+            None => self.search.query.clear(),
+        }
+    }
+
+    pub fn cancel_search(&mut self) {
+        if let Some(origin) = self.search.origin {
+            self.search = Search::default();
+            self.scroll_offset = origin;
+        }
+    }
+
+    // the line's text or the name of its part has the query in it
+    pub fn line_matches(&self, idx: usize) -> bool {
+        let Some(line) = self.data.lines.get(idx) else {
+            return false;
+        };
+        let part = self.data.part_at(idx).map(|pi| &self.data.parts[pi].name);
+        !self.search.find(&line.text).is_empty()
+            || part.is_some_and(|name| !self.search.find(name).is_empty())
+    }
+
+    // what to highlight in a line: the pending replace while one is typed,
+    // else the search
+    pub fn highlighter(&self) -> Highlighter<'_> {
+        match self.prompt.as_deref().filter(|p| p.starts_with("s/")) {
+            Some(input) => Highlighter::Replace(parse_replace(input).ok().map(|(p, _)| p)),
+            None => Highlighter::Search(&self.search),
+        }
+    }
+
+    // the next matching line from `from` on in `dir`, wrapping around
+    fn jump_to_match(&mut self, from: usize, dir: i32, skip_from: bool) -> bool {
+        let len = self.data.lines.len();
+        let skip = usize::from(skip_from);
+        for step in skip..len + skip {
+            let idx = (from as i64 + dir as i64 * step as i64).rem_euclid(len as i64) as usize;
+            if self.line_matches(idx) {
+                self.scroll_offset = idx;
+                self.manual_scroll = true;
+                return true;
+            }
+        }
+        false
+    }
+
+    pub fn handle_search_action(&mut self, action: Action) {
+        match action {
+            Action::Search => self.open_search(),
+            Action::GoTo => self.open_goto(),
+            Action::NextMatch | Action::PrevMatch => {
+                let dir = if action == Action::NextMatch { 1 } else { -1 };
+                if self.search.query.is_empty() {
+                    self.server_status = "Nothing searched yet".to_string();
+                } else if self.jump_to_match(self.scroll_offset, dir, true) {
+                    self.server_status = self.match_count();
+                } else {
+                    self.server_status = format!("No line matches '{}'", self.search.query);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn match_count(&self) -> String {
+        let matches: Vec<usize> = (0..self.data.lines.len())
+            .filter(|&i| self.line_matches(i))
+            .collect();
+        match matches.iter().position(|&i| i == self.scroll_offset) {
+            Some(n) => format!(
+                "Match {} of {} for '{}'",
+                n + 1,
+                matches.len(),
+                self.search.query
+            ),
+            None => format!("{} lines match '{}'", matches.len(), self.search.query),
+        }
+    }
+
+    // shown next to the prompt while typing a search or a replace
+    pub fn prompt_preview(&self) -> Option<String> {
+        let input = self.prompt.as_deref()?;
+        if input.starts_with('/') {
+            return Some(self.match_count());
+        }
+        if !input.starts_with("s/") {
+            return None;
+        }
+        let (pattern, replacement) = match parse_replace(input) {
+            Ok(parsed) => parsed,
+            Err(e) => return Some(e),
+        };
+        let changes = self.replacements(&pattern, &replacement);
+        Some(match changes.first() {
+            Some((idx, new)) => format!(
+                "{} lines change, line {}: {} -> {}",
+                changes.len(),
+                idx + 1,
+                self.data.lines[*idx].text,
+                new.text
+            ),
+            None => "no line changes".to_string(),
+        })
+    }
+
+    // lines the replace would change, as they would be after it
+    fn replacements(&self, pattern: &Regex, replacement: &str) -> Vec<(usize, TextSegment)> {
+        let range = self.marked_lines().unwrap_or(0..self.data.lines.len());
+        range
+            .filter_map(|i| Some((i, replace_line(&self.data.lines[i], pattern, replacement)?)))
+            .collect()
+    }
+
+    // "s/re/replacement/" over the marked lines or the whole document
+    pub fn replace(&mut self, input: &str) -> Result<String, String> {
+        let (pattern, replacement) = parse_replace(input)?;
+        let changes = self.replacements(&pattern, &replacement);
+        for (idx, line) in &changes {
+            self.data.lines[*idx] = line.clone();
+        }
+        for (idx, _) in &changes {
+            self.data.sync_repeats_from(*idx);
+        }
+        Ok(format!("Replaced text in {} lines", changes.len()))
+    }
+
+    // a search submitted from the prompt stays highlighted
+    pub fn finish_search(&mut self, query: &str) -> Result<String, String> {
+        self.search = Search {
+            query: query.to_string(),
+            origin: None,
+        };
+        if query.is_empty() {
+            return Ok(String::new());
+        }
+        if !self.line_matches(self.scroll_offset)
+            && !self.jump_to_match(self.scroll_offset, 1, true)
+        {
+            return Err(format!("No line matches '{}'", query));
+        }
+        Ok(self.match_count())
+    }
+
+    // "1:23.5" or "83.5" seeks there, "#12" to the start of line 12
+    pub fn goto(&mut self, target: &str) -> Result<String, String> {
+        if let Some(n) = target.strip_prefix('#') {
+            let n: usize = n
+                .parse()
+                .map_err(|_| format!("'{}' is not a line number", n))?;
+            let idx = n
+                .checked_sub(1)
+                .filter(|&i| i < self.data.lines.len())
+                .ok_or(format!("There is no line {}", n))?;
+            self.set_time(self.data.lines[idx].start);
+            self.scroll_offset = idx;
+            self.manual_scroll = true;
+            return Ok(format!("Line {}", n));
+        }
+        let time = parse_time(target).ok_or(format!("'{}' is not a time", target))?;
+        self.set_time(time.max(0.0));
+        self.manual_scroll = false;
+        Ok(format!("Went to {:.2}s", time.max(0.0)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_replace, replace_line};
+    use crate::model::AnimationData;
+    use crate::tui::harness::{Harness, sample_document};
+    use crossterm::event::KeyCode;
+
+    #[test]
+    fn test_search_replace_and_goto() {
+        let data = sample_document();
+        let mut h = Harness::new("search", &[("song.txt", &data)]);
+        h.key(KeyCode::Enter);
+
+        // each key moves to the first match from where the search started
+        h.keys("/w");
+        assert_eq!(h.app.scroll_offset, 1);
+        h.keys("o");
+        assert_eq!(h.app.scroll_offset, 3);
+        h.key(KeyCode::Esc);
+        assert_eq!((h.app.scroll_offset, h.app.search.query.as_str()), (0, ""));
+
+        h.keys("/STAR");
+        h.key(KeyCode::Enter);
+        assert_eq!(h.app.scroll_offset, 2);
+        h.keys("/twinkle");
+        h.key(KeyCode::Enter);
+        h.keys("n");
+        assert_eq!(h.app.scroll_offset, 1);
+        assert_eq!(h.app.server_status, "Match 1 of 1 for 'twinkle'");
+
+        // the preview names what changes, enter applies it as one undo step
+        h.keys(":s/(\\w+) (\\w+)$/$2 $1/");
+        assert_eq!(
+            h.app.prompt_preview().unwrap(),
+            "3 lines change, line 2: Twinkle twinkle -> twinkle Twinkle"
+        );
+        h.assert_snapshot("replace_preview");
+        h.key(KeyCode::Enter);
+        assert_eq!(h.app.data.lines[3].text, "how wonder I");
+        h.keys("u");
+        assert_eq!(h.app.data.lines[3].text, "how I wonder");
+
+        h.keys("g1:05.5");
+        h.key(KeyCode::Enter);
+        assert_eq!(h.app.current_time, 65.5);
+        h.keys("g#3");
+        h.key(KeyCode::Enter);
+        assert_eq!((h.app.current_time, h.app.scroll_offset), (4.0, 2));
+        h.keys("g#9");
+        h.key(KeyCode::Enter);
+        assert_eq!(h.app.server_status, "Error: There is no line 9");
+    }
+
+    #[test]
+    fn test_replace_keeps_ruby_and_keyframes() {
+        let mut data = AnimationData::default();
+        let line = data.add_line("今日は晴れ", 0.0, 4.0);
+        line.add_ruby(0, 2, "きょう");
+        line.add_ruby(3, 5, "はれ");
+        line.add_keyframe(0.0, 0.0)
+            .add_keyframe(2.0, 9.0)
+            .add_keyframe(4.0, 15.0);
+
+        // は (byte 6..9) becomes から, what follows moves by three bytes and a char
+        let (pattern, replacement) = parse_replace("s/は/から/").unwrap();
+        let line = replace_line(&data.lines[0], &pattern, &replacement).unwrap();
+        assert_eq!(line.text, "今日から晴れ");
+        assert_eq!((line.ruby[0].start, line.ruby[0].end), (0, 2));
+        assert_eq!((line.ruby[1].start, line.ruby[1].end), (4, 6));
+        let indices: Vec<f32> = line.keyframes.iter().map(|k| k.index).collect();
+        assert_eq!(indices, [0.0, 12.0, 18.0]);
+
+        // a reading whose base text is replaced goes with it
+        let (pattern, replacement) = parse_replace("s/今日/昨日/").unwrap();
+        let line = replace_line(&line, &pattern, &replacement).unwrap();
+        assert_eq!(line.ruby.len(), 1);
+        assert_eq!(line.ruby[0].text, "はれ");
+    }
+}
@@ -0,0 +1,190 @@
+use std::ops::Range;
+
+use crate::model::AnimationData;
+use crate::tui::action::Action;
+use crate::tui::app::App;
+use crate::tui::effect::Effect;
+use crate::tui::types::ViewMode;
+
+impl App {
+    // the line list and text mode point at
+    pub fn cursor_line(&self) -> usize {
+        if self.view_mode == ViewMode::TextEdit {
+            self.focus_line_index.unwrap_or(self.scroll_offset)
+        } else {
+            self.scroll_offset
+        }
+    }
+
+    // from where the selection was started to the cursor, both included
+    pub fn marked_lines(&self) -> Option<Range<usize>> {
+        let anchor = self.selection?;
+        let cursor = self.cursor_line();
+        let end = (anchor.max(cursor) + 1).min(self.data.lines.len());
+        Some(anchor.min(cursor).min(end)..end)
+    }
+
+    // what batch commands act on: the marked lines, else the one at the cursor
+    pub fn target_lines(&self) -> Range<usize> {
+        self.marked_lines().unwrap_or_else(|| {
+            let idx = self.cursor_line();
+            idx.min(self.data.lines.len())..(idx + 1).min(self.data.lines.len())
+        })
+    }
+
+    pub fn handle_selection_action(&mut self, action: Action) {
+        match action {
+            Action::SelectUp | Action::SelectDown => {
+                let up = action == Action::SelectUp;
+                self.selection.get_or_insert(self.cursor_line());
+                if self.view_mode == ViewMode::TextEdit {
+                    self.text_action(if up {
+                        Action::CursorUp
+                    } else {
+                        Action::CursorDown
+                    });
+                } else {
+                    self.manual_scroll = true;
+                    if up {
+                        self.scroll_offset = self.scroll_offset.saturating_sub(1);
+                    } else if self.scroll_offset + 1 < self.data.lines.len() {
+                        self.scroll_offset += 1;
+                    }
+                }
+            }
+            Action::DeleteLines => {
+                let range = self.target_lines();
+                self.server_status = match self.delete_lines(range) {
+                    Ok(msg) => msg,
+                    Err(e) => format!("Error: {}", e),
+                };
+            }
+            Action::CopyLines => {
+                self.server_status = match self.copy_lines() {
+                    Ok(msg) => msg,
+                    Err(e) => format!("Copy err: {}", e),
+                };
+            }
+            Action::InsertLines(text) => {
+                self.server_status = match self.insert_lines(&text) {
+                    Ok(msg) => msg,
+                    Err(e) => format!("Paste err: {}", e),
+                };
+            }
+            _ => {}
+        }
+    }
+
+    pub fn delete_lines(&mut self, range: Range<usize>) -> Result<String, String> {
+        self.data.delete_lines(range.clone())?;
+        self.selection = None;
+        self.scroll_offset = range.start;
+        self.focus_line_index = self.focus_line_index.map(|_| range.start);
+        Ok(format!("Deleted {} lines", range.len()))
+    }
+
+    // the lines with their timing, in the draft format so a paste gets them back
+    fn copy_lines(&mut self) -> Result<String, String> {
+        let range = self.target_lines();
+        if range.is_empty() {
+            return Err("no lines to copy".to_string());
+        }
+        let copy = AnimationData {
+            lines: self.data.lines[range.clone()].to_vec(),
+            ..Default::default()
+        };
+        self.effects.push(Effect::Copy {
+            text: copy.compile(),
+            done: format!("Copied {} lines", range.len()),
+        });
+        Ok(format!("Copying {} lines", range.len()))
+    }
+
+    // copied lines go in by time, moved so the first one starts at the playhead
+    fn insert_lines(&mut self, text: &str) -> Result<String, String> {
+        let pasted: AnimationData = text
+            .parse()
+            .map_err(|_| "the clipboard holds no copied lines".to_string())?;
+        let Some(first) = pasted.lines.first() else {
+            return Err("the clipboard holds no copied lines".to_string());
+        };
+        let offset = self.current_time - first.start;
+        let at = self
+            .data
+            .lines
+            .iter()
+            .position(|l| l.start > self.current_time)
+            .unwrap_or(self.data.lines.len());
+        let count = pasted.lines.len();
+        for (k, mut line) in pasted.lines.into_iter().enumerate() {
+            line.start = (line.start + offset).max(0.0);
+            line.end = (line.end + offset).max(line.start);
+            self.data.insert_line(at + k, line);
+        }
+        self.data.sync_repeats();
+        self.manual_scroll = true;
+        self.selection = Some(at);
+        self.scroll_offset = at + count - 1;
+        Ok(format!(
+            "Pasted {} lines at {:.2}s",
+            count, self.current_time
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tui::Action;
+    use crate::tui::harness::{Harness, sample_document};
+    use crossterm::event::{KeyCode, KeyModifiers};
+
+    #[test]
+    fn test_select_and_batch_edit_lines() {
+        let data = sample_document();
+        let mut h = Harness::new("selection", &[("song.txt", &data)]);
+        h.key(KeyCode::Enter);
+
+        // mark "Twinkle" and "little star"
+        h.key(KeyCode::Down);
+        h.chord(KeyCode::Down, KeyModifiers::SHIFT);
+        assert_eq!(h.app.marked_lines(), Some(1..3));
+        h.assert_snapshot("selection");
+
+        h.app.apply(Action::Run("part Chorus".to_string()));
+        h.app.apply(Action::Run("agent v2".to_string()));
+        h.app.apply(Action::Run("distribute 0 4".to_string()));
+        assert_eq!((h.app.data.parts[0].start, h.app.data.parts[0].end), (1, 3));
+        assert_eq!(h.app.data.lines[2].agent.as_deref(), Some("v2"));
+        assert_eq!(h.app.data.lines[1].agent.as_deref(), Some("v2"));
+        assert_eq!(
+            (h.app.data.lines[2].start, h.app.data.lines[2].end),
+            (2.0, 4.0)
+        );
+        h.app.apply(Action::Run("clear-keyframes".to_string()));
+        assert!(h.app.data.lines[2].keyframes.is_empty());
+
+        // copying goes through the clipboard, pasting its text back duplicates
+        let copy = crate::model::AnimationData {
+            lines: h.app.data.lines[1..3].to_vec(),
+            ..Default::default()
+        };
+        h.app.apply(Action::Seek(20.0));
+        h.app.apply(Action::InsertLines(copy.compile()));
+        assert_eq!(h.app.data.lines.len(), 6);
+        assert_eq!(h.app.data.lines[4].text, "Twinkle twinkle");
+        assert_eq!(
+            (h.app.data.lines[5].start, h.app.data.lines[5].end),
+            (22.0, 24.0)
+        );
+        assert_eq!(h.app.marked_lines(), Some(4..6));
+
+        // a plain move drops the selection, delete then takes the cursor line
+        h.key(KeyCode::Up);
+        assert_eq!(h.app.marked_lines(), None);
+        h.key(KeyCode::Delete);
+        assert_eq!(h.app.data.lines.len(), 5);
+        assert_eq!(h.app.data.lines[3].text, "how I wonder");
+        h.keys("u");
+        assert_eq!(h.app.data.lines.len(), 6);
+    }
+}
@@ -0,0 +1,442 @@
+use std::time::{Duration, Instant};
+
+use crate::model::{Keyframe, Split, TextSegment, byte_offset, split_points};
+use crate::tui::action::Action;
+use crate::tui::app::App;
+use crate::tui::types::ViewMode;
+
+// a key held at least this long stamps the line end on release, a shorter tap
+// leaves the end to the next one
+const HOLD: Duration = Duration::from_millis(300);
+
+// lines `at..` and the tap state as they were before a stamp, for the back key
+struct Stamp {
+    at: usize,
+    before: Vec<TextSegment>,
+    next: usize,
+    last: Option<usize>,
+    ended: Option<usize>,
+}
+
+#[derive(Default)]
+pub struct TapSync {
+    // the line the next tap starts
+    pub next: usize,
+    // the last tapped line, its end is stamped by the next tap
+    last: Option<usize>,
+    // a line whose end was stamped by releasing the key
+    ended: Option<usize>,
+    // the line started by the key still down, and since when
+    pressed: Option<(usize, Instant)>,
+    stamps: Vec<Stamp>,
+}
+
+impl TapSync {
+    // stamps from before the document changed under them can't be taken back
+    pub fn keep_in_bounds(&mut self, len: usize) {
+        self.next = self.next.min(len);
+        self.last = self.last.filter(|&i| i < self.next);
+        self.ended = self.ended.filter(|&i| i < len);
+        self.pressed = self.pressed.filter(|(i, _)| *i < len);
+        self.stamps.retain(|s| s.at + s.before.len() <= len);
+    }
+}
+
+// taps keyframes into the words or syllables of one line after another
+pub struct WordSync {
+    pub line: usize,
+    pub split: Split,
+    // char indexes the units of `line` start at, and the one tapped next
+    pub points: Vec<usize>,
+    pub next: usize,
+    // the line as it was and where the taps were, before each tap
+    stamps: Vec<(usize, TextSegment, usize)>,
+}
+
+impl Default for WordSync {
+    fn default() -> Self {
+        WordSync {
+            line: 0,
+            split: Split::Syllables,
+            points: Vec::new(),
+            next: 0,
+            stamps: Vec::new(),
+        }
+    }
+}
+
+impl WordSync {
+    // false once the line is gone
+    pub fn keep_in_bounds(&mut self, len: usize) -> bool {
+        self.stamps.retain(|(line, _, _)| *line < len);
+        self.line < len
+    }
+}
+
+impl App {
+    pub fn handle_tap_action(&mut self, action: Action) {
+        let words = self.view_mode == ViewMode::WordSync;
+        match action {
+            Action::TapSync => self.start_tap_sync(),
+            Action::WordSync => self.start_word_sync(),
+            Action::ToggleSplit if words => {
+                self.words.split = match self.words.split {
+                    Split::Words => Split::Syllables,
+                    Split::Syllables => Split::Words,
+                };
+                // untapped lines only, a half tapped one keeps its units
+                if self.words.next == 0 {
+                    self.words_on_line(self.words.line);
+                }
+            }
+            Action::Tap if words => self.tap_word(),
+            Action::UndoTap if words => self.undo_word_tap(),
+            Action::Tap => self.tap(),
+            Action::TapRelease if !words => self.tap_release(),
+            Action::UndoTap => self.undo_tap(),
+            _ => {}
+        }
+    }
+
+    fn start_tap_sync(&mut self) {
+        self.tap = TapSync {
+            next: self.next_tap_line(self.scroll_offset),
+            ..TapSync::default()
+        };
+        self.view_mode = ViewMode::TapSync;
+        self.manual_scroll = false;
+        self.focus_line_index = None;
+        if !self.is_playing {
+            self.is_playing = true;
+            self.send_remote("play".to_string());
+        }
+        self.server_status = "Tap along, each tap starts the next line".to_string();
+    }
+
+    pub fn stop_tap_sync(&mut self) {
+        if self.is_playing {
+            self.is_playing = false;
+            self.send_remote("pause".to_string());
+        }
+        self.server_status = format!("Tap sync done, {} stamps", self.tap.stamps.len());
+    }
+
+    // blank lines are gaps, not something to tap for
+    fn next_tap_line(&self, from: usize) -> usize {
+        (from..self.data.lines.len())
+            .find(|&i| !self.data.lines[i].text.trim().is_empty())
+            .unwrap_or(self.data.lines.len())
+    }
+
+    fn tap(&mut self) {
+        let next = self.tap.next;
+        if next >= self.data.lines.len() {
+            self.server_status = "Every line is stamped, [BACKSPACE] to take one back".to_string();
+            return;
+        }
+        let t = self.current_time;
+        let at = self.tap.last.unwrap_or(next);
+        self.push_stamp(at, next + 1);
+
+        // the previous line ends here unless its release did that already,
+        // blank lines in between fill the gap
+        if let Some(last) = self.tap.last {
+            if self.tap.ended != Some(last) {
+                self.data.lines[last].end = t;
+            }
+            let gap_start = self.data.lines[last].end.min(t);
+            for line in &mut self.data.lines[last + 1..next] {
+                line.start = gap_start;
+                line.end = t;
+            }
+            self.data.sync_repeats_from(last);
+        }
+
+        let line = &mut self.data.lines[next];
+        let duration = (line.end - line.start).max(0.0);
+        line.start = t;
+        line.end = t + duration;
+        self.data.sync_repeats_from(next);
+
+        self.tap.last = Some(next);
+        self.tap.ended = None;
+        self.tap.pressed = Some((next, self.clock.now()));
+        self.tap.next = self.next_tap_line(next + 1);
+        self.scroll_offset = next;
+    }
+
+    fn tap_release(&mut self) {
+        let Some((line, since)) = self.tap.pressed.take() else {
+            return;
+        };
+        if self.clock.now().duration_since(since) < HOLD || line >= self.data.lines.len() {
+            return;
+        }
+        self.push_stamp(line, line + 1);
+        let end = self.current_time.max(self.data.lines[line].start);
+        self.data.lines[line].end = end;
+        self.data.sync_repeats_from(line);
+        self.tap.ended = Some(line);
+    }
+
+    fn push_stamp(&mut self, from: usize, to: usize) {
+        self.tap.stamps.push(Stamp {
+            at: from,
+            before: self.data.lines[from..to].to_vec(),
+            next: self.tap.next,
+            last: self.tap.last,
+            ended: self.tap.ended,
+        });
+    }
+
+    // playback goes back to the line before, to tap the next one again
+    fn undo_tap(&mut self) {
+        let Some(stamp) = self.tap.stamps.pop() else {
+            self.server_status = "Nothing to take back".to_string();
+            return;
+        };
+        let end = (stamp.at + stamp.before.len()).min(self.data.lines.len());
+        self.data.lines.splice(stamp.at..end, stamp.before);
+        self.data.sync_repeats_from(stamp.at);
+
+        self.tap.next = stamp.next;
+        self.tap.last = stamp.last;
+        self.tap.ended = stamp.ended;
+        self.tap.pressed = None;
+        if let Some(line) = self.tap.last.and_then(|i| self.data.lines.get(i)) {
+            self.set_time(line.start.max(0.0));
+        }
+        self.scroll_offset = self.tap.next;
+        self.server_status = "Took back the last stamp".to_string();
+    }
+}
+
+impl App {
+    fn start_word_sync(&mut self) {
+        let split = self.words.split;
+        let from = self.focus_line_index.or(self.get_active_line_index());
+        let Some(line) = from.and_then(|from| self.next_word_line(from, split)) else {
+            self.server_status = "No line to tap words into".to_string();
+            return;
+        };
+        self.words = WordSync {
+            split,
+            ..WordSync::default()
+        };
+        self.words_on_line(line);
+        self.view_mode = ViewMode::WordSync;
+        self.active_kf_index = None;
+        // a second of lead-in to catch the first word
+        self.set_time((self.data.lines[line].start - 1.0).max(0.0));
+        if !self.is_playing {
+            self.is_playing = true;
+            self.send_remote("play".to_string());
+        }
+    }
+
+    pub fn stop_word_sync(&mut self) {
+        if self.is_playing {
+            self.is_playing = false;
+            self.send_remote("pause".to_string());
+        }
+        self.focus_line_index = Some(self.words.line);
+        self.server_status = format!("Word sync done, {} taps", self.words.stamps.len());
+    }
+
+    fn next_word_line(&self, from: usize, split: Split) -> Option<usize> {
+        (from..self.data.lines.len())
+            .find(|&i| !split_points(&self.data.lines[i].text, split).is_empty())
+    }
+
+    fn words_on_line(&mut self, line: usize) {
+        self.words.line = line;
+        self.words.points = split_points(&self.data.lines[line].text, self.words.split);
+        self.words.next = 0;
+        self.focus_line_index = Some(line);
+    }
+
+    fn tap_word(&mut self) {
+        if self.words.next >= self.words.points.len() {
+            self.server_status = "Every line is tapped, [BACKSPACE] to take one back".to_string();
+            return;
+        }
+        let idx = self.words.line;
+        self.words
+            .stamps
+            .push((idx, self.data.lines[idx].clone(), self.words.next));
+        let t = self.current_time;
+        let line = &mut self.data.lines[idx];
+
+        // the first tap replaces whatever keyframes the line had, and pulls
+        // its start in when the line was sung early. nothing is lit until then
+        let time = (t - line.start).max(0.0);
+        if self.words.next == 0 {
+            line.keyframes.clear();
+            if t < line.start {
+                line.start = t;
+            }
+            if time > 0.0 {
+                line.keyframes.push(Keyframe {
+                    time: 0.0,
+                    index: 0.0,
+                });
+            }
+        }
+        line.keyframes.push(Keyframe {
+            time,
+            index: byte_offset(&line.text, self.words.points[self.words.next]) as f32,
+        });
+        self.words.next += 1;
+        if self.words.next < self.words.points.len() {
+            self.data.sync_repeats_from(idx);
+            return;
+        }
+
+        // the last unit lasts until the line ends
+        let len = line.text.len() as f32;
+        let end = (line.end - line.start).max(time + 0.01);
+        line.end = line.start + end;
+        line.keyframes.push(Keyframe {
+            time: end,
+            index: len,
+        });
+        line.sort_keyframes();
+        self.data.sync_repeats_from(idx);
+
+        match self.next_word_line(idx + 1, self.words.split) {
+            Some(next) => {
+                self.words_on_line(next);
+                self.server_status = format!("Line {} tapped", idx + 1);
+            }
+            None => {
+                self.server_status =
+                    "Every line is tapped, [BACKSPACE] to take one back".to_string();
+            }
+        }
+    }
+
+    fn undo_word_tap(&mut self) {
+        let Some((idx, before, next)) = self.words.stamps.pop() else {
+            self.server_status = "Nothing to take back".to_string();
+            return;
+        };
+        let time = before.start + before.keyframes.last().map_or(0.0, |k| k.time);
+        self.data.lines[idx] = before;
+        self.data.sync_repeats_from(idx);
+        if idx != self.words.line {
+            self.words_on_line(idx);
+        }
+        self.words.next = next;
+        // back to the unit before, to tap the next one again
+        self.set_time(if next == 0 {
+            (self.data.lines[idx].start - 1.0).max(0.0)
+        } else {
+            time
+        });
+        self.server_status = "Took back the last tap".to_string();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::AnimationData;
+    use crate::tui::harness::{Harness, sample_document};
+    use crate::tui::{Action, ViewMode};
+    use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+
+    #[test]
+    fn test_tap_sync_stamps_lines() {
+        let data = sample_document();
+        let mut h = Harness::new("tap", &[("song.txt", &data)]);
+        h.key(KeyCode::Enter);
+        h.keys("t");
+        assert!(h.app.is_playing);
+
+        let space = KeyEvent::new(KeyCode::Char(' '), KeyModifiers::NONE);
+        let mut release = space;
+        release.kind = KeyEventKind::Release;
+
+        // a quick tap, then one held for a second
+        h.advance(2.0);
+        h.app.handle_key(space);
+        h.app.handle_key(release);
+        h.advance(1.5);
+        h.app.handle_key(space);
+        h.advance(1.0);
+        h.app.handle_key(release);
+        h.advance(1.0);
+        h.app.handle_key(space);
+
+        // the blank line the document opens with is skipped
+        let times: Vec<(f32, f32)> = h.app.data.lines.iter().map(|l| (l.start, l.end)).collect();
+        assert_eq!(times, [(0.0, 0.0), (2.0, 3.5), (3.5, 4.5), (5.5, 8.5)]);
+
+        // back twice: the last tap, then the held line's end
+        h.app.apply(Action::UndoTap);
+        h.app.apply(Action::UndoTap);
+        assert_eq!(h.app.data.lines[2].end, 6.5);
+        assert_eq!(h.app.data.lines[3].start, 7.0);
+        assert_eq!(h.app.tap.next, 3);
+        h.advance(0.5);
+        h.app.handle_key(space);
+        assert_eq!(h.app.data.lines[2].end, 4.0);
+        assert_eq!(h.app.data.lines[3].start, 4.0);
+    }
+
+    #[test]
+    fn test_word_sync_taps_keyframes() {
+        let data = sample_document();
+        let mut h = Harness::new("words", &[("song.txt", &data)]);
+        h.key(KeyCode::Enter);
+        // focus the "little star" line, then tap its three syllables
+        h.keys("jnn");
+        assert_eq!(h.app.focus_line_index, Some(2));
+        h.keys("w");
+        assert_eq!(h.app.words.points, [0, 3, 7]);
+        assert_eq!(h.app.current_time, 3.0);
+
+        for secs in [1.5, 0.5, 1.0] {
+            h.advance(secs);
+            h.keys(" ");
+        }
+        let line = &h.app.data.lines[2];
+        assert_eq!(line.start, 4.0);
+        let keyframes: Vec<(f32, f32)> = line.keyframes.iter().map(|k| (k.time, k.index)).collect();
+        assert_eq!(
+            keyframes,
+            [(0.0, 0.0), (0.5, 0.0), (1.0, 3.0), (2.0, 7.0), (3.0, 11.0)]
+        );
+        // the line is dark until the first tap
+        assert_eq!(line.get_current_index(0.25), 0.0);
+        // on to the next line, whole words this time
+        assert_eq!(h.app.words.line, 3);
+        h.key(KeyCode::Tab);
+        assert_eq!(h.app.words.points, [0, 4, 6]);
+
+        h.key(KeyCode::Backspace);
+        assert_eq!(h.app.words.line, 2);
+        assert_eq!(h.app.words.next, 2);
+        assert_eq!(h.app.data.lines[2].keyframes.len(), 3);
+        h.key(KeyCode::Esc);
+        assert!(h.app.view_mode == ViewMode::Line);
+    }
+
+    #[test]
+    fn test_word_sync_keyframes_count_bytes() {
+        let mut data = AnimationData::new();
+        data.add_line("東京 タワー", 1.0, 4.0);
+        let mut h = Harness::new("words-multibyte", &[("song.txt", &data)]);
+        h.key(KeyCode::Enter);
+        h.keys("jn");
+        assert_eq!(h.app.focus_line_index, Some(1));
+        h.keys("w");
+        for _ in 0..4 {
+            h.advance(0.5);
+            h.keys(" ");
+        }
+        let line = &h.app.data.lines[1];
+        let indexes: Vec<f32> = line.keyframes.iter().map(|k| k.index).collect();
+        assert_eq!(indexes, [0.0, 3.0, 7.0, 10.0, 16.0]);
+        assert_eq!(line.text.len(), 16);
+    }
+}
@@ -0,0 +1,62 @@
+use std::str::FromStr;
+
+use ratatui::style::Color;
+use serde::{Deserialize, Deserializer};
+
+// colors by role, `[theme]` in the config takes names ("light blue"), "#rrggbb" or 0-255
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    // selection, cursor and the border while editing
+    #[serde(deserialize_with = "color")]
+    pub accent: Color,
+    // text drawn on top of `accent`
+    #[serde(deserialize_with = "color")]
+    pub accent_text: Color,
+    #[serde(deserialize_with = "color")]
+    pub text: Color,
+    // timestamps and secondary labels
+    #[serde(deserialize_with = "color")]
+    pub dim: Color,
+    // lines away from the playhead
+    #[serde(deserialize_with = "color")]
+    pub muted: Color,
+    #[serde(deserialize_with = "color")]
+    pub playing: Color,
+    // the keyframe at the playhead, hints and the paused border
+    #[serde(deserialize_with = "color")]
+    pub highlight: Color,
+    #[serde(deserialize_with = "color")]
+    pub warning: Color,
+    #[serde(deserialize_with = "color")]
+    pub error: Color,
+    // review markers and recovery diffs
+    #[serde(deserialize_with = "color")]
+    pub info: Color,
+    #[serde(deserialize_with = "color")]
+    pub part: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            accent: Color::Blue,
+            accent_text: Color::White,
+            text: Color::White,
+            dim: Color::Gray,
+            muted: Color::DarkGray,
+            playing: Color::Green,
+            highlight: Color::Yellow,
+            warning: Color::Yellow,
+            error: Color::Red,
+            info: Color::Cyan,
+            part: Color::Magenta,
+        }
+    }
+}
+
+fn color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    Color::from_str(&name)
+        .map_err(|_| serde::de::Error::custom(format!("unknown color '{}'", name)))
+}
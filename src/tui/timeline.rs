@@ -0,0 +1,442 @@
+use std::rc::Rc;
+
+use ratatui::{
+    Frame,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::Style,
+    text::{Line as TuiLine, Span},
+    widgets::{Block, Borders, Paragraph},
+};
+
+use crate::tui::action::Action;
+use crate::tui::app::App;
+use crate::tui::edit_line::UI;
+use crate::tui::types::ViewMode;
+
+// columns per second, from a whole song on screen down to single syllables
+const ZOOMS: &[f32] = &[0.5, 1.0, 2.0, 4.0, 8.0, 16.0, 32.0];
+// an edge this many columns from a neighbouring line's edge lands on it
+const SNAP_COLUMNS: f32 = 2.0;
+const PAN_COLUMNS: f32 = 20.0;
+
+// the time axis of the timeline view. it follows the playhead until the
+// selection is pinned (`manual_scroll`), from then on it stays at `center`
+pub struct Timeline {
+    zoom: usize,
+    pub center: f32,
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Timeline {
+            zoom: 3,
+            center: 0.0,
+        }
+    }
+}
+
+impl Timeline {
+    pub fn columns_per_sec(&self) -> f32 {
+        ZOOMS[self.zoom]
+    }
+}
+
+// where an edge moving from `from` to `to` ends up next to `target`: on it,
+// when heading for it and it's close or passed. one resting on `target` moves off
+fn snap(from: f32, to: f32, target: f32, within: f32) -> Option<f32> {
+    let heading = (target - from) * (to - from) > 0.0;
+    let passed = (to - target) * (from - target) < 0.0;
+    (heading && (passed || (to - target).abs() <= within)).then_some(target)
+}
+
+impl App {
+    pub fn handle_timeline_action(&mut self, action: Action) {
+        match action {
+            Action::Timeline => {
+                self.manual_scroll = false;
+                self.focus_line_index = None;
+                self.active_kf_index = None;
+                self.view_mode = ViewMode::Timeline;
+            }
+            Action::ZoomIn => self.timeline.zoom = (self.timeline.zoom + 1).min(ZOOMS.len() - 1),
+            Action::ZoomOut => self.timeline.zoom = self.timeline.zoom.saturating_sub(1),
+            Action::PanLeft | Action::PanRight | Action::Pan(_) => {
+                self.pin_timeline();
+                let columns = match action {
+                    Action::PanLeft => -PAN_COLUMNS,
+                    Action::PanRight => PAN_COLUMNS,
+                    Action::Pan(columns) => columns as f32,
+                    _ => 0.0,
+                };
+                let pan = columns / self.timeline.columns_per_sec();
+                self.timeline.center = (self.timeline.center + pan).max(0.0);
+            }
+            Action::FollowPlayhead => self.manual_scroll = false,
+            Action::ScrollUp | Action::ScrollDown => {
+                if action == Action::ScrollUp {
+                    self.scroll_offset = self.scroll_offset.saturating_sub(1);
+                } else if self.scroll_offset + 1 < self.data.lines.len() {
+                    self.scroll_offset += 1;
+                }
+                self.manual_scroll = true;
+                if let Some(line) = self.data.lines.get(self.scroll_offset) {
+                    self.timeline.center = (line.start + line.end) / 2.0;
+                }
+            }
+            _ => self.trim_selected(action),
+        }
+    }
+
+    // stops following the playhead, where it is now
+    fn pin_timeline(&mut self) {
+        if !self.manual_scroll {
+            self.manual_scroll = true;
+            self.timeline.center = self.current_time;
+        }
+    }
+
+    // moves or trims the selected line by one column
+    fn trim_selected(&mut self, action: Action) {
+        let idx = self.scroll_offset;
+        let Some((start, end)) = self.data.lines.get(idx).map(|l| (l.start, l.end)) else {
+            return;
+        };
+        self.pin_timeline();
+        let step = 1.0 / self.timeline.columns_per_sec();
+        let within = SNAP_COLUMNS * step;
+        let prev_end = idx.checked_sub(1).map(|i| self.data.lines[i].end);
+        let next_start = self.data.lines.get(idx + 1).map(|l| l.start);
+        let snap_to = |from: f32, to: f32, target: Option<f32>| {
+            target.and_then(|t| snap(from, to, t, within))
+        };
+
+        let mut snapped = None;
+        match action {
+            Action::MoveEarlier | Action::MoveLater => {
+                let delta = if action == Action::MoveEarlier {
+                    (-step).max(-start)
+                } else {
+                    step
+                };
+                // whichever edge lands on its neighbour sooner
+                let snaps = [
+                    snap_to(start, start + delta, prev_end)
+                        .map(|t| (t - start, idx.wrapping_sub(1))),
+                    snap_to(end, end + delta, next_start).map(|t| (t - end, idx + 1)),
+                ];
+                let delta = match snaps
+                    .into_iter()
+                    .flatten()
+                    .min_by(|a, b| a.0.abs().total_cmp(&b.0.abs()))
+                {
+                    Some((to, other)) => {
+                        snapped = Some(other);
+                        to
+                    }
+                    None => delta,
+                };
+                let line = &mut self.data.lines[idx];
+                line.start += delta;
+                line.end += delta;
+                self.data.sync_repeats_from(idx);
+            }
+            Action::StartEarlier | Action::StartLater => {
+                let to = if action == Action::StartEarlier {
+                    start - step
+                } else {
+                    (start + step).min(end)
+                };
+                let to = snap_to(start, to, prev_end)
+                    .inspect(|_| snapped = Some(idx - 1))
+                    .unwrap_or(to);
+                self.data.set_line_bounds(idx, to, end);
+            }
+            Action::EndEarlier | Action::EndLater => {
+                let to = if action == Action::EndEarlier {
+                    (end - step).max(start)
+                } else {
+                    end + step
+                };
+                let to = snap_to(end, to, next_start)
+                    .inspect(|_| snapped = Some(idx + 1))
+                    .unwrap_or(to);
+                self.data.set_line_bounds(idx, start, to);
+            }
+            _ => return,
+        }
+
+        let line = &self.data.lines[idx];
+        self.server_status = format!("Line {}: {:.2}s - {:.2}s", idx + 1, line.start, line.end);
+        if let Some(other) = snapped {
+            self.server_status += &format!(", snapped to line {}", other + 1);
+        }
+    }
+}
+
+// one terminal cell: a symbol, "" behind a wide one
+type Cell = (String, Style);
+
+fn put(row: &mut [Cell], col: i32, symbol: &str, style: Style) {
+    let width = Span::raw(symbol).width().max(1) as i32;
+    if col < 0 || col + width > row.len() as i32 {
+        return;
+    }
+    let col = col as usize;
+    // whatever was half overwritten becomes blank
+    if row[col].0.is_empty() && col > 0 {
+        row[col - 1].0 = " ".to_string();
+    }
+    let last = col + width as usize - 1;
+    if last + 1 < row.len() && row[last + 1].0.is_empty() {
+        row[last + 1].0 = " ".to_string();
+    }
+    row[col] = (symbol.to_string(), style);
+    for cell in &mut row[col + 1..=last] {
+        *cell = (String::new(), style);
+    }
+}
+
+fn ruler_label(t: f32, interval: f32) -> String {
+    if interval < 1.0 {
+        format!("{:.1}", t)
+    } else {
+        let secs = t.round() as u32;
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+impl UI {
+    // the time axis, and the selected line's details below it
+    pub fn timeline_chunks(area: Rect) -> Rc<[Rect]> {
+        Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(7), Constraint::Min(0)])
+            .split(area)
+    }
+
+    pub fn render_timeline(f: &mut Frame, app: &App, area: Rect) {
+        let theme = &app.config.theme;
+        let chunks = Self::timeline_chunks(area);
+
+        let width = chunks[0].width.saturating_sub(2) as usize;
+        let cps = app.timeline.columns_per_sec();
+        let center = if app.manual_scroll {
+            app.timeline.center
+        } else {
+            app.current_time
+        };
+        let left = center - width as f32 / 2.0 / cps;
+        let col = |t: f32| ((t - left) * cps).floor() as i32;
+        let time_at = |c: usize| left + c as f32 / cps;
+
+        // ruler, then the blocks and keyframe ticks of two lanes, so that
+        // touching lines stay apart
+        let blank = (" ".to_string(), Style::default());
+        let mut rows = vec![vec![blank; width]; 5];
+        let dim = Style::default().fg(theme.dim);
+        for cell in rows[0].iter_mut() {
+            *cell = ("\u{2500}".to_string(), dim);
+        }
+        let interval = [0.5, 1.0, 2.0, 5.0, 10.0, 15.0, 30.0, 60.0, 120.0, 300.0]
+            .into_iter()
+            .find(|i| i * cps >= 10.0)
+            .unwrap_or(600.0);
+        let mut tick = (time_at(0) / interval).ceil().max(0.0) * interval;
+        while col(tick) < width as i32 {
+            put(&mut rows[0], col(tick), "\u{252c}", dim);
+            for (i, c) in ruler_label(tick, interval).chars().enumerate() {
+                put(&mut rows[0], col(tick) + 1 + i as i32, &c.to_string(), dim);
+            }
+            tick += interval;
+        }
+
+        let active = app.get_active_line_index();
+        for (i, line) in app.data.lines.iter().enumerate() {
+            let (c0, c1) = (col(line.start), col(line.end).max(col(line.start) + 1));
+            if c1 < 0 || c0 >= width as i32 {
+                continue;
+            }
+            let lane = 1 + (i % 2) * 2;
+            let style = if i == app.scroll_offset && app.manual_scroll {
+                Style::default().fg(theme.accent_text).bg(theme.accent)
+            } else if Some(i) == active {
+                Style::default().fg(theme.accent_text).bg(theme.playing)
+            } else {
+                Style::default().fg(theme.text).bg(theme.muted)
+            };
+            if line.text.trim().is_empty() {
+                for c in c0..c1 {
+                    put(&mut rows[lane], c, "\u{b7}", dim);
+                }
+            } else {
+                for c in c0..c1 {
+                    put(&mut rows[lane], c, " ", style);
+                }
+                // the text stays readable while the line's start is off screen
+                let mut c = c0.max(0);
+                for ch in line.text.chars() {
+                    let symbol = ch.to_string();
+                    let w = Span::raw(symbol.as_str()).width() as i32;
+                    if c + w > c1 {
+                        put(&mut rows[lane], c1 - 1, "\u{2026}", style);
+                        break;
+                    }
+                    put(&mut rows[lane], c, &symbol, style);
+                    c += w.max(1);
+                }
+            }
+            let tick_style = Style::default().fg(if style.bg == Some(theme.accent) {
+                theme.accent
+            } else {
+                theme.dim
+            });
+            for kf in &line.keyframes {
+                put(
+                    &mut rows[lane + 1],
+                    col(line.start + kf.time),
+                    "\u{2575}",
+                    tick_style,
+                );
+            }
+        }
+
+        let playhead = col(app.current_time);
+        if (0..width as i32).contains(&playhead) {
+            let x = playhead as usize;
+            let on = Style::default().fg(theme.highlight);
+            rows[0][x] = ("\u{25bc}".to_string(), on);
+            for row in rows.iter_mut().skip(1) {
+                if row[x].0 == " " && row[x].1.bg.is_none() {
+                    row[x] = ("\u{2502}".to_string(), on);
+                } else if !row[x].0.is_empty() {
+                    row[x].1 = Style::default().fg(theme.accent_text).bg(theme.highlight);
+                }
+            }
+        }
+
+        let lines: Vec<TuiLine> = rows
+            .into_iter()
+            .map(|row| {
+                TuiLine::from(
+                    row.into_iter()
+                        .filter(|(s, _)| !s.is_empty())
+                        .map(|(s, style)| Span::styled(s, style))
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect();
+        let title = format!(
+            " {:.2}s per column | Status: {} ",
+            1.0 / cps,
+            app.server_status
+        );
+        f.render_widget(
+            Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title)),
+            chunks[0],
+        );
+
+        f.render_widget(
+            Paragraph::new(Self::timeline_details(app))
+                .block(Block::default().borders(Borders::ALL)),
+            chunks[1],
+        );
+    }
+
+    // the selected line and the gaps around it
+    fn timeline_details(app: &App) -> Vec<TuiLine<'static>> {
+        let idx = app.scroll_offset;
+        let Some(line) = app.data.lines.get(idx) else {
+            return vec![TuiLine::from(" No lines")];
+        };
+        let theme = &app.config.theme;
+        let gap = |g: Option<f32>| match g {
+            Some(g) if g < 0.0 => format!("overlaps {:.2}s", -g),
+            Some(g) => format!("{:.2}s", g),
+            None => "-".to_string(),
+        };
+        let before = idx
+            .checked_sub(1)
+            .map(|i| line.start - app.data.lines[i].end);
+        let after = app.data.lines.get(idx + 1).map(|l| l.start - line.end);
+        let part = app
+            .data
+            .part_at(idx)
+            .map(|pi| format!(" [{}]", app.data.parts[pi].name))
+            .unwrap_or_default();
+        vec![
+            TuiLine::from(vec![
+                Span::styled(
+                    format!(
+                        " Line {} of {}{}: {:.2}s - {:.2}s ({:.2}s) ",
+                        idx + 1,
+                        app.data.lines.len(),
+                        part,
+                        line.start,
+                        line.end,
+                        line.end - line.start
+                    ),
+                    Style::default().fg(theme.dim),
+                ),
+                Span::styled(line.text.clone(), Style::default().fg(theme.text)),
+            ]),
+            TuiLine::from(Span::styled(
+                format!(
+                    " Gap before: {} | after: {} | {} keyframes",
+                    gap(before),
+                    gap(after),
+                    line.keyframes.len()
+                ),
+                Style::default().fg(theme.dim),
+            )),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tui::harness::{Harness, sample_document};
+    use crossterm::event::KeyCode;
+
+    #[test]
+    fn test_timeline_trims_and_snaps() {
+        let mut data = sample_document();
+        data.lines[2].start = 4.5;
+        let mut h = Harness::new("timeline", &[("song.txt", &data)]);
+        h.key(KeyCode::Enter);
+        h.keys("l");
+        h.advance(0.0);
+        // past the blank first line and "Twinkle twinkle"
+        h.key(KeyCode::Down);
+        h.key(KeyCode::Down);
+        assert_eq!(h.app.scroll_offset, 2);
+
+        // 0.25s a column: the start is within two columns of the line before
+        h.keys("[");
+        assert_eq!(h.app.data.lines[2].start, 4.0);
+        // the keyframes stay put in time
+        assert_eq!(h.app.data.lines[2].keyframes[1].time, 2.0);
+        // and moves off it again one column at a time
+        h.keys("[");
+        assert_eq!(h.app.data.lines[2].start, 3.75);
+
+        h.keys(",,");
+        assert_eq!(h.app.data.lines[2].end, 6.5);
+        h.keys(".");
+        assert_eq!(h.app.data.lines[2].end, 7.0);
+        h.keys("h");
+        let bounds = |h: &Harness| (h.app.data.lines[2].start, h.app.data.lines[2].end);
+        assert_eq!(bounds(&h), (3.5, 6.75));
+        // the end is nearer the next line than the start is to the one before
+        h.keys("l");
+        assert_eq!(bounds(&h), (3.75, 7.0));
+        assert_eq!(
+            h.app.server_status,
+            "Line 3: 3.75s - 7.00s, snapped to line 4"
+        );
+
+        h.keys(" ");
+        h.advance(4.5);
+        h.keys(" ");
+        h.assert_snapshot("timeline");
+    }
+}
@@ -10,4 +10,7 @@ pub enum ViewMode {
     List,
     TextEdit,
     DraftSelector,
+    TapSync,
+    WordSync,
+    Timeline,
 }